@@ -1,11 +1,41 @@
 use eframe::egui::*;
+use ex::{clipboard::Clipboard, history::History};
+use jobs::Jobs;
+use settings::Settings;
+use status::Status;
 use tabs::Tabs;
 
 mod browser;
+mod commands;
+mod compress;
+mod connect;
+mod connections;
+mod delete;
+mod drag;
+mod duplicates;
+mod git;
+mod history;
+mod jobs;
+mod open_with;
+mod patterns;
+mod preview;
+mod recycle;
+mod rename;
+mod settings;
+mod sizes;
+mod status;
 mod tabs;
+mod thumbnails;
+mod types;
+mod usage;
 
 pub struct App {
     tabs: Tabs,
+    settings: Settings,
+    history: History,
+    clipboard: Clipboard,
+    status: Status,
+    jobs: Jobs,
     debug: bool,
 }
 
@@ -13,8 +43,19 @@ impl App {
     pub fn new(cc: &eframe::CreationContext<'_>) -> Self {
         App::set_style(&cc.egui_ctx);
 
+        let settings: Settings = cc
+            .storage
+            .and_then(|storage| eframe::get_value(storage, eframe::APP_KEY))
+            .unwrap_or_default();
+        ex::vfs::save_connections(&settings.connections);
+
         Self {
             tabs: Tabs::new(),
+            settings,
+            history: History::new(),
+            clipboard: Clipboard::new(),
+            status: Status::new(),
+            jobs: Jobs::new(),
             debug: true,
         }
     }
@@ -57,7 +98,15 @@ impl App {
 
 impl eframe::App for App {
     fn update(&mut self, ctx: &Context, _frame: &mut eframe::Frame) {
-        let Self { tabs, debug: _d } = self;
+        let Self {
+            tabs,
+            settings,
+            history,
+            clipboard,
+            status,
+            jobs,
+            debug: _d,
+        } = self;
 
         // Window::new("🔧 Settings")
         //     .open(_d)
@@ -66,12 +115,24 @@ impl eframe::App for App {
         //         ctx.style_ui(ui);
         //     });
 
+        tabs.status(ctx, status, settings);
+
         tabs.side_buttons(ctx);
 
         tabs.header(ctx);
 
-        tabs.quick_access(ctx);
+        tabs.quick_access(ctx, settings, status);
+
+        tabs.preview(ctx);
+
+        tabs.body(ctx, settings, history, clipboard, status, jobs);
+
+        if status.sync(history) {
+            ctx.request_repaint();
+        }
+    }
 
-        tabs.body(ctx);
+    fn save(&mut self, storage: &mut dyn eframe::Storage) {
+        eframe::set_value(storage, eframe::APP_KEY, &self.settings);
     }
 }
@@ -1,55 +1,224 @@
-use eframe::egui::*;
+use super::{
+    commands,
+    compress::CompressDialog,
+    delete::DeleteDialog,
+    drag,
+    duplicates::Finder,
+    git::{self, Git, Ignored},
+    jobs::Jobs,
+    open_with::OpenWith,
+    patterns,
+    recycle::{self, RecycleBin},
+    rename::RenameDialog,
+    settings::Settings,
+    sizes::FolderSizes,
+    status::{Level, Status},
+    thumbnails::Thumbnails,
+    types::Types,
+    usage::{self, Analyzer},
+};
+use eframe::egui::{text::LayoutJob, *};
 use egui_extras::*;
-use ex::Ex;
+use ex::{
+    clipboard::{Clipboard, Mode},
+    compress,
+    external::External,
+    file_type::{self, FileType},
+    filter::Filter,
+    history::{History, Operation},
+    open_with, preview,
+    vfs::{self, Location, Metadata, Vfs},
+    Ex, Listing, Sort, Units,
+};
 use std::{
-    path::{Path, PathBuf},
-    process::Command,
+    path::{Component, Path, PathBuf},
+    sync::{
+        mpsc::{channel, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
 };
 
 pub enum Event {
+    //The name being typed and the folder it goes in.
     NewFile(String, PathBuf),
-    Rename(String, PathBuf),
-    Delete(PathBuf),
+    NewFolder(String, PathBuf),
+    Clip(Mode),
+    Paste,
+    //The archive and whether it gets a folder of its own.
+    Extract(PathBuf, bool),
 }
 
-pub enum Buffer {
-    Copy(PathBuf),
-    Cut(PathBuf),
+#[derive(Clone, Copy, PartialEq)]
+pub enum View {
+    Details,
+    List,
+    Icons,
+    Thumbnails,
+}
+
+impl View {
+    pub const ALL: [View; 4] = [View::Details, View::List, View::Icons, View::Thumbnails];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            View::Details => "Details",
+            View::List => "List",
+            View::Icons => "Icons",
+            View::Thumbnails => "Thumbnails",
+        }
+    }
+}
+
+//A folder on a server being read in the background, since that can take a few seconds.
+struct Loading {
+    receiver: Receiver<ex::Result<Listing>>,
 }
 
 pub struct Browser {
-    pub ex: Ex,
+    pub ex: Ex<Arc<dyn Vfs>>,
+    loading: Option<Loading>,
     pub search: String,
+    pub view: View,
+    pub sizes: FolderSizes,
+    folder_sizes: bool,
+    show_hidden: bool,
+    usage: Option<Analyzer>,
+    duplicates: Option<Finder>,
+    recycle: Option<RecycleBin>,
+    delete: Option<DeleteDialog>,
+    commands: bool,
+    patterns: bool,
+    //Shown in the status bar at the end of the frame.
+    messages: Vec<(Level, String)>,
+    rename: Option<RenameDialog>,
+    compress: Option<CompressDialog>,
+    open_with: Option<OpenWith>,
+    //The last item is the one that was clicked most recently.
+    selection: Vec<PathBuf>,
     event: Option<Event>,
-    buffer: Option<Buffer>,
-    popup: bool,
     new_tab: Option<PathBuf>,
     new_dir: Option<PathBuf>,
+    thumbnails: Thumbnails,
+    git: Git,
+    types: Types,
+    //The listing changed and has to be sorted with what only the browser knows.
+    unsorted: bool,
 }
 
 impl Browser {
     pub fn new() -> Self {
         Self {
             search: String::new(),
+            view: View::Details,
+            sizes: FolderSizes::new(),
+            folder_sizes: false,
+            show_hidden: false,
+            usage: None,
+            duplicates: None,
+            recycle: None,
+            delete: None,
+            commands: false,
+            patterns: false,
+            messages: Vec::new(),
+            rename: None,
+            compress: None,
+            open_with: None,
+            selection: Vec::new(),
             event: None,
-            buffer: None,
-            popup: false,
-            ex: Ex::new(),
+            ex: Ex::disk(),
+            loading: None,
             new_tab: None,
             new_dir: None,
+            thumbnails: Thumbnails::new(),
+            git: Git::new(),
+            types: Types::new(),
+            unsorted: false,
         }
     }
     pub fn set_path(mut self, path: &Path) -> Self {
-        self.ex.set_directory(path, &self.search);
+        self.set_directory(path);
         self
     }
+    //Folders that can't be opened are reported in the status bar. Folders on servers and in
+    //archives are read in the background and the current one stays until they're done.
+    pub fn set_directory(&mut self, path: &Path) {
+        self.git.refresh();
+        let path = self.ex.resolve(path);
+        let slow = vfs::is_remote(&path) || path.ancestors().any(vfs::is_archive);
+        if !slow {
+            self.loading = None;
+            match self.ex.set_directory(&path, &self.search) {
+                Ok(()) => self.unsorted = true,
+                Err(e) => self.messages.push((Level::Error, e.to_string())),
+            }
+            return;
+        }
+
+        let (sender, receiver) = channel();
+        let vfs = self.ex.vfs().clone();
+        thread::spawn(move || {
+            let _ = sender.send(Listing::read(&vfs, &path));
+        });
+        self.loading = Some(Loading { receiver });
+    }
+    fn poll_loading(&mut self) {
+        let Some(loading) = &self.loading else {
+            return;
+        };
+        match loading.receiver.try_recv() {
+            Ok(Ok(listing)) => {
+                self.ex.show(listing, &self.search);
+                self.unsorted = true;
+            }
+            Ok(Err(e)) => self.messages.push((Level::Error, e.to_string())),
+            Err(TryRecvError::Empty) => return,
+            Err(TryRecvError::Disconnected) => (),
+        }
+        self.loading = None;
+    }
+    //Reloads the current folder at the end of the frame.
+    pub fn refresh(&mut self) {
+        self.new_dir = Some(self.ex.current_path().to_path_buf());
+    }
+    pub fn open_trash(&mut self) {
+        self.usage = None;
+        self.recycle = Some(RecycleBin::new(self.ex.current_path()));
+    }
     pub fn previous(&mut self) {
-        self.ex.previous();
+        if let Some(parent) = self.ex.parent() {
+            self.set_directory(&parent);
+        }
     }
     pub fn next(&mut self) {
         //TODO: keep history of paths visited
         // self.ex.next();
     }
+    //Folders inside archives can't be changed.
+    pub fn read_only(&self) -> bool {
+        self.ex.vfs().read_only(self.ex.current_path())
+    }
+    //Sizes, disk usage and duplicates walk the local disk directly, so servers and archives
+    //can't use them.
+    pub fn local(&self) -> bool {
+        self.ex.vfs().local_path(self.ex.current_path()).is_some()
+    }
+    pub fn selected(&self) -> Option<&Path> {
+        //Selections from previously visited folders are stale.
+        self.selection
+            .last()
+            .map(PathBuf::as_path)
+            .filter(|path| path.parent() == Some(self.ex.current_path()))
+    }
+    //Selected files in the order they're listed.
+    pub fn selection(&self) -> Vec<PathBuf> {
+        self.ex
+            .files
+            .iter()
+            .filter(|file| self.selection.contains(file))
+            .cloned()
+            .collect()
+    }
     pub fn title(&self) -> String {
         let file = self.ex.current_file();
         if file.contains(':') {
@@ -59,108 +228,397 @@ impl Browser {
             file
         }
     }
-    pub fn ui(&mut self, ctx: &Context) -> Option<PathBuf> {
-        if self.popup {
-            Window::new("Delete?")
-                .resizable(false)
-                .collapsible(false)
-                .show(ctx, |ui| {
-                    ui.horizontal(|ui| {
-                        if ui.button("Yes").clicked() {
-                            if let Some(Event::Delete(path)) = &self.event {
-                                eprintln!("Deleting path: {path:?}");
-                                ex::delete(path).unwrap();
-                                let path = self.ex.current_path().to_path_buf();
-                                self.ex.set_directory(&path, &self.search);
-                            };
-                            self.popup = false;
-                        };
+    pub fn ui(
+        &mut self,
+        ctx: &Context,
+        settings: &mut Settings,
+        history: &mut History,
+        clipboard: &mut Clipboard,
+        status: &mut Status,
+        jobs: &mut Jobs,
+    ) -> Option<PathBuf> {
+        if self.sizes.update() && self.ex.sort().0 == Sort::Size {
+            self.unsorted = true;
+        }
 
-                        if ui.button("No").clicked() {
-                            self.popup = false;
-                        };
-                    });
-                });
+        if let Some(dialog) = &mut self.delete {
+            if dialog.show(ctx, &mut self.sizes, settings, history) {
+                self.new_dir = Some(self.ex.current_path().to_path_buf());
+            }
+            if let Some(analyzer) = &mut self.usage {
+                for path in dialog.take_deleted() {
+                    analyzer.remove(&path);
+                }
+            }
+            if !dialog.open {
+                self.delete = None;
+            }
+        }
+
+        if let Some(finder) = &mut self.duplicates {
+            if finder.show(ctx, settings.units, history) {
+                self.new_dir = Some(self.ex.current_path().to_path_buf());
+            }
+            if !finder.open {
+                self.duplicates = None;
+            }
+        }
+
+        if let Some(dialog) = &mut self.rename {
+            if dialog.show(ctx, history) {
+                self.new_dir = Some(self.ex.current_path().to_path_buf());
+            }
+            if !dialog.open {
+                self.rename = None;
+            }
         }
 
+        if let Some(dialog) = &mut self.compress {
+            dialog.show(ctx, jobs);
+            if !dialog.open {
+                self.compress = None;
+            }
+        }
+
+        if let Some(dialog) = &mut self.open_with {
+            dialog.show(ctx, settings);
+            if !dialog.open {
+                self.open_with = None;
+            }
+        }
+
+        //Keys only apply to the files when nothing else wants them.
+        if ctx.memory(|m| m.focus().is_none()) && self.rename.is_none() && self.delete.is_none() {
+            let (rename, trash, permanent, copy, cut, paste, hidden) = ctx.input_mut(|i| {
+                (
+                    i.consume_key(Modifiers::NONE, Key::F2),
+                    i.consume_key(Modifiers::NONE, Key::Delete),
+                    i.consume_key(Modifiers::SHIFT, Key::Delete),
+                    i.consume_key(Modifiers::COMMAND, Key::C),
+                    i.consume_key(Modifiers::COMMAND, Key::X),
+                    i.consume_key(Modifiers::COMMAND, Key::V),
+                    i.consume_key(Modifiers::COMMAND, Key::H),
+                )
+            });
+            if hidden {
+                self.show_hidden = !self.show_hidden;
+            }
+            let read_only = self.read_only();
+            if paste && !read_only {
+                self.event = Some(Event::Paste);
+            }
+            let selection = self.selection();
+            //Only copying works inside archives.
+            if !selection.is_empty() && (copy || !read_only) {
+                if copy || cut {
+                    let mode = if cut { Mode::Cut } else { Mode::Copy };
+                    clipboard.set(mode, selection);
+                } else if rename {
+                    self.rename = Some(RenameDialog::new(selection));
+                } else if trash || permanent {
+                    self.delete = Some(DeleteDialog::new(selection, permanent));
+                }
+            }
+        }
+
+        self.new_item(ctx, history);
+        self.poll_loading();
+
         let cd = self.ex.current_path_string();
 
+        let local = self.ex.vfs().local_path(self.ex.current_path());
+        self.git.prepare(ctx, self.ex.current_path(), local);
+        self.update_filter(settings);
+
         let response = CentralPanel::default()
             .show(ctx, |ui| {
                 self.header(ui, &cd);
-                self.center(ui);
+                self.center(ui, settings, history);
             })
             .response;
 
+        let (read_only, local) = (self.read_only(), self.local());
         response.context_menu(|ui| {
-            if ui.button("New File").clicked() {
-                //TODO: new file
-                self.event = Some(Event::NewFile(String::new(), PathBuf::default()));
+            if ui
+                .add_enabled(!read_only, Button::new("New File"))
+                .clicked()
+            {
+                let dir = self.ex.current_path().to_path_buf();
+                self.event = Some(Event::NewFile(String::new(), dir));
                 ui.close_menu();
             };
 
-            if ui.button("Open in Terminal").clicked() {
-                Command::new("wt.exe").args(["-d", &cd]).output().unwrap();
+            if ui
+                .add_enabled(!read_only, Button::new("New Folder"))
+                .clicked()
+            {
+                let dir = self.ex.current_path().to_path_buf();
+                self.event = Some(Event::NewFolder(String::new(), dir));
                 ui.close_menu();
             };
 
-            if ui.button("Open in VSCode").clicked() {
-                Command::new("cmd")
-                    .args(["/c", "code", &cd])
-                    .output()
-                    .unwrap();
+            if ui.add_enabled(!read_only, Button::new("Paste")).clicked() {
+                self.event = Some(Event::Paste);
+                ui.close_menu();
+            };
+
+            ui.separator();
+
+            ui.checkbox(&mut self.folder_sizes, "Calculate folder sizes");
+
+            ui.checkbox(&mut self.show_hidden, "Show hidden files")
+                .on_hover_text("Ctrl+H");
+
+            if ui.button("Edit hidden patterns…").clicked() {
+                self.patterns = true;
+                ui.close_menu();
+            };
+
+            ui.add_enabled_ui(self.git.repo().is_some(), |ui| {
+                ui.menu_button("Ignored files", |ui| {
+                    for ignored in Ignored::ALL {
+                        if ui
+                            .radio_value(&mut settings.ignored, ignored, ignored.label())
+                            .clicked()
+                        {
+                            ui.close_menu();
+                        }
+                    }
+                });
+            });
+
+            if ui
+                .add_enabled(local, Button::new("Analyze disk usage"))
+                .clicked()
+            {
+                self.usage = Some(Analyzer::new(ui.ctx(), self.ex.current_path()));
+                ui.close_menu();
+            };
+
+            if ui
+                .add_enabled(local, Button::new("Find duplicates"))
+                .clicked()
+            {
+                self.duplicates = Some(Finder::new(ui.ctx(), self.ex.current_path()));
+                ui.close_menu();
+            };
+
+            ui.separator();
+
+            self.external(ui, &settings.commands);
+
+            if ui.button("Edit commands…").clicked() {
+                self.commands = true;
                 ui.close_menu();
             };
         });
 
+        commands::show(ctx, &mut self.commands, &mut settings.commands);
+        patterns::show(ctx, &mut self.patterns, &mut settings.hidden);
+
+        match self.event.take() {
+            Some(Event::Clip(mode)) => clipboard.set(mode, self.selection()),
+            Some(Event::Paste) => self.paste(ctx, history, clipboard, jobs),
+            Some(Event::Extract(archive, folder)) => extract(ctx, jobs, archive, folder),
+            event => self.event = event,
+        }
+
         if let Some(dir) = self.new_dir.take() {
-            self.ex.set_directory(&dir, &self.search);
+            self.set_directory(&dir);
+        }
+
+        if !self.messages.is_empty() {
+            ctx.request_repaint();
+        }
+        for (level, text) in self.messages.drain(..) {
+            match level {
+                Level::Info => status.info(text),
+                Level::Error => status.error(text),
+            }
         }
 
         self.new_tab.take()
     }
+    fn new_item(&mut self, ctx: &Context, history: &mut History) {
+        let (title, dir) = match &self.event {
+            Some(Event::NewFile(..)) => ("New File", false),
+            Some(Event::NewFolder(..)) => ("New Folder", true),
+            _ => return,
+        };
+        let Some(Event::NewFile(name, parent) | Event::NewFolder(name, parent)) = &mut self.event
+        else {
+            return;
+        };
+
+        let mut create = false;
+        let mut cancel = false;
+
+        Window::new(title)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                let response = ui.text_edit_singleline(name);
+                response.request_focus();
+                if ui.input(|i| i.key_pressed(Key::Enter)) {
+                    create = true;
+                }
+                if ui.input(|i| i.key_pressed(Key::Escape)) {
+                    cancel = true;
+                }
+
+                ui.horizontal(|ui| {
+                    ui.style_mut().visuals.button_frame = true;
+                    let enabled = !name.trim().is_empty();
+                    if ui.add_enabled(enabled, Button::new("Create")).clicked() {
+                        create = true;
+                    }
+                    if ui.button("Cancel").clicked() {
+                        cancel = true;
+                    }
+                });
+            });
+
+        if create && !name.trim().is_empty() {
+            let path = parent.join(name.trim());
+            let result = if dir {
+                ex::create_dir(&path)
+            } else {
+                ex::create_file(&path)
+            };
+            match result {
+                Ok(_) => history.record(Operation::Create { path, dir }),
+                Err(e) => history.error(format!("{title} {}", path.display()), e),
+            }
+            self.refresh();
+        }
+
+        if create || cancel {
+            self.event = None;
+        }
+    }
+    fn paste(
+        &mut self,
+        ctx: &Context,
+        history: &mut History,
+        clipboard: &mut Clipboard,
+        jobs: &mut Jobs,
+    ) {
+        let Some(files) = clipboard.get() else {
+            return;
+        };
+        let dir = self.ex.current_path().to_path_buf();
+
+        if vfs::is_remote(&dir) || files.paths.iter().any(|path| vfs::is_remote(path)) {
+            let copy = files.mode == Mode::Copy;
+            if !copy {
+                clipboard.clear();
+            }
+            jobs.transfer(ctx, files.paths, dir, copy);
+            return;
+        }
+
+        let mut moved = false;
+
+        for from in files.paths {
+            match files.mode {
+                Mode::Copy => match ex::copy(&from, &dir) {
+                    Ok(to) => history.record(Operation::Copy { from, to }),
+                    Err(e) => history.error(format!("Copy {}", from.display()), e),
+                },
+                Mode::Cut => match ex::cut(&from, &dir) {
+                    Ok(to) => {
+                        history.record(Operation::Move { from, to });
+                        moved = true;
+                    }
+                    Err(e) => history.error(format!("Move {}", from.display()), e),
+                },
+            }
+        }
+
+        //They're not where they were cut from anymore.
+        if moved {
+            clipboard.clear();
+        }
+        self.refresh();
+    }
     //TODO: only show the first 5 paths
     fn header(&mut self, ui: &mut Ui, cd: &str) {
         ui.horizontal(|ui| {
             //Add the frame back just for these buttons
             ui.style_mut().visuals.button_frame = true;
 
-            let splits: Vec<&str> = cd.split('\\').filter(|str| !str.is_empty()).collect();
-
-            for (i, s) in splits.iter().enumerate() {
-                let label = if s.contains(':') {
-                    //TODO: drive name
-                    format!("Drive ({s})")
-                } else {
-                    s.to_string()
-                };
-
+            for (label, path) in crumbs(cd) {
                 let button = ui.button(label);
+                let path = path.as_path();
 
-                let selection = &splits[..i + 1];
-                //join doesn't work if there is only one item
-                let path = if selection.len() == 1 {
-                    format!("{}\\", selection.join(" "))
-                } else {
-                    selection.join("\\")
-                };
-
-                let path = Path::new(&path);
+                drag::target(&button, path);
 
                 if button.clicked() {
-                    self.ex.set_directory(path, &self.search);
+                    self.set_directory(path);
                 }
 
                 if button.middle_clicked() {
                     self.new_tab = Some(path.to_path_buf());
                 }
             }
+
+            //Also keeps the frames coming until the folder has been read.
+            if self.loading.is_some() {
+                ui.spinner();
+            }
+
+            if let Some(repo) = self.git.repo() {
+                if let Some(branch) = &repo.branch {
+                    //The branch glyph is only in the monospace font.
+                    let text = RichText::new(format!("\u{e0a0} {branch}")).monospace();
+                    ui.label(text)
+                        .on_hover_text(format!("Git repository at {}", repo.root.display()));
+                }
+            }
+
+            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                for view in View::ALL.into_iter().rev() {
+                    ui.selectable_value(&mut self.view, view, view.label());
+                }
+            });
         });
     }
-    fn center(&mut self, ui: &mut Ui) {
-        let files = &self.ex.files;
+    fn center(&mut self, ui: &mut Ui, settings: &mut Settings, history: &mut History) {
+        if let Some(bin) = &mut self.recycle {
+            if bin.dir == self.ex.current_path() {
+                match bin.ui(ui) {
+                    Some(recycle::Action::Restored) => self.refresh(),
+                    Some(recycle::Action::Close) => self.recycle = None,
+                    None => (),
+                }
+                return;
+            }
+            self.recycle = None;
+        }
+
+        if let Some(analyzer) = &mut self.usage {
+            match analyzer.ui(ui, self.ex.current_path(), settings.units) {
+                Some(usage::Action::Navigate(dir)) => self.new_dir = Some(dir),
+                Some(usage::Action::Trash(path)) => match ex::delete(&path) {
+                    Ok(_) => {
+                        analyzer.remove(&path);
+                        analyzer.error = None;
+                        history.record(Operation::Trash(path));
+                        self.new_dir = Some(self.ex.current_path().to_path_buf());
+                    }
+                    Err(e) => analyzer.error = Some(e.to_string()),
+                },
+                //The totals are updated once the dialog has deleted it.
+                Some(usage::Action::Delete(path)) => {
+                    self.delete = Some(DeleteDialog::new(vec![path], true));
+                }
+                Some(usage::Action::Close) => self.usage = None,
+                None => (),
+            }
+            return;
+        }
 
-        if files.is_empty() {
+        if self.ex.files.is_empty() {
             if !self.search.is_empty() {
                 ui.centered_and_justified(|ui| ui.label("No results found."));
             } else {
@@ -169,145 +627,691 @@ impl Browser {
             return;
         }
 
+        let current = self.ex.current_path().to_path_buf();
+        if self.types.prepare(ui.ctx(), &current) && self.ex.sort().0 == Sort::Type {
+            self.unsorted = true;
+        }
+        if std::mem::take(&mut self.unsorted) {
+            self.sort();
+        }
+
+        match self.view {
+            View::Details => self.details(ui, settings),
+            View::List => self.grid(ui, Vec2::new(220.0, 22.0), settings),
+            View::Icons => self.grid(ui, Vec2::new(96.0, 96.0), settings),
+            View::Thumbnails => {
+                let dir = self.ex.current_path().to_path_buf();
+                self.thumbnails.prepare(ui.ctx(), &dir);
+                self.grid(ui, Vec2::new(140.0, 170.0), settings);
+            }
+        }
+    }
+    fn details(&mut self, ui: &mut Ui, settings: &mut Settings) {
         ui.style_mut().spacing.button_padding = Vec2::new(0.0, 0.5);
 
-        TableBuilder::new(ui)
+        //Only folders in a repository have a status.
+        let git = self.git.repo().is_some();
+        let mut table = TableBuilder::new(ui)
             .striped(true)
-            .column(Column::auto())
-            .column(Column::auto())
-            .column(Column::auto())
-            .column(Column::auto())
+            .columns(Column::auto(), 4);
+        if git {
+            table = table.column(Column::auto());
+        }
+
+        table
             .header(20.0, |mut header| {
                 header.col(|ui| {
-                    ui.heading("Name");
+                    self.sort_header(ui, "Name", Sort::Name);
                 });
                 header.col(|ui| {
-                    ui.heading("Date modified");
+                    self.sort_header(ui, "Date modified", Sort::Modified);
                 });
                 header.col(|ui| {
-                    ui.heading("Type");
+                    self.sort_header(ui, "Type", Sort::Type);
                 });
                 header.col(|ui| {
-                    ui.heading("Size");
+                    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                        self.sort_header(ui, "Size", Sort::Size)
+                            .on_hover_text("Right click to change units")
+                            .context_menu(|ui| {
+                                let units = &mut settings.units;
+                                ui.radio_value(units, Units::Decimal, "Decimal (KB, MB, GB)");
+                                ui.radio_value(units, Units::Binary, "Binary (KiB, MiB, GiB)");
+                            });
+                    });
                 });
+                if git {
+                    header.col(|ui| {
+                        ui.heading("Status");
+                    });
+                }
             })
             .body(|body| {
                 #[allow(unused)]
-                body.rows(20.0, files.len(), |i, mut row| {
-                    let file = files[i].clone();
+                body.rows(20.0, self.ex.files.len(), |i, mut row| {
+                    let file = self.ex.files[i].clone();
                     if let Some(name) = file.file_name() {
                         let name = name.to_string_lossy().to_string();
 
                         row.col(|ui| {
-                            //is_file() can fail on fails that have bad permissions.
-                            let button = if file.is_file() {
-                                ui.add(Button::new(format!("🖹  {name}")).wrap(false))
-                            } else {
-                                ui.add(Button::new(format!("🗀  {name}")).wrap(false))
-                            };
-
-                            if button.clicked() && file.is_dir() {
-                                self.new_dir = Some(file.clone());
-                            }
+                            let selected = self.selection.contains(&file);
+                            let fill = ui.visuals().selection.bg_fill;
 
-                            if button.double_clicked() && !file.is_dir() {
-                                if let Err(e) = ex::open(&file) {
-                                    //TODO: print to error bar like Onivim
-                                    dbg!(e);
-                                }
-                            }
-
-                            if button.middle_clicked() && file.is_dir() {
-                                //TODO: don't focus this new tab
-                                self.new_tab = Some(file.to_path_buf());
+                            let icon = self.file_type(&file).category.icon();
+                            let mut label = RichText::new(format!("{icon}  {name}"));
+                            if self.dimmed(&file, settings) {
+                                label = label.weak();
                             }
+                            let button = ui.add(
+                                Button::new(label)
+                                    .wrap(false)
+                                    .frame(selected)
+                                    .fill(fill)
+                                    .sense(Sense::click_and_drag()),
+                            );
 
-                            button.context_menu(|ui| {
-                                if ui.button("Copy").clicked() {
-                                    self.buffer = Some(Buffer::Copy(file.to_path_buf()));
-                                    ui.close_menu();
-                                };
-
-                                if ui.button("Cut").clicked() {
-                                    self.buffer = Some(Buffer::Cut(file.to_path_buf()));
-                                    ui.close_menu();
-                                };
-
-                                ui.separator();
-
-                                if self.buffer.is_some() {
-                                    if ui.button("Paste").clicked() {
-                                        match &self.buffer {
-                                            Some(Buffer::Copy(from)) => {
-                                                ex::copy(from, self.ex.current_path())
-                                            }
-                                            Some(Buffer::Cut(from)) => {
-                                                ex::cut(from, self.ex.current_path())
-                                            }
-                                            None => (),
-                                        }
-                                        self.new_dir = Some(self.ex.current_path().to_path_buf());
-                                        ui.close_menu();
-                                    };
-                                    ui.separator();
-                                }
-
-                                if ui.button("Rename").clicked() {
-                                    self.event =
-                                        Some(Event::Rename(name.clone(), file.to_path_buf()));
-                                    ui.close_menu();
-                                };
-
-                                ui.separator();
-
-                                if ui.button("Delete").clicked() {
-                                    self.popup = true;
-                                    self.event = Some(Event::Delete(file.to_path_buf()));
-
-                                    ui.close_menu();
-                                };
-                            });
+                            self.entry(button, &file, settings);
                         });
                     }
 
+                    let metadata = self.ex.metadata(&file).copied();
+
                     row.col(|ui| {
-                        if let Some(date) = ex::last_modified(&file) {
-                            ui.add(Button::new(date).wrap(false));
+                        if let Some(time) = metadata.and_then(|m| m.modified) {
+                            ui.add(Button::new(ex::format_time(time)).wrap(false));
                         }
                     });
 
                     row.col(|ui| {
-                        if file.is_dir() {
-                            ui.add(Button::new("File folder").wrap(false));
-                        } else if let Some(ex) = file.extension() {
-                            let ex = ex.to_string_lossy().to_string();
-                            let unknown = format!(".{ex} file");
-                            let file_type = match ex.as_str() {
-                                "lnk" => "Shortcut",
-                                "zip" => "zip Archive",
-                                "exe" => "Application",
-                                _ => &unknown,
-                            };
-                            ui.add(Button::new(file_type).wrap(false));
-                        } else if let Some(file_name) = file.file_name() {
-                            let file_name = file_name.to_string_lossy().to_string();
-                            if file_name.starts_with('.') {
-                                let file_type = match file_name.as_str() {
-                                    ".gitignore" => "Git Ignore",
-                                    ".gitconfig" => "Git Config",
-                                    _ => "Unknown dot file",
-                                };
-                                ui.add(Button::new(file_type).wrap(false));
-                            }
-                        }
+                        let file_type = self.file_type(&file);
+                        ui.add(Button::new(file_type.name).wrap(false));
                     });
 
                     row.col(|ui| {
-                        if let Some(size) = ex::file_size(&file) {
-                            ui.add(Button::new(size).wrap(false));
+                        let Some(metadata) = metadata else {
+                            return;
+                        };
+                        if metadata.is_dir() {
+                            if self.folder_sizes {
+                                self.sizes.calculate(ui.ctx(), &file);
+                            }
+                            if let Some((size, done)) = self.sizes.get(&file) {
+                                let mut text = ex::format_size(size.bytes, settings.units);
+                                if !done {
+                                    text.push('…');
+                                }
+                                let tooltip = format!(
+                                    "{}\n{} files, {} folders",
+                                    ex::format_bytes(size.bytes),
+                                    size.files,
+                                    size.folders
+                                );
+                                size_cell(ui, text, tooltip);
+                            }
+                        } else {
+                            let text = ex::format_size(metadata.len, settings.units);
+                            size_cell(ui, text, ex::format_bytes(metadata.len));
                         }
                     });
+
+                    if git {
+                        row.col(|ui| {
+                            if let Some(status) = self.git.status(&file) {
+                                let color = git::color(status, ui.visuals());
+                                ui.colored_label(color, status.marker())
+                                    .on_hover_text(status.label());
+                            }
+                        });
+                    }
                 });
             });
     }
+    fn sort_header(&mut self, ui: &mut Ui, label: &str, sort: Sort) -> Response {
+        let (current, ascending) = self.ex.sort();
+        let text = if current != sort {
+            label.to_string()
+        } else if ascending {
+            format!("{label} ⏶")
+        } else {
+            format!("{label} ⏷")
+        };
+
+        let response = ui.add(Label::new(RichText::new(text).heading()).sense(Sense::click()));
+        if response.clicked() {
+            //Clicking the same column again flips the order.
+            let ascending = current != sort || !ascending;
+            self.ex.set_sort(sort, ascending);
+            self.unsorted = true;
+        }
+        response
+    }
+    fn grid(&mut self, ui: &mut Ui, tile: Vec2, settings: &Settings) {
+        let len = self.ex.files.len();
+        let spacing = ui.spacing().item_spacing;
+        let columns = ((ui.available_width() + spacing.x) / (tile.x + spacing.x)).max(1.0) as usize;
+        let rows = len.div_ceil(columns);
+
+        //Only the visible rows are laid out, so only their thumbnails get loaded.
+        ScrollArea::vertical()
+            .auto_shrink([false; 2])
+            .show_rows(ui, tile.y, rows, |ui, range| {
+                for row in range {
+                    ui.horizontal(|ui| {
+                        for i in row * columns..((row + 1) * columns).min(len) {
+                            let file = self.ex.files[i].clone();
+                            self.tile(ui, &file, tile, settings);
+                        }
+                    });
+                }
+            });
+    }
+    fn tile(&mut self, ui: &mut Ui, file: &Path, size: Vec2, settings: &Settings) {
+        let name = file
+            .file_name()
+            .unwrap_or(file.as_os_str())
+            .to_string_lossy()
+            .to_string();
+        let selected = self.selection.iter().any(|path| path == file);
+        let icon = self.file_type(file).category.icon();
+        let dimmed = self.dimmed(file, settings);
+
+        let (rect, response) = ui.allocate_exact_size(size, Sense::click_and_drag());
+
+        if ui.is_rect_visible(rect) {
+            let visuals = ui.style().interact_selectable(&response, selected);
+            let color = if dimmed {
+                ui.visuals().weak_text_color()
+            } else {
+                visuals.text_color()
+            };
+            if selected || response.hovered() {
+                ui.painter().rect_filled(rect, 2.0, visuals.weak_bg_fill);
+            }
+
+            let text = |rows: usize, width: f32| {
+                let font = FontId::proportional(14.0);
+                let mut job = LayoutJob::simple(name.clone(), font, color, width);
+                job.wrap.max_rows = rows;
+                job.wrap.break_anywhere = true;
+                ui.fonts(|f| f.layout_job(job))
+            };
+
+            if self.view == View::List {
+                let icon_rect = Rect::from_min_size(rect.min, Vec2::splat(rect.height()));
+                ui.painter().text(
+                    icon_rect.center(),
+                    Align2::CENTER_CENTER,
+                    icon,
+                    FontId::proportional(16.0),
+                    color,
+                );
+                let galley = text(1, rect.width() - icon_rect.width());
+                let pos = Pos2::new(icon_rect.right(), rect.center().y - galley.size().y / 2.0);
+                ui.painter().galley(pos, galley);
+            } else {
+                let galley = text(2, rect.width() - 4.0);
+                let icon_rect = Rect::from_min_max(
+                    rect.min + Vec2::splat(4.0),
+                    Pos2::new(rect.right() - 4.0, rect.bottom() - galley.size().y - 4.0),
+                );
+
+                let thumbnail = if self.view == View::Thumbnails && preview::is_image(file) {
+                    self.thumbnails.get(ui.ctx(), file)
+                } else {
+                    None
+                };
+
+                if let Some(texture) = thumbnail {
+                    let scale = (icon_rect.size() / texture.size_vec2()).min_elem().min(1.0);
+                    let image =
+                        Rect::from_center_size(icon_rect.center(), texture.size_vec2() * scale);
+                    let uv = Rect::from_min_max(Pos2::ZERO, Pos2::new(1.0, 1.0));
+                    ui.painter().image(texture.id(), image, uv, Color32::WHITE);
+                } else {
+                    ui.painter().text(
+                        icon_rect.center(),
+                        Align2::CENTER_CENTER,
+                        icon,
+                        FontId::proportional(icon_rect.height().min(icon_rect.width()) * 0.6),
+                        color,
+                    );
+                }
+
+                let pos = Pos2::new(rect.center().x - galley.size().x / 2.0, icon_rect.bottom());
+                ui.painter().galley(pos, galley);
+            }
+        }
+
+        self.entry(response.on_hover_text(&name), file, settings);
+    }
+    //Lists the folder again when what counts as hidden has changed.
+    fn update_filter(&mut self, settings: &Settings) {
+        //Hiding ignored files treats them like dotfiles.
+        let gitignore = settings.ignored == Ignored::Hide;
+        let filter = self.ex.filter();
+        if filter.show_hidden != self.show_hidden
+            || filter.gitignore != gitignore
+            || filter.patterns() != settings.hidden
+        {
+            let filter = Filter::new(self.show_hidden, &settings.hidden, gitignore);
+            self.ex.set_filter(filter);
+            self.refresh();
+        }
+    }
+    fn dimmed(&self, file: &Path, settings: &Settings) -> bool {
+        self.ex.hidden(file) || (settings.ignored == Ignored::Dim && self.git.ignored(file))
+    }
+    //Types are sorted by what the column shows, sniffed types get sorted in once they're known.
+    //Folders sort by their total size once it's been counted.
+    fn sort(&mut self) {
+        match self.ex.sort().0 {
+            Sort::Type => {
+                let types = &mut self.types;
+                let vfs = self.ex.vfs().clone();
+                self.ex.sort_by(|file, metadata| {
+                    let is_dir = metadata.is_some_and(Metadata::is_dir);
+                    types.get(file, is_dir, vfs.local_path(file)).name
+                });
+            }
+            Sort::Size => {
+                let sizes = &self.sizes;
+                self.ex.sort_by(|file, metadata| match metadata {
+                    Some(metadata) if metadata.is_dir() => sizes.bytes(file),
+                    metadata => metadata.map(|m| m.len),
+                });
+            }
+            Sort::Name | Sort::Modified => (),
+        }
+    }
+    //Folders in archives and on servers are only known to be folders from their listing.
+    fn file_type(&mut self, file: &Path) -> FileType {
+        let is_dir = self.ex.metadata(file).is_some_and(Metadata::is_dir);
+        let local = self.ex.vfs().local_path(file);
+        self.types.get(file, is_dir, local)
+    }
+    fn select_range(&mut self, file: &Path) {
+        let files = &self.ex.files;
+        let anchor = self
+            .selected()
+            .and_then(|a| files.iter().position(|f| f == a));
+        let Some(anchor) = anchor else {
+            self.selection = vec![file.to_path_buf()];
+            return;
+        };
+        let Some(end) = files.iter().position(|f| f == file) else {
+            return;
+        };
+
+        //Keep the anchor last so the next shift click extends from the same place.
+        let (start, end) = (anchor.min(end), anchor.max(end));
+        let mut selection: Vec<PathBuf> = files[start..=end].to_vec();
+        let anchor = selection.remove(anchor - start);
+        selection.push(anchor);
+        self.selection = selection;
+    }
+    //"Open in ..." for each of the user's commands.
+    fn external(&mut self, ui: &mut Ui, commands: &[External]) {
+        let files = self.selection();
+        let dir = self.ex.current_path().to_path_buf();
+        for command in commands {
+            let enabled = !command.needs_files() || !files.is_empty();
+            let button = Button::new(format!("Open in {}", command.name));
+            if ui
+                .add_enabled(enabled, button)
+                .on_hover_text(&command.command)
+                .clicked()
+            {
+                if let Err(e) = command.spawn(&dir, &files) {
+                    self.messages.push((Level::Error, e.to_string()));
+                }
+                ui.close_menu();
+            }
+        }
+    }
+    //Opens with the chosen program, or whatever the system uses without one.
+    fn open(&mut self, file: &Path, app: Option<&External>) {
+        let result = match app {
+            Some(app) => {
+                let dir = file.parent().unwrap_or(file);
+                app.spawn(dir, &[file.to_path_buf()])
+                    .map_err(|e| e.to_string())
+            }
+            None => ex::open(file).map_err(|e| e.to_string()),
+        };
+        if let Err(e) = result {
+            self.messages.push((Level::Error, e));
+        }
+    }
+    fn open_with(&mut self, ui: &mut Ui, file: &Path, settings: &Settings) {
+        let default = settings.open_with(file);
+        if let Some(app) = default {
+            if ui.button(format!("{} (always)", app.name)).clicked() {
+                self.open(file, Some(app));
+                ui.close_menu();
+            }
+        }
+
+        let mime = file_type::from_path(file).mime;
+        for app in open_with::apps(&mime) {
+            if default == Some(&app) {
+                continue;
+            }
+            if ui.button(&app.name).on_hover_text(&app.command).clicked() {
+                self.open(file, Some(&app));
+                ui.close_menu();
+            }
+        }
+
+        if ui.button("System default").clicked() {
+            self.open(file, None);
+            ui.close_menu();
+        }
+
+        ui.separator();
+
+        if ui.button("Other…").clicked() {
+            self.open_with = Some(OpenWith::new(file.to_path_buf()));
+            ui.close_menu();
+        }
+    }
+    fn entry(&mut self, button: Response, file: &Path, settings: &Settings) {
+        let is_dir = self.ex.metadata(file).is_some_and(Metadata::is_dir);
+        //Archives are browsed like folders.
+        let is_archive = !is_dir && vfs::is_archive(file);
+        let read_only = self.read_only();
+        let local = self.local();
+        if button.drag_started() {
+            let paths = if self.selection.iter().any(|path| path == file) {
+                self.selection()
+            } else {
+                vec![file.to_path_buf()]
+            };
+            drag::start(&button.ctx, paths);
+        }
+
+        if is_dir {
+            drag::target(&button, file);
+        }
+
+        if button.clicked() {
+            let modifiers = button.ctx.input(|i| i.modifiers);
+            if modifiers.command {
+                match self.selection.iter().position(|path| path == file) {
+                    Some(i) => {
+                        self.selection.remove(i);
+                    }
+                    None => self.selection.push(file.to_path_buf()),
+                }
+            } else if modifiers.shift {
+                self.select_range(file);
+            } else {
+                self.selection = vec![file.to_path_buf()];
+                if is_dir || is_archive {
+                    self.new_dir = Some(file.to_path_buf());
+                }
+            }
+        }
+
+        //Right clicking inside the selection acts on all of it.
+        if button.secondary_clicked() && !self.selection.iter().any(|path| path == file) {
+            self.selection = vec![file.to_path_buf()];
+        }
+
+        if button.double_clicked() && !is_dir {
+            self.open(file, settings.open_with(file));
+        }
+
+        if button.middle_clicked() && (is_dir || is_archive) {
+            //TODO: don't focus this new tab
+            self.new_tab = Some(file.to_path_buf());
+        }
+
+        button.context_menu(|ui| {
+            if is_dir && local {
+                if ui.button("Calculate size").clicked() {
+                    self.sizes.calculate(ui.ctx(), file);
+                    ui.close_menu();
+                };
+
+                if ui.button("Analyze disk usage").clicked() {
+                    self.usage = Some(Analyzer::new(ui.ctx(), file));
+                    self.new_dir = Some(file.to_path_buf());
+                    ui.close_menu();
+                };
+
+                if ui.button("Find duplicates").clicked() {
+                    self.duplicates = Some(Finder::new(ui.ctx(), file));
+                    ui.close_menu();
+                };
+
+                ui.separator();
+            }
+
+            if !is_dir {
+                ui.menu_button("Open with…", |ui| self.open_with(ui, file, settings));
+                ui.separator();
+            }
+
+            //Only items on the local disk can be compressed or extracted.
+            if self.ex.vfs().local_path(file).is_some() {
+                if ui.button("Compress to…").clicked() {
+                    let dir = self.ex.current_path().to_path_buf();
+                    self.compress = Some(CompressDialog::new(self.selection(), dir));
+                    ui.close_menu();
+                }
+
+                if is_archive {
+                    if ui.button("Extract here").clicked() {
+                        self.event = Some(Event::Extract(file.to_path_buf(), false));
+                        ui.close_menu();
+                    }
+
+                    if let Some(folder) = vfs::folder(file).as_deref().and_then(Path::file_name) {
+                        let label = format!("Extract to {}", folder.to_string_lossy());
+                        if ui.button(label).clicked() {
+                            self.event = Some(Event::Extract(file.to_path_buf(), true));
+                            ui.close_menu();
+                        }
+                    }
+                }
+
+                ui.separator();
+            }
+
+            if !settings.commands.is_empty() {
+                self.external(ui, &settings.commands);
+                ui.separator();
+            }
+
+            if ui.button("Copy").clicked() {
+                self.event = Some(Event::Clip(Mode::Copy));
+                ui.close_menu();
+            };
+
+            if ui.add_enabled(!read_only, Button::new("Cut")).clicked() {
+                self.event = Some(Event::Clip(Mode::Cut));
+                ui.close_menu();
+            };
+
+            if ui.add_enabled(!read_only, Button::new("Paste")).clicked() {
+                self.event = Some(Event::Paste);
+                ui.close_menu();
+            };
+
+            ui.separator();
+
+            let selection = self.selection();
+            let current = self.ex.current_path();
+            let mut copy_text = |ui: &mut Ui, text: Vec<String>| {
+                let message = match text.len() {
+                    1 => format!("Copied {}", text[0]),
+                    n => format!("Copied {n} lines"),
+                };
+                self.messages.push((Level::Info, message));
+                ui.output_mut(|o| o.copied_text = text.join("\n"));
+                ui.close_menu();
+            };
+
+            if ui.button("Copy path").clicked() {
+                copy_text(
+                    ui,
+                    selection.iter().map(|p| p.display().to_string()).collect(),
+                );
+            };
+
+            if ui.button("Copy name").clicked() {
+                let names = selection.iter().filter_map(|p| p.file_name());
+                copy_text(ui, names.map(|n| n.to_string_lossy().to_string()).collect());
+            };
+
+            //Search results can be in sub folders.
+            if ui.button("Copy as relative path").clicked() {
+                let relative = selection
+                    .iter()
+                    .map(|p| p.strip_prefix(current).unwrap_or(p).display().to_string());
+                copy_text(ui, relative.collect());
+            };
+
+            ui.separator();
+
+            if ui.add_enabled(!read_only, Button::new("Rename")).clicked() {
+                self.rename = Some(RenameDialog::new(self.selection()));
+                ui.close_menu();
+            };
+
+            ui.separator();
+
+            ui.set_enabled(!read_only);
+
+            if ui.button("Delete").clicked() {
+                self.delete = Some(DeleteDialog::new(self.selection(), false));
+                ui.close_menu();
+            };
+
+            if ui
+                .button("Delete Permanently")
+                .on_hover_text("Shift+Delete")
+                .clicked()
+            {
+                self.delete = Some(DeleteDialog::new(self.selection(), true));
+                ui.close_menu();
+            };
+        });
+    }
+}
+
+//Every folder leading up to `cd` and the path to go back to it.
+fn crumbs(cd: &str) -> Vec<(String, PathBuf)> {
+    if let Some(location) = Location::parse(Path::new(cd)) {
+        let mut path = PathBuf::from("/");
+        let mut crumbs = vec![(location.authority.clone(), location.root())];
+        for component in location.path.iter().skip(1) {
+            path.push(component);
+            crumbs.push((
+                component.to_string_lossy().to_string(),
+                location.join(&path),
+            ));
+        }
+        return crumbs;
+    }
+
+    //Archives are folders here, so their contents get crumbs like any other folder.
+    let mut crumbs: Vec<(String, PathBuf)> = Path::new(cd)
+        .ancestors()
+        .filter(|path| !path.as_os_str().is_empty())
+        .map(|path| {
+            let label = match (path.file_name(), path.components().next()) {
+                (Some(name), _) => name.to_string_lossy().to_string(),
+                //TODO: drive name
+                (None, Some(Component::Prefix(prefix))) => {
+                    format!("Drive ({})", prefix.as_os_str().to_string_lossy())
+                }
+                (None, _) => path.to_string_lossy().to_string(),
+            };
+            (label, path.to_path_buf())
+        })
+        .collect();
+    crumbs.reverse();
+    crumbs
+}
+
+//Extracting into a folder of its own creates the folder first, which is what gets undone.
+fn extract(ctx: &Context, jobs: &mut Jobs, archive: PathBuf, folder: bool) {
+    let title = match archive.file_name() {
+        Some(name) => format!("Extract {}", name.to_string_lossy()),
+        None => return,
+    };
+    let to = match (folder, vfs::folder(&archive), archive.parent()) {
+        (true, Some(folder), _) => folder,
+        (false, _, Some(parent)) => parent.to_path_buf(),
+        _ => return,
+    };
+    let created = folder.then(|| Operation::Create {
+        path: to.clone(),
+        dir: true,
+    });
+    jobs.spawn(ctx, title, move |update| {
+        if folder {
+            ex::create_dir(&to)?;
+        }
+        compress::extract(&archive, &to, update)?;
+        Ok(created.into_iter().collect())
+    });
+}
+
+//Sizes are right aligned so the digits line up.
+fn size_cell(ui: &mut Ui, text: String, tooltip: String) {
+    ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+        ui.add(Button::new(text).wrap(false)).on_hover_text(tooltip);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn labels(cd: &str) -> Vec<(String, String)> {
+        crumbs(cd)
+            .into_iter()
+            .map(|(label, path)| (label, path.to_string_lossy().to_string()))
+            .collect()
+    }
+
+    fn expected(crumbs: &[(&str, &str)]) -> Vec<(String, String)> {
+        crumbs
+            .iter()
+            .map(|(label, path)| (label.to_string(), path.to_string()))
+            .collect()
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn local_crumbs() {
+        let crumbs = expected(&[("/", "/"), ("home", "/home"), ("u", "/home/u")]);
+        assert_eq!(labels("/home/u"), crumbs);
+        assert_eq!(labels("/"), expected(&[("/", "/")]));
+
+        let archive = labels("/home/u/photos.zip/2023");
+        assert_eq!(
+            archive[3],
+            ("photos.zip".into(), "/home/u/photos.zip".into())
+        );
+        assert_eq!(
+            archive[4],
+            ("2023".into(), "/home/u/photos.zip/2023".into())
+        );
+    }
+
+    #[test]
+    #[cfg(windows)]
+    fn local_crumbs() {
+        let crumbs = expected(&[("Drive (C:)", "C:\\"), ("Users", "C:\\Users")]);
+        assert_eq!(labels("C:\\Users"), crumbs);
+    }
+
+    #[test]
+    fn remote_crumbs() {
+        let crumbs = expected(&[("me@box", "sftp://me@box/"), ("home", "sftp://me@box/home")]);
+        assert_eq!(labels("sftp://me@box/home"), crumbs);
+    }
 }
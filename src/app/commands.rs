@@ -0,0 +1,49 @@
+use eframe::egui::*;
+use ex::external::{self, External};
+
+//Editor for the "Open in ..." commands.
+pub fn show(ctx: &Context, open: &mut bool, commands: &mut Vec<External>) {
+    Window::new("External commands")
+        .open(open)
+        .default_width(500.0)
+        .show(ctx, |ui| {
+            ui.label(
+                "{dir} current folder, {file} first selected file, \
+                 {files} every selected file, {line} line number",
+            );
+            ui.separator();
+
+            let mut remove = None;
+            Grid::new("commands").num_columns(3).show(ui, |ui| {
+                ui.strong("Name");
+                ui.strong("Command");
+                ui.end_row();
+
+                for (i, command) in commands.iter_mut().enumerate() {
+                    ui.add(TextEdit::singleline(&mut command.name).desired_width(120.0));
+                    ui.add(TextEdit::singleline(&mut command.command).desired_width(300.0));
+                    if ui.button("🗑").on_hover_text("Remove").clicked() {
+                        remove = Some(i);
+                    }
+                    ui.end_row();
+                }
+            });
+            if let Some(i) = remove {
+                commands.remove(i);
+            }
+
+            ui.horizontal(|ui| {
+                ui.style_mut().visuals.button_frame = true;
+                if ui.button("Add").clicked() {
+                    commands.push(External::new("", ""));
+                }
+                if ui
+                    .button("Detect")
+                    .on_hover_text("Replace the list with the installed terminal and editor")
+                    .clicked()
+                {
+                    *commands = external::detect();
+                }
+            });
+        });
+}
@@ -0,0 +1,118 @@
+use super::jobs::Jobs;
+use eframe::egui::*;
+use ex::{
+    compress::{self, Format},
+    history::Operation,
+};
+use std::path::PathBuf;
+
+pub struct CompressDialog {
+    pub open: bool,
+    paths: Vec<PathBuf>,
+    dir: PathBuf,
+    name: String,
+    format: Format,
+    level: u32,
+}
+
+impl CompressDialog {
+    pub fn new(paths: Vec<PathBuf>, dir: PathBuf) -> Self {
+        //A single item names the archive, otherwise it's named after the folder.
+        let name = match paths.as_slice() {
+            [path] => path.file_stem(),
+            _ => dir.file_name(),
+        }
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| String::from("Archive"));
+
+        let format = Format::Zip;
+        Self {
+            open: true,
+            paths,
+            dir,
+            name,
+            format,
+            level: format.default_level(),
+        }
+    }
+    pub fn show(&mut self, ctx: &Context, jobs: &mut Jobs) {
+        let mut open = self.open;
+        let mut start = false;
+
+        let to = self
+            .dir
+            .join(format!("{}.{}", self.name.trim(), self.format.extension()));
+
+        Window::new("Compress")
+            .id(Id::new("compress"))
+            .open(&mut open)
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                Grid::new("compress_options").num_columns(2).show(ui, |ui| {
+                    ui.label("Name");
+                    ui.horizontal(|ui| {
+                        ui.text_edit_singleline(&mut self.name);
+                        ui.weak(format!(".{}", self.format.extension()));
+                    });
+                    ui.end_row();
+
+                    ui.label("Format");
+                    let before = self.format;
+                    ComboBox::from_id_source("compress_format")
+                        .selected_text(self.format.extension())
+                        .show_ui(ui, |ui| {
+                            for format in Format::ALL {
+                                ui.selectable_value(&mut self.format, format, format.extension());
+                            }
+                        });
+                    if self.format != before {
+                        self.level = self.format.default_level();
+                    }
+                    ui.end_row();
+
+                    ui.label("Level");
+                    ui.add(Slider::new(&mut self.level, self.format.levels()))
+                        .on_hover_text("Higher levels are smaller but slower");
+                    ui.end_row();
+                });
+
+                let exists = to.symlink_metadata().is_ok();
+                if exists {
+                    ui.colored_label(
+                        ui.visuals().error_fg_color,
+                        format!("{} already exists", to.display()),
+                    );
+                }
+
+                ui.horizontal(|ui| {
+                    ui.style_mut().visuals.button_frame = true;
+                    let enabled = !self.name.trim().is_empty() && !exists;
+                    if ui.add_enabled(enabled, Button::new("Compress")).clicked() {
+                        start = true;
+                    }
+                    if ui.button("Cancel").clicked() || ui.input(|i| i.key_pressed(Key::Escape)) {
+                        self.open = false;
+                    }
+                });
+            });
+
+        if start {
+            let name = to.file_name().unwrap_or_default().to_string_lossy();
+            let title = format!("Compress {name}");
+            let created = Operation::Create {
+                path: to.clone(),
+                dir: false,
+            };
+            let (paths, format, level) = (self.paths.clone(), self.format, self.level);
+            jobs.spawn(ctx, title, move |update| {
+                compress::compress(&paths, &to, format, level, update)?;
+                Ok(vec![created])
+            });
+            self.open = false;
+        }
+
+        self.open &= open;
+    }
+}
@@ -0,0 +1,127 @@
+use eframe::egui::*;
+use ex::vfs::{self, Location};
+use std::{
+    path::PathBuf,
+    sync::mpsc::{channel, Receiver},
+    thread,
+};
+
+//A server being connected to in the background, since that can take a few seconds.
+pub struct Connecting {
+    receiver: Receiver<ex::Result<PathBuf>>,
+}
+
+impl Connecting {
+    pub fn new(ctx: &Context, path: PathBuf) -> Self {
+        let (sender, receiver) = channel();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            let result = vfs::disk().stat(&path).map(|_| path);
+            let _ = sender.send(result);
+            ctx.request_repaint();
+        });
+        Self { receiver }
+    }
+    //The folder to open once it's connected.
+    pub fn poll(&self) -> Option<ex::Result<PathBuf>> {
+        self.receiver.try_recv().ok()
+    }
+}
+
+//Asks for a server that isn't saved and connects to it.
+pub struct ConnectDialog {
+    pub open: bool,
+    address: String,
+    connecting: Option<Connecting>,
+    error: Option<String>,
+}
+
+impl ConnectDialog {
+    pub fn new() -> Self {
+        Self {
+            open: true,
+            address: String::new(),
+            connecting: None,
+            error: None,
+        }
+    }
+    //`user@host/path` is read as SFTP.
+    fn path(&self) -> Option<PathBuf> {
+        let address = self.address.trim();
+        let path = if address.contains("://") {
+            PathBuf::from(address)
+        } else {
+            PathBuf::from(format!("sftp://{address}"))
+        };
+        let location = Location::parse(&path)?;
+        Some(location.join(&location.path))
+    }
+    //Returns the folder to open once it's connected.
+    pub fn show(&mut self, ctx: &Context) -> Option<PathBuf> {
+        let mut connected = None;
+        if let Some(connecting) = &self.connecting {
+            if let Some(result) = connecting.poll() {
+                self.connecting = None;
+                match result {
+                    Ok(path) => connected = Some(path),
+                    Err(e) => self.error = Some(e.to_string()),
+                }
+            }
+        }
+        if connected.is_some() {
+            self.open = false;
+            return connected;
+        }
+
+        let mut open = self.open;
+        let mut start = false;
+        let path = self.path();
+        let connecting = self.connecting.is_some();
+
+        Window::new("Connect to server")
+            .id(Id::new("connect"))
+            .open(&mut open)
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                let response = ui.add_enabled(
+                    !connecting,
+                    TextEdit::singleline(&mut self.address)
+                        .hint_text("sftp://user@host/path or davs://host/path")
+                        .desired_width(300.0),
+                );
+                response.request_focus();
+                ui.weak("SFTP logs in with your SSH keys, save a connection for passwords");
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+
+                ui.horizontal(|ui| {
+                    ui.style_mut().visuals.button_frame = true;
+                    let enabled = path.is_some() && !connecting;
+                    let enter = ui.input(|i| i.key_pressed(Key::Enter));
+                    if ui.add_enabled(enabled, Button::new("Connect")).clicked()
+                        || (enabled && enter)
+                    {
+                        start = true;
+                    }
+                    if ui.button("Cancel").clicked() || ui.input(|i| i.key_pressed(Key::Escape)) {
+                        self.open = false;
+                    }
+                    if connecting {
+                        ui.spinner();
+                    }
+                });
+            });
+
+        if let (true, Some(path)) = (start, path) {
+            self.connecting = Some(Connecting::new(ctx, path));
+            self.error = None;
+        }
+
+        self.open &= open;
+        None
+    }
+}
@@ -0,0 +1,217 @@
+use super::settings::Settings;
+use eframe::egui::*;
+use ex::vfs::{self, Connection, Protocol, Secret};
+use std::path::PathBuf;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Source {
+    None,
+    Env,
+    File,
+}
+
+impl Source {
+    const ALL: [Source; 3] = [Source::None, Source::Env, Source::File];
+
+    fn label(self) -> &'static str {
+        match self {
+            Source::None => "None",
+            Source::Env => "Environment variable",
+            Source::File => "File",
+        }
+    }
+}
+
+//Adds or edits a saved connection. Passwords and keys are only referenced, never stored.
+pub struct ConnectionDialog {
+    pub open: bool,
+    //The connection being edited, or `None` when adding one.
+    index: Option<usize>,
+    connection: Connection,
+    source: Source,
+    //The variable name or file path, depending on `source`.
+    reference: String,
+}
+
+impl ConnectionDialog {
+    pub fn new(settings: &Settings, index: Option<usize>) -> Self {
+        let connection = index
+            .and_then(|i| settings.connections.get(i))
+            .cloned()
+            .unwrap_or_default();
+        let (source, reference) = match &connection.secret {
+            Secret::None => (Source::None, String::new()),
+            Secret::Env(name) => (Source::Env, name.clone()),
+            Secret::File(path) => (Source::File, path.display().to_string()),
+        };
+        Self {
+            open: true,
+            index,
+            connection,
+            source,
+            reference,
+        }
+    }
+    //Names are used as the host in `scheme://name/path`.
+    fn problem(&self, settings: &Settings) -> Option<&'static str> {
+        let name = self.connection.name.trim();
+        let taken = settings
+            .connections
+            .iter()
+            .enumerate()
+            .any(|(i, connection)| {
+                Some(i) != self.index
+                    && connection.name == name
+                    && connection.protocol == self.connection.protocol
+            });
+        if name.is_empty() {
+            Some("Enter a name")
+        } else if name.contains(['/', '\\', ':', '@']) || name.contains(char::is_whitespace) {
+            Some("Names can't have spaces or / \\ : @")
+        } else if taken {
+            Some("There's already a connection with this name")
+        } else if self.connection.endpoint.trim().is_empty() {
+            Some("Enter an endpoint")
+        } else {
+            None
+        }
+    }
+    pub fn show(&mut self, ctx: &Context, settings: &mut Settings) {
+        let mut open = self.open;
+        let mut save = false;
+        let problem = self.problem(settings);
+
+        let title = match self.index {
+            Some(_) => "Edit connection",
+            None => "Add connection",
+        };
+        Window::new(title)
+            .id(Id::new("connection"))
+            .open(&mut open)
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                let connection = &mut self.connection;
+                Grid::new("connection_options")
+                    .num_columns(2)
+                    .show(ui, |ui| {
+                        ui.label("Name");
+                        ui.text_edit_singleline(&mut connection.name);
+                        ui.end_row();
+
+                        ui.label("Protocol");
+                        ComboBox::from_id_source("connection_protocol")
+                            .selected_text(connection.protocol.label())
+                            .show_ui(ui, |ui| {
+                                for protocol in Protocol::ALL {
+                                    ui.selectable_value(
+                                        &mut connection.protocol,
+                                        protocol,
+                                        protocol.label(),
+                                    );
+                                }
+                            });
+                        ui.end_row();
+
+                        let hint = match connection.protocol {
+                            Protocol::Sftp => "user@host:22",
+                            Protocol::WebDav => "https://host/remote.php/dav/files/user",
+                            Protocol::S3 => "http://localhost:9000",
+                        };
+                        ui.label("Endpoint");
+                        ui.add(
+                            TextEdit::singleline(&mut connection.endpoint)
+                                .hint_text(hint)
+                                .desired_width(300.0),
+                        );
+                        ui.end_row();
+
+                        //SFTP takes the user from the endpoint and logs in with keys.
+                        if connection.protocol != Protocol::Sftp {
+                            if connection.protocol == Protocol::S3 {
+                                ui.label("Region");
+                                ui.add(
+                                    TextEdit::singleline(&mut connection.region)
+                                        .hint_text("us-east-1"),
+                                );
+                                ui.end_row();
+                            }
+
+                            let (user, secret) = match connection.protocol {
+                                Protocol::S3 => ("Access key", "Secret key from"),
+                                _ => ("User", "Password from"),
+                            };
+                            ui.label(user);
+                            ui.text_edit_singleline(&mut connection.user);
+                            ui.end_row();
+
+                            ui.label(secret);
+                            ui.horizontal(|ui| {
+                                ComboBox::from_id_source("connection_secret")
+                                    .selected_text(self.source.label())
+                                    .show_ui(ui, |ui| {
+                                        for source in Source::ALL {
+                                            ui.selectable_value(
+                                                &mut self.source,
+                                                source,
+                                                source.label(),
+                                            );
+                                        }
+                                    });
+                                let hint = match self.source {
+                                    Source::None => None,
+                                    Source::Env => Some("DAV_PASSWORD"),
+                                    Source::File => Some("/run/secrets/minio"),
+                                };
+                                if let Some(hint) = hint {
+                                    ui.add(
+                                        TextEdit::singleline(&mut self.reference).hint_text(hint),
+                                    );
+                                }
+                            });
+                            ui.end_row();
+                        }
+                    });
+
+                if let Some(problem) = problem {
+                    ui.weak(problem);
+                }
+
+                ui.horizontal(|ui| {
+                    ui.style_mut().visuals.button_frame = true;
+                    if ui
+                        .add_enabled(problem.is_none(), Button::new("Save"))
+                        .clicked()
+                    {
+                        save = true;
+                    }
+                    if ui.button("Cancel").clicked() || ui.input(|i| i.key_pressed(Key::Escape)) {
+                        self.open = false;
+                    }
+                });
+            });
+
+        if save {
+            let mut connection = self.connection.clone();
+            connection.name = connection.name.trim().to_string();
+            connection.endpoint = connection.endpoint.trim().to_string();
+            let reference = self.reference.trim();
+            connection.secret = match self.source {
+                _ if connection.protocol == Protocol::Sftp => Secret::None,
+                Source::Env if !reference.is_empty() => Secret::Env(reference.to_string()),
+                Source::File if !reference.is_empty() => Secret::File(PathBuf::from(reference)),
+                _ => Secret::None,
+            };
+
+            match self.index.and_then(|i| settings.connections.get_mut(i)) {
+                Some(saved) => *saved = connection,
+                None => settings.connections.push(connection),
+            }
+            vfs::save_connections(&settings.connections);
+            self.open = false;
+        }
+
+        self.open &= open;
+    }
+}
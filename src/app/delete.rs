@@ -0,0 +1,304 @@
+use super::{settings::Settings, sizes::FolderSizes};
+use eframe::egui::*;
+use ex::{
+    history::{History, Operation},
+    vfs::{self, Vfs},
+    Error,
+};
+use std::{
+    path::PathBuf,
+    sync::mpsc::{channel, Receiver, TryRecvError},
+    thread,
+};
+
+//How many names are listed before the rest are summarised.
+const NAMES: usize = 5;
+
+type Deleted = (PathBuf, ex::Result<()>);
+
+enum State {
+    Confirm,
+    Deleting {
+        receiver: Receiver<Deleted>,
+        done: usize,
+    },
+    Failed,
+}
+
+//The selection's files and folders, read in the background since they may be on a server.
+struct Summary {
+    bytes: u64,
+    //Folders on the local disk, counted with the folder sizes.
+    folders: Vec<PathBuf>,
+    //Folders on servers, walking them could take longer than the deletion.
+    uncounted: usize,
+}
+
+impl Summary {
+    fn read(paths: &[PathBuf]) -> Self {
+        let disk = vfs::disk();
+        let mut summary = Summary {
+            bytes: 0,
+            folders: Vec::new(),
+            uncounted: 0,
+        };
+        for path in paths {
+            match disk.stat(path) {
+                Ok(metadata) if metadata.is_dir() => match disk.local_path(path) {
+                    Some(local) => summary.folders.push(local),
+                    None => summary.uncounted += 1,
+                },
+                Ok(metadata) => summary.bytes += metadata.len,
+                Err(_) => (),
+            }
+        }
+        summary
+    }
+}
+
+pub struct DeleteDialog {
+    pub open: bool,
+    paths: Vec<PathBuf>,
+    summary: Option<Summary>,
+    reading: Option<Receiver<Summary>>,
+    permanent: bool,
+    state: State,
+    errors: Vec<Error>,
+    //Everything that's gone since the last `take_deleted`.
+    deleted: Vec<PathBuf>,
+}
+
+impl DeleteDialog {
+    pub fn new(paths: Vec<PathBuf>, permanent: bool) -> Self {
+        let (sender, receiver) = channel();
+        let selection = paths.clone();
+        thread::spawn(move || {
+            let _ = sender.send(Summary::read(&selection));
+        });
+        Self {
+            open: true,
+            summary: None,
+            reading: Some(receiver),
+            paths,
+            permanent,
+            state: State::Confirm,
+            errors: Vec::new(),
+            deleted: Vec::new(),
+        }
+    }
+    //Returns true if anything was deleted.
+    pub fn show(
+        &mut self,
+        ctx: &Context,
+        sizes: &mut FolderSizes,
+        settings: &mut Settings,
+        history: &mut History,
+    ) -> bool {
+        let mut deleted = false;
+
+        if let Some(receiver) = &self.reading {
+            match receiver.try_recv() {
+                Ok(summary) => {
+                    self.summary = Some(summary);
+                    self.reading = None;
+                }
+                Err(TryRecvError::Empty) => ctx.request_repaint(),
+                Err(TryRecvError::Disconnected) => self.reading = None,
+            }
+        }
+
+        if let State::Deleting { receiver, done } = &mut self.state {
+            let mut finished = false;
+            loop {
+                match receiver.try_recv() {
+                    Ok((path, result)) => {
+                        *done += 1;
+                        match result {
+                            Ok(_) => {
+                                deleted = true;
+                                if !self.permanent {
+                                    history.record(Operation::Trash(path.clone()));
+                                }
+                                self.deleted.push(path);
+                            }
+                            Err(e) => {
+                                let verb = if self.permanent { "Delete" } else { "Trash" };
+                                history.error(format!("{verb} {}", path.display()), &e);
+                                self.errors.push(e);
+                            }
+                        }
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        finished = true;
+                        break;
+                    }
+                }
+            }
+            if finished {
+                if self.errors.is_empty() {
+                    self.open = false;
+                } else {
+                    self.state = State::Failed;
+                }
+            }
+        }
+
+        let title = if self.permanent {
+            "Delete permanently?"
+        } else {
+            "Move to Trash?"
+        };
+
+        let mut answer = None;
+        let mut permanently = Vec::new();
+
+        Window::new(title)
+            .id(Id::new("delete"))
+            .anchor(Align2::CENTER_CENTER, Vec2::ZERO)
+            .resizable(false)
+            .collapsible(false)
+            .show(ctx, |ui| match &self.state {
+                State::Confirm => {
+                    let count = self.paths.len();
+                    let items = if count == 1 { "item" } else { "items" };
+                    if self.permanent {
+                        ui.label(format!("Permanently delete {count} {items}?"));
+                        ui.colored_label(ui.visuals().warn_fg_color, "This can't be undone.");
+                    } else {
+                        ui.label(format!("Move {count} {items} to the Trash?"));
+                    }
+
+                    ui.add_space(4.0);
+                    for path in self.paths.iter().take(NAMES) {
+                        let name = path.file_name().unwrap_or(path.as_os_str());
+                        ui.label(format!("• {}", name.to_string_lossy()));
+                    }
+                    if count > NAMES {
+                        ui.weak(format!("and {} more", count - NAMES));
+                    }
+                    ui.add_space(4.0);
+
+                    //Folders are counted in the background so the dialog shows up right away.
+                    let (mut bytes, mut counting) = (0, self.summary.is_none());
+                    if let Some(summary) = &self.summary {
+                        bytes = summary.bytes;
+                        for path in &summary.folders {
+                            sizes.calculate(ctx, path);
+                            match sizes.get(path) {
+                                Some((size, done)) => {
+                                    bytes += size.bytes;
+                                    counting |= !done;
+                                }
+                                None => counting = true,
+                            }
+                        }
+                    }
+                    let mut total = ex::format_size(bytes, settings.units);
+                    if counting {
+                        total.push('…');
+                    }
+                    let uncounted = self.summary.as_ref().map_or(0, |s| s.uncounted);
+                    if uncounted > 0 {
+                        let folders = if uncounted == 1 { "folder" } else { "folders" };
+                        total.push_str(&format!(", not counting {uncounted} {folders} on servers"));
+                    }
+                    ui.label(format!("Total size: {total}"))
+                        .on_hover_text(ex::format_bytes(bytes));
+
+                    if self.permanent {
+                        ui.checkbox(&mut settings.secure_delete, "Overwrite before deleting")
+                            .on_hover_text(
+                                "Files are filled with zeros first, this has no effect on SSDs",
+                            );
+                    }
+
+                    ui.horizontal(|ui| {
+                        ui.style_mut().visuals.button_frame = true;
+                        if ui.button("Yes").clicked() {
+                            answer = Some(true);
+                        }
+                        if ui.button("No").clicked() || ui.input(|i| i.key_pressed(Key::Escape)) {
+                            answer = Some(false);
+                        }
+                    });
+                }
+                State::Deleting { done, .. } => {
+                    ui.horizontal(|ui| {
+                        ui.spinner();
+                        ui.label(format!("Deleting {done} of {}", self.paths.len()));
+                    });
+                }
+                State::Failed => {
+                    ui.colored_label(
+                        ui.visuals().error_fg_color,
+                        format!("{} items couldn't be deleted:", self.errors.len()),
+                    );
+                    ScrollArea::vertical().max_height(200.0).show(ui, |ui| {
+                        for e in &self.errors {
+                            ui.label(e.to_string());
+                        }
+                    });
+                    ui.style_mut().visuals.button_frame = true;
+                    //Drives without a trash, like network shares, can only delete for good.
+                    let untrashable: Vec<PathBuf> = self
+                        .errors
+                        .iter()
+                        .filter(|e| matches!(e, Error::TrashUnavailable { .. }))
+                        .map(|e| e.path().to_path_buf())
+                        .collect();
+                    if !untrashable.is_empty()
+                        && ui
+                            .button("Delete permanently")
+                            .on_hover_text("These items can't be moved to the Trash")
+                            .clicked()
+                    {
+                        permanently = untrashable;
+                    }
+                    if ui.button("Close").clicked() {
+                        answer = Some(false);
+                    }
+                }
+            });
+
+        match answer {
+            Some(true) => self.state = self.delete(ctx, settings.secure_delete),
+            Some(false) => self.open = false,
+            None => (),
+        }
+
+        if !permanently.is_empty() {
+            *self = DeleteDialog::new(permanently, true);
+        }
+
+        deleted
+    }
+    pub fn take_deleted(&mut self) -> Vec<PathBuf> {
+        std::mem::take(&mut self.deleted)
+    }
+    fn delete(&self, ctx: &Context, secure: bool) -> State {
+        let (sender, receiver) = channel();
+        let paths = self.paths.clone();
+        let permanent = self.permanent;
+        let ctx = ctx.clone();
+
+        thread::spawn(move || {
+            for path in paths {
+                let result = if permanent {
+                    ex::delete_permanently(&path, secure)
+                } else {
+                    ex::delete(&path)
+                };
+                if sender.send((path, result)).is_err() {
+                    break;
+                }
+                ctx.request_repaint();
+            }
+            //The dialog finishes once it sees the channel close.
+            drop(sender);
+            ctx.request_repaint();
+        });
+
+        State::Deleting { receiver, done: 0 }
+    }
+}
@@ -0,0 +1,147 @@
+use super::jobs::Jobs;
+use eframe::egui::*;
+use ex::{
+    history::{History, Operation},
+    vfs,
+};
+use std::path::{Path, PathBuf};
+
+//egui has no drag and drop payloads yet, so the dragged files are kept in its temporary
+//storage where every panel can see them.
+fn payload_id() -> Id {
+    Id::new("drag_files")
+}
+
+fn drop_id() -> Id {
+    Id::new("drop_files")
+}
+
+#[derive(Clone)]
+struct Drop {
+    paths: Vec<PathBuf>,
+    dir: PathBuf,
+    copy: bool,
+}
+
+pub fn start(ctx: &Context, paths: Vec<PathBuf>) {
+    ctx.data_mut(|d| d.insert_temp(payload_id(), paths));
+}
+
+fn payload(ctx: &Context) -> Option<Vec<PathBuf>> {
+    ctx.data_mut(|d| d.get_temp::<Vec<PathBuf>>(payload_id()))
+}
+
+//Lets files be dropped on `response` to move them into `dir`, holding Ctrl copies instead.
+pub fn target(response: &Response, dir: &Path) {
+    let ctx = &response.ctx;
+    let Some(paths) = payload(ctx) else {
+        return;
+    };
+    let hovered = ctx
+        .input(|i| i.pointer.hover_pos())
+        .is_some_and(|pos| response.rect.contains(pos));
+    //Folders can't go inside themselves.
+    if !hovered || paths.iter().any(|path| dir.starts_with(path)) {
+        return;
+    }
+
+    let stroke = ctx.style().visuals.selection.stroke;
+    ctx.layer_painter(response.layer_id)
+        .rect_stroke(response.rect, 2.0, stroke);
+
+    let (released, modifiers) = ctx.input(|i| (i.pointer.any_released(), i.modifiers));
+    if released {
+        let drop = Drop {
+            paths,
+            dir: dir.to_path_buf(),
+            copy: modifiers.command && !modifiers.shift,
+        };
+        ctx.data_mut(|d| d.insert_temp(drop_id(), drop));
+    }
+}
+
+//Shows what's being dragged and runs the drop once the mouse is let go.
+//Returns true if any files were moved or copied.
+pub fn finish(ctx: &Context, history: &mut History, jobs: &mut Jobs) -> bool {
+    let Some(paths) = payload(ctx) else {
+        return false;
+    };
+
+    if !ctx.input(|i| i.pointer.any_released()) {
+        let copy = ctx.input(|i| i.modifiers.command && !i.modifiers.shift);
+        let text = match (paths.len(), copy) {
+            (1, true) => String::from("Copy 1 item"),
+            (n, true) => format!("Copy {n} items"),
+            (1, false) => String::from("Move 1 item"),
+            (n, false) => format!("Move {n} items"),
+        };
+        show_tooltip_at_pointer(ctx, payload_id(), |ui| ui.label(text));
+        return false;
+    }
+
+    ctx.data_mut(|d| d.remove::<Vec<PathBuf>>(payload_id()));
+    let Some(drop) = ctx.data_mut(|d| d.get_temp::<Drop>(drop_id())) else {
+        return false;
+    };
+    ctx.data_mut(|d| d.remove::<Drop>(drop_id()));
+
+    transfer(ctx, drop.paths, &drop.dir, drop.copy, history, jobs)
+}
+
+//Files dragged in from other applications are always copied.
+pub fn dropped_files(ctx: &Context, dir: &Path, history: &mut History, jobs: &mut Jobs) -> bool {
+    let (hovering, dropped) = ctx.input(|i| {
+        let dropped: Vec<PathBuf> = i
+            .raw
+            .dropped_files
+            .iter()
+            .filter_map(|file| file.path.clone())
+            .collect();
+        (!i.raw.hovered_files.is_empty(), dropped)
+    });
+
+    if hovering {
+        let text = format!("Copy to {}", dir.display());
+        show_tooltip_at_pointer(ctx, drop_id(), |ui| ui.label(text));
+    }
+
+    !dropped.is_empty() && transfer(ctx, dropped, dir, true, history, jobs)
+}
+
+fn transfer(
+    ctx: &Context,
+    mut paths: Vec<PathBuf>,
+    dir: &Path,
+    copy: bool,
+    history: &mut History,
+    jobs: &mut Jobs,
+) -> bool {
+    //Dropping a file back where it came from does nothing.
+    paths.retain(|path| path.parent() != Some(dir));
+    if paths.is_empty() {
+        return false;
+    }
+
+    //Servers are slow, so those are left to a job which refreshes once it's done.
+    if vfs::is_remote(dir) || paths.iter().any(|path| vfs::is_remote(path)) {
+        jobs.transfer(ctx, paths, dir.to_path_buf(), copy);
+        return false;
+    }
+
+    let mut changed = false;
+    for from in paths {
+        if copy {
+            match ex::copy(&from, dir) {
+                Ok(to) => history.record(Operation::Copy { from, to }),
+                Err(e) => history.error(format!("Copy {}", from.display()), e),
+            }
+        } else {
+            match ex::cut(&from, dir) {
+                Ok(to) => history.record(Operation::Move { from, to }),
+                Err(e) => history.error(format!("Move {}", from.display()), e),
+            }
+        }
+        changed = true;
+    }
+    changed
+}
@@ -0,0 +1,406 @@
+use eframe::egui::*;
+use egui_extras::*;
+use ex::{
+    duplicates::{self, Duplicate, Group, Keep, Progress},
+    history::{History, Operation},
+    Units,
+};
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver, TryRecvError},
+        Arc,
+    },
+    thread,
+    time::{Duration, Instant},
+};
+
+enum Message {
+    Progress(Progress),
+    Done(Option<Vec<Group>>),
+}
+
+enum State {
+    Running {
+        progress: Progress,
+        cancel: Arc<AtomicBool>,
+        receiver: Receiver<Message>,
+    },
+    //Files are trashed or linked one at a time in the background.
+    Replacing {
+        groups: Vec<Group>,
+        receiver: Receiver<Replaced>,
+        done: usize,
+        total: usize,
+        //The group and position of every file that was replaced.
+        handled: Vec<(usize, usize)>,
+        trash: bool,
+    },
+    Done(Vec<Group>),
+}
+
+//A selected file and the copy it's replaced by.
+struct Task {
+    group: usize,
+    file: usize,
+    original: Duplicate,
+    duplicate: Duplicate,
+    size: u64,
+}
+
+type Replaced = (usize, usize, Result<(), String>);
+
+#[derive(Clone, Copy)]
+enum Replace {
+    Trash,
+    HardLink,
+}
+
+pub struct Finder {
+    pub open: bool,
+    root: PathBuf,
+    state: State,
+    selected: Vec<Vec<bool>>,
+    errors: Vec<String>,
+}
+
+impl Finder {
+    pub fn new(ctx: &Context, root: &Path) -> Self {
+        Self {
+            open: true,
+            root: root.to_path_buf(),
+            state: find(ctx, root),
+            selected: Vec::new(),
+            errors: Vec::new(),
+        }
+    }
+    //Returns true if any files were changed.
+    pub fn show(&mut self, ctx: &Context, units: Units, history: &mut History) -> bool {
+        if let State::Running {
+            progress, receiver, ..
+        } = &mut self.state
+        {
+            let mut done = None;
+            while let Ok(message) = receiver.try_recv() {
+                match message {
+                    Message::Progress(p) => *progress = p,
+                    Message::Done(groups) => done = Some(groups),
+                }
+            }
+            match done {
+                Some(Some(groups)) => {
+                    self.selected = groups.iter().map(|g| vec![false; g.files.len()]).collect();
+                    self.state = State::Done(groups);
+                }
+                Some(None) => self.open = false,
+                None => (),
+            }
+        }
+
+        let mut changed = false;
+        if let State::Replacing {
+            groups,
+            receiver,
+            done,
+            handled,
+            trash,
+            ..
+        } = &mut self.state
+        {
+            let mut finished = false;
+            loop {
+                match receiver.try_recv() {
+                    Ok((g, f, result)) => {
+                        *done += 1;
+                        let path = &groups[g].files[f].path;
+                        match result {
+                            Ok(()) => {
+                                if *trash {
+                                    history.record(Operation::Trash(path.clone()));
+                                }
+                                handled.push((g, f));
+                                changed = true;
+                            }
+                            Err(e) => self.errors.push(format!("{}: {e}", path.display())),
+                        }
+                    }
+                    Err(TryRecvError::Empty) => break,
+                    Err(TryRecvError::Disconnected) => {
+                        finished = true;
+                        break;
+                    }
+                }
+            }
+
+            if finished {
+                let mut groups = std::mem::take(groups);
+                //Links don't take up any more space so they're no longer duplicates either.
+                handled.sort_unstable();
+                for &(g, f) in handled.iter().rev() {
+                    groups[g].files.remove(f);
+                }
+                groups.retain(|group| group.files.len() > 1);
+                self.selected = groups.iter().map(|g| vec![false; g.files.len()]).collect();
+                self.state = State::Done(groups);
+            }
+        }
+
+        let mut open = self.open;
+        let mut replace = None;
+
+        Window::new(format!("Duplicate files in {}", self.root.display()))
+            .id(Id::new("duplicates"))
+            .open(&mut open)
+            .default_size([800.0, 500.0])
+            .show(ctx, |ui| {
+                let groups = match &self.state {
+                    State::Running { progress, .. } => {
+                        match progress {
+                            Progress::Scanning { files } => {
+                                ui.horizontal(|ui| {
+                                    ui.spinner();
+                                    ui.label(format!("Scanning... {files} files"));
+                                });
+                            }
+                            Progress::Hashing { done, total } => {
+                                ui.label(format!("Comparing {done} of {total} files"));
+                                let fraction = *done as f32 / (*total).max(1) as f32;
+                                ui.add(ProgressBar::new(fraction).show_percentage());
+                            }
+                        }
+                        return;
+                    }
+                    State::Replacing { done, total, .. } => {
+                        ui.label(format!("Replacing {done} of {total} files"));
+                        let fraction = *done as f32 / (*total).max(1) as f32;
+                        ui.add(ProgressBar::new(fraction).show_percentage());
+                        return;
+                    }
+                    State::Done(groups) => groups,
+                };
+
+                if groups.is_empty() {
+                    ui.label("No duplicates found.");
+                    return;
+                }
+
+                let wasted: u64 = groups.iter().map(Group::wasted).sum();
+                ui.label(format!(
+                    "{} groups of duplicates, {} can be freed.",
+                    groups.len(),
+                    ex::format_size(wasted, units)
+                ));
+
+                ui.horizontal(|ui| {
+                    ui.label("Select all but:");
+                    for (keep, label) in [
+                        (Keep::Newest, "Newest"),
+                        (Keep::Oldest, "Oldest"),
+                        (Keep::ShortestPath, "Shortest path"),
+                    ] {
+                        if ui.button(label).clicked() {
+                            for (group, selected) in groups.iter().zip(&mut self.selected) {
+                                let keep = group.keep(keep);
+                                for (i, s) in selected.iter_mut().enumerate() {
+                                    *s = i != keep;
+                                }
+                            }
+                        }
+                    }
+                    if ui.button("Clear").clicked() {
+                        for selected in &mut self.selected {
+                            selected.fill(false);
+                        }
+                    }
+                });
+
+                ui.horizontal(|ui| {
+                    let count: usize = self.selected.iter().flatten().filter(|s| **s).count();
+                    let enabled = count > 0;
+                    if ui
+                        .add_enabled(enabled, Button::new(format!("Move {count} to Trash")))
+                        .clicked()
+                    {
+                        replace = Some(Replace::Trash);
+                    }
+                    if ui
+                        .add_enabled(enabled, Button::new("Replace with hard links"))
+                        .on_hover_text("Selected files become links to a copy that isn't selected")
+                        .clicked()
+                    {
+                        replace = Some(Replace::HardLink);
+                    }
+                });
+
+                if !self.errors.is_empty() {
+                    CollapsingHeader::new(format!("{} errors", self.errors.len()))
+                        .id_source("duplicate_errors")
+                        .show(ui, |ui| {
+                            for e in &self.errors {
+                                ui.colored_label(ui.visuals().error_fg_color, e);
+                            }
+                        });
+                }
+
+                ui.separator();
+
+                //Every group gets a heading row followed by one row per file.
+                let rows: Vec<(usize, Option<usize>)> = groups
+                    .iter()
+                    .enumerate()
+                    .flat_map(|(g, group)| {
+                        std::iter::once((g, None))
+                            .chain((0..group.files.len()).map(move |f| (g, Some(f))))
+                    })
+                    .collect();
+
+                TableBuilder::new(ui)
+                    .striped(true)
+                    .column(Column::auto())
+                    .column(Column::remainder().clip(true))
+                    .column(Column::auto())
+                    .column(Column::auto())
+                    .body(|body| {
+                        body.rows(20.0, rows.len(), |i, mut row| {
+                            let (g, f) = rows[i];
+                            let group = &groups[g];
+                            match f {
+                                None => {
+                                    row.col(|_| ());
+                                    row.col(|ui| {
+                                        ui.strong(format!(
+                                            "{} copies of {}",
+                                            group.files.len(),
+                                            ex::format_size(group.size, units)
+                                        ));
+                                    });
+                                    row.col(|_| ());
+                                    row.col(|_| ());
+                                }
+                                Some(f) => {
+                                    let file = &group.files[f];
+                                    row.col(|ui| {
+                                        ui.checkbox(&mut self.selected[g][f], "");
+                                    });
+                                    row.col(|ui| {
+                                        ui.add(
+                                            Label::new(file.path.display().to_string()).wrap(false),
+                                        );
+                                    });
+                                    row.col(|ui| {
+                                        if let Some(modified) = file.modified {
+                                            ui.label(ex::format_time(modified));
+                                        }
+                                    });
+                                    row.col(|ui| {
+                                        ui.label(ex::format_size(group.size, units));
+                                    });
+                                }
+                            }
+                        });
+                    });
+            });
+
+        self.open = open;
+        if !self.open {
+            if let State::Running { cancel, .. } = &self.state {
+                cancel.store(true, Ordering::Relaxed);
+            }
+        }
+
+        if let Some(replace) = replace {
+            self.replace(ctx, replace);
+        }
+        changed
+    }
+    //Trashing goes through the desktop and linking hashes both files again, so it's done on
+    //a thread of its own.
+    fn replace(&mut self, ctx: &Context, replace: Replace) {
+        let State::Done(groups) = std::mem::replace(&mut self.state, State::Done(Vec::new()))
+        else {
+            return;
+        };
+
+        let mut tasks = Vec::new();
+        for (g, (group, selected)) in groups.iter().zip(&self.selected).enumerate() {
+            let Some(keep) = selected.iter().position(|s| !s) else {
+                self.errors.push(format!(
+                    "Skipped {}, every copy was selected.",
+                    group.files[0].path.display()
+                ));
+                continue;
+            };
+            for (f, file) in group.files.iter().enumerate() {
+                if selected[f] {
+                    tasks.push(Task {
+                        group: g,
+                        file: f,
+                        original: group.files[keep].clone(),
+                        duplicate: file.clone(),
+                        size: group.size,
+                    });
+                }
+            }
+        }
+
+        let (sender, receiver) = channel();
+        let total = tasks.len();
+        let ctx = ctx.clone();
+        thread::spawn(move || {
+            for task in tasks {
+                let result = match replace {
+                    Replace::Trash => ex::delete(&task.duplicate.path).map_err(|e| e.to_string()),
+                    Replace::HardLink => {
+                        duplicates::hard_link(&task.original, &task.duplicate, task.size)
+                            .map_err(|e| e.to_string())
+                    }
+                };
+                if sender.send((task.group, task.file, result)).is_err() {
+                    break;
+                }
+                ctx.request_repaint();
+            }
+            //The dialog finds out it's finished once the sender is gone.
+            drop(sender);
+            ctx.request_repaint();
+        });
+
+        self.state = State::Replacing {
+            groups,
+            receiver,
+            done: 0,
+            total,
+            handled: Vec::new(),
+            trash: matches!(replace, Replace::Trash),
+        };
+    }
+}
+
+fn find(ctx: &Context, root: &Path) -> State {
+    let (sender, receiver) = channel();
+    let cancel = Arc::new(AtomicBool::new(false));
+
+    let ctx = ctx.clone();
+    let root = root.to_path_buf();
+    let stop = cancel.clone();
+    thread::spawn(move || {
+        let mut last = Instant::now();
+        let groups = duplicates::find(&root, &stop, |progress| {
+            //Hashing reports every file, the ui doesn't need to hear about all of them.
+            if last.elapsed() > Duration::from_millis(50) {
+                let _ = sender.send(Message::Progress(progress));
+                ctx.request_repaint();
+                last = Instant::now();
+            }
+        });
+        let _ = sender.send(Message::Done(groups));
+        ctx.request_repaint();
+    });
+
+    State::Running {
+        progress: Progress::Scanning { files: 0 },
+        cancel,
+        receiver,
+    }
+}
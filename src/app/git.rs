@@ -0,0 +1,102 @@
+use eframe::egui::{Color32, Context, Visuals};
+use ex::git::{Repo, Status};
+use serde::{Deserialize, Serialize};
+use std::{
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver},
+    thread,
+};
+
+//What to do with files the repository ignores.
+#[derive(Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum Ignored {
+    Show,
+    #[default]
+    Dim,
+    Hide,
+}
+
+impl Ignored {
+    pub const ALL: [Ignored; 3] = [Ignored::Show, Ignored::Dim, Ignored::Hide];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Ignored::Show => "Show",
+            Ignored::Dim => "Dim",
+            Ignored::Hide => "Hide",
+        }
+    }
+}
+
+//The git status of the current folder, read in the background whenever it changes.
+pub struct Git {
+    dir: PathBuf,
+    repo: Option<Repo>,
+    stale: bool,
+    receiver: Option<Receiver<Option<Repo>>>,
+}
+
+impl Git {
+    pub fn new() -> Self {
+        Self {
+            dir: PathBuf::new(),
+            repo: None,
+            stale: false,
+            receiver: None,
+        }
+    }
+    //Called once per frame. `local` is where the folder is on disk, servers and archives have
+    //no repository.
+    pub fn prepare(&mut self, ctx: &Context, dir: &Path, local: Option<PathBuf>) {
+        if self.dir != dir {
+            self.dir = dir.to_path_buf();
+            self.repo = None;
+            self.stale = true;
+        }
+
+        if std::mem::take(&mut self.stale) {
+            self.receiver = local.map(|local| {
+                let (sender, receiver) = channel();
+                let ctx = ctx.clone();
+                thread::spawn(move || {
+                    let _ = sender.send(Repo::open(&local));
+                    ctx.request_repaint();
+                });
+                receiver
+            });
+            if self.receiver.is_none() {
+                self.repo = None;
+            }
+        }
+
+        if let Some(receiver) = &self.receiver {
+            if let Ok(repo) = receiver.try_recv() {
+                self.repo = repo;
+                self.receiver = None;
+            }
+        }
+    }
+    //Reads the status again, the old one is kept until then so nothing flickers.
+    pub fn refresh(&mut self) {
+        self.stale = true;
+    }
+    pub fn repo(&self) -> Option<&Repo> {
+        self.repo.as_ref()
+    }
+    pub fn status(&self, path: &Path) -> Option<Status> {
+        self.repo.as_ref()?.status(path)
+    }
+    pub fn ignored(&self, path: &Path) -> bool {
+        self.status(path) == Some(Status::Ignored)
+    }
+}
+
+pub fn color(status: Status, visuals: &Visuals) -> Color32 {
+    match status {
+        Status::Ignored => visuals.weak_text_color(),
+        Status::Untracked => Color32::from_rgb(90, 160, 220),
+        Status::Staged => Color32::from_rgb(90, 180, 90),
+        Status::Modified => visuals.warn_fg_color,
+        Status::Conflicted => visuals.error_fg_color,
+    }
+}
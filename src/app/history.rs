@@ -0,0 +1,66 @@
+use super::jobs::Jobs;
+use eframe::egui::*;
+use ex::history::{Action, History};
+
+pub struct HistoryPanel {
+    pub open: bool,
+}
+
+impl HistoryPanel {
+    pub fn new() -> Self {
+        Self { open: false }
+    }
+    pub fn ui(&mut self, ctx: &Context, history: &mut History, jobs: &mut Jobs) {
+        Window::new("History")
+            .open(&mut self.open)
+            .default_size([450.0, 350.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.style_mut().visuals.button_frame = true;
+
+                    let undo = history.next_undo().map(|op| op.to_string());
+                    let button = ui.add_enabled(undo.is_some(), Button::new("Undo"));
+                    if button.on_hover_text(undo.unwrap_or_default()).clicked() {
+                        jobs.undo(ctx, history);
+                    }
+
+                    let redo = history.next_redo().map(|op| op.to_string());
+                    let button = ui.add_enabled(redo.is_some(), Button::new("Redo"));
+                    if button.on_hover_text(redo.unwrap_or_default()).clicked() {
+                        jobs.redo(ctx, history);
+                    }
+                });
+
+                ui.separator();
+
+                if history.log().is_empty() {
+                    ui.label("Nothing has happened yet.");
+                    return;
+                }
+
+                ScrollArea::vertical()
+                    .auto_shrink([false; 2])
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        Grid::new("history").striped(true).show(ui, |ui| {
+                            for entry in history.log() {
+                                ui.weak(ex::format_time(entry.time));
+
+                                let action = match entry.action {
+                                    Action::Did => "",
+                                    Action::Undid => "Undo: ",
+                                    Action::Redid => "Redo: ",
+                                };
+                                ui.label(format!("{action}{}", entry.description));
+
+                                match &entry.error {
+                                    Some(e) => ui.colored_label(ui.visuals().error_fg_color, e),
+                                    None => ui.label("✔"),
+                                };
+                                ui.end_row();
+                            }
+                        });
+                    });
+            });
+    }
+}
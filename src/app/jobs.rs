@@ -0,0 +1,237 @@
+use super::status::Status;
+use eframe::egui::*;
+use ex::{
+    compress::Update,
+    history::{History, Operation},
+};
+use std::{
+    io,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver, TryRecvError},
+    thread,
+};
+
+//What the job did, recorded so it can be undone.
+type Done = Vec<Operation>;
+
+enum Message {
+    Update(Update),
+    Finished(ex::Result<Done>),
+}
+
+struct Job {
+    title: String,
+    //Undoing or redoing this, it goes back to the history when the job is done.
+    step: Option<(Step, Operation)>,
+    receiver: Receiver<Message>,
+    total: u64,
+    done: u64,
+    current: Option<PathBuf>,
+}
+
+#[derive(Clone, Copy)]
+enum Step {
+    Undo,
+    Redo,
+}
+
+//Long running work like compressing, extracting and copying to servers, shown in the corner until
+//it's done.
+pub struct Jobs {
+    jobs: Vec<Job>,
+}
+
+impl Jobs {
+    pub fn new() -> Self {
+        Self { jobs: Vec::new() }
+    }
+    pub fn spawn(
+        &mut self,
+        ctx: &Context,
+        title: String,
+        work: impl FnOnce(&mut dyn FnMut(Update)) -> ex::Result<Done> + Send + 'static,
+    ) {
+        self.start(ctx, title, None, work);
+    }
+    //Redoing a copy copies everything again, so neither happens on the ui thread. Returns false
+    //if there was nothing to undo.
+    pub fn undo(&mut self, ctx: &Context, history: &mut History) -> bool {
+        let Some(operation) = history.take_undo() else {
+            return false;
+        };
+        self.step(ctx, Step::Undo, operation);
+        true
+    }
+    pub fn redo(&mut self, ctx: &Context, history: &mut History) -> bool {
+        let Some(operation) = history.take_redo() else {
+            return false;
+        };
+        self.step(ctx, Step::Redo, operation);
+        true
+    }
+    fn step(&mut self, ctx: &Context, step: Step, operation: Operation) {
+        let verb = match step {
+            Step::Undo => "Undo",
+            Step::Redo => "Redo",
+        };
+        let title = format!("{verb}: {operation}");
+        let work = operation.clone();
+        self.start(ctx, title, Some((step, operation)), move |_| {
+            match step {
+                Step::Undo => work.undo()?,
+                Step::Redo => work.redo()?,
+            }
+            Ok(Vec::new())
+        });
+    }
+    fn start(
+        &mut self,
+        ctx: &Context,
+        title: String,
+        step: Option<(Step, Operation)>,
+        work: impl FnOnce(&mut dyn FnMut(Update)) -> ex::Result<Done> + Send + 'static,
+    ) {
+        let (sender, receiver) = channel();
+        let ctx = ctx.clone();
+
+        thread::spawn(move || {
+            let mut update = |update| {
+                let _ = sender.send(Message::Update(update));
+                ctx.request_repaint();
+            };
+            let result = work(&mut update);
+            let _ = sender.send(Message::Finished(result));
+            ctx.request_repaint();
+        });
+
+        self.jobs.push(Job {
+            title,
+            step,
+            receiver,
+            total: 0,
+            done: 0,
+            current: None,
+        });
+    }
+    //Copies or moves `paths` into `dir` one item at a time. Used whenever a server is involved
+    //since that can take a while.
+    pub fn transfer(&mut self, ctx: &Context, paths: Vec<PathBuf>, dir: PathBuf, copy: bool) {
+        let verb = if copy { "Copy" } else { "Move" };
+        let title = match paths.as_slice() {
+            [path] => match path.file_name() {
+                Some(name) => format!("{verb} {}", name.to_string_lossy()),
+                None => format!("{verb} {}", path.display()),
+            },
+            _ => format!("{verb} {} items", paths.len()),
+        };
+
+        self.spawn(ctx, title, move |update| {
+            update(Update::Total(paths.len() as u64));
+            let mut done = Vec::new();
+            for (i, from) in paths.into_iter().enumerate() {
+                let result = if copy {
+                    ex::copy(&from, &dir).map(|to| Operation::Copy {
+                        from: from.clone(),
+                        to,
+                    })
+                } else {
+                    ex::cut(&from, &dir).map(|to| Operation::Move {
+                        from: from.clone(),
+                        to,
+                    })
+                };
+                match result {
+                    Ok(operation) => done.push(operation),
+                    Err(e) => update(Update::Failed(e)),
+                }
+                update(Update::Done {
+                    path: from,
+                    done: i as u64 + 1,
+                });
+            }
+            Ok(done)
+        });
+    }
+    //Returns true when a job finished, since folders might have changed.
+    pub fn show(&mut self, ctx: &Context, history: &mut History, status: &mut Status) -> bool {
+        let mut finished = false;
+
+        self.jobs.retain_mut(|job| loop {
+            match job.receiver.try_recv() {
+                Ok(Message::Update(Update::Total(total))) => job.total = total,
+                Ok(Message::Update(Update::Done { path, done })) => {
+                    job.done = done;
+                    job.current = Some(path);
+                }
+                //Skipped entries don't stop the job.
+                Ok(Message::Update(Update::Failed(e))) => history.error(job.title.clone(), &e),
+                Ok(Message::Finished(result)) => {
+                    match job.step.take() {
+                        Some((Step::Undo, operation)) => {
+                            history.undone(operation, result.map(|_| ()))
+                        }
+                        Some((Step::Redo, operation)) => {
+                            history.redone(operation, result.map(|_| ()))
+                        }
+                        None => match result {
+                            Ok(done) if done.is_empty() => {
+                                status.info(format!("{} finished", job.title))
+                            }
+                            Ok(done) => done.into_iter().for_each(|done| history.record(done)),
+                            Err(e) => history.error(job.title.clone(), &e),
+                        },
+                    }
+                    finished = true;
+                    return false;
+                }
+                Err(TryRecvError::Empty) => return true,
+                Err(TryRecvError::Disconnected) => {
+                    //The history has to get its operation back even if the job panicked.
+                    if let Some((step, operation)) = job.step.take() {
+                        let result = Err(ex::Error::io(
+                            Path::new(""),
+                            io::Error::other("The job stopped unexpectedly"),
+                        ));
+                        match step {
+                            Step::Undo => history.undone(operation, result),
+                            Step::Redo => history.redone(operation, result),
+                        }
+                    }
+                    finished = true;
+                    return false;
+                }
+            }
+        });
+
+        if self.jobs.is_empty() {
+            return finished;
+        }
+
+        Window::new("Jobs")
+            .id(Id::new("jobs"))
+            .anchor(Align2::RIGHT_BOTTOM, [-8.0, -40.0])
+            .resizable(false)
+            .collapsible(true)
+            .show(ctx, |ui| {
+                for job in &self.jobs {
+                    ui.label(&job.title);
+                    let progress = if job.total == 0 {
+                        0.0
+                    } else {
+                        job.done as f32 / job.total as f32
+                    };
+                    ui.add(
+                        ProgressBar::new(progress)
+                            .desired_width(250.0)
+                            .show_percentage(),
+                    );
+                    if let Some(name) = job.current.as_ref().and_then(|path| path.file_name()) {
+                        ui.weak(name.to_string_lossy());
+                    }
+                    ui.add_space(4.0);
+                }
+            });
+
+        finished
+    }
+}
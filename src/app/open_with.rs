@@ -0,0 +1,151 @@
+use super::settings::{self, Settings};
+use eframe::egui::*;
+use ex::{external::External, file_type, open_with};
+use std::path::PathBuf;
+
+//Picks any installed program, or a command, to open a file with.
+pub struct OpenWith {
+    pub open: bool,
+    file: PathBuf,
+    extension: Option<String>,
+    apps: Vec<External>,
+    selected: Option<usize>,
+    filter: String,
+    command: String,
+    remember: bool,
+    error: Option<String>,
+}
+
+impl OpenWith {
+    pub fn new(file: PathBuf) -> Self {
+        let mime = file_type::from_path(&file).mime;
+        //Programs registered for the type go first.
+        let mut apps = open_with::apps(&mime);
+        for app in open_with::all() {
+            if !apps.contains(&app) {
+                apps.push(app);
+            }
+        }
+        Self {
+            open: true,
+            extension: settings::extension(&file),
+            file,
+            apps,
+            selected: None,
+            filter: String::new(),
+            command: String::new(),
+            remember: false,
+            error: None,
+        }
+    }
+    fn app(&self) -> Option<External> {
+        if !self.command.trim().is_empty() {
+            let name = self.command.split_whitespace().next().unwrap_or_default();
+            let command = if self.command.contains("{file") {
+                self.command.clone()
+            } else {
+                format!("{} {{file}}", self.command.trim())
+            };
+            return Some(External::new(name, &command));
+        }
+        self.selected.and_then(|i| self.apps.get(i)).cloned()
+    }
+    pub fn show(&mut self, ctx: &Context, settings: &mut Settings) {
+        let mut open = self.open;
+        let name = self
+            .file
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default();
+
+        Window::new(format!("Open {name} with"))
+            .id(Id::new("open_with"))
+            .open(&mut open)
+            .default_size([400.0, 450.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    ui.label("Filter");
+                    ui.text_edit_singleline(&mut self.filter);
+                });
+                ui.separator();
+
+                let filter = self.filter.to_lowercase();
+                ScrollArea::vertical().max_height(250.0).show(ui, |ui| {
+                    if self.apps.is_empty() {
+                        ui.label("No applications found, enter a command below.");
+                    }
+                    for (i, app) in self.apps.iter().enumerate() {
+                        if !app.name.to_lowercase().contains(&filter) {
+                            continue;
+                        }
+                        let selected = self.selected == Some(i);
+                        let response = ui
+                            .selectable_label(selected, &app.name)
+                            .on_hover_text(&app.command);
+                        if response.clicked() {
+                            self.selected = Some(i);
+                            self.command.clear();
+                        }
+                    }
+                });
+                ui.separator();
+
+                ui.horizontal(|ui| {
+                    ui.label("Command");
+                    ui.text_edit_singleline(&mut self.command).on_hover_text(
+                        "{file} is replaced with the file, otherwise it's added last",
+                    );
+                });
+
+                let extension = self.extension.clone();
+                if let Some(extension) = &extension {
+                    ui.checkbox(
+                        &mut self.remember,
+                        format!("Always use this for .{extension} files"),
+                    );
+                    let current = settings
+                        .open_with
+                        .get(extension)
+                        .map(|app| app.name.clone());
+                    if let Some(current) = current {
+                        ui.horizontal(|ui| {
+                            ui.label(format!("Currently opens with {current}"));
+                            ui.style_mut().visuals.button_frame = true;
+                            if ui.button("Forget").clicked() {
+                                settings.open_with.remove(extension);
+                            }
+                        });
+                    }
+                }
+
+                if let Some(error) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, error);
+                }
+
+                ui.horizontal(|ui| {
+                    ui.style_mut().visuals.button_frame = true;
+                    let app = self.app();
+                    if ui.add_enabled(app.is_some(), Button::new("Open")).clicked() {
+                        let Some(app) = app else {
+                            return;
+                        };
+                        let dir = self.file.parent().unwrap_or(&self.file).to_path_buf();
+                        match app.spawn(&dir, std::slice::from_ref(&self.file)) {
+                            Ok(()) => {
+                                if let (true, Some(extension)) = (self.remember, extension) {
+                                    settings.open_with.insert(extension, app);
+                                }
+                                self.open = false;
+                            }
+                            Err(e) => self.error = Some(e.to_string()),
+                        }
+                    }
+                    if ui.button("Cancel").clicked() {
+                        self.open = false;
+                    }
+                });
+            });
+
+        self.open &= open;
+    }
+}
@@ -0,0 +1,41 @@
+use eframe::egui::*;
+use ex::filter;
+
+//Editor for the names that are hidden along with dotfiles.
+pub fn show(ctx: &Context, open: &mut bool, patterns: &mut Vec<String>) {
+    Window::new("Hidden patterns")
+        .open(open)
+        .default_width(300.0)
+        .show(ctx, |ui| {
+            ui.label(
+                "Files and folders with matching names are hidden, like node_modules or *.pyc",
+            );
+            ui.separator();
+
+            let mut remove = None;
+            Grid::new("patterns").num_columns(3).show(ui, |ui| {
+                for (i, pattern) in patterns.iter_mut().enumerate() {
+                    ui.add(TextEdit::singleline(pattern).desired_width(240.0));
+                    if ui.button("🗑").on_hover_text("Remove").clicked() {
+                        remove = Some(i);
+                    }
+                    //Invalid patterns are skipped rather than hiding everything.
+                    if let Some(error) = filter::pattern_error(pattern) {
+                        ui.colored_label(ui.visuals().error_fg_color, "⚠")
+                            .on_hover_text(error);
+                    }
+                    ui.end_row();
+                }
+            });
+            if let Some(i) = remove {
+                patterns.remove(i);
+            }
+
+            ui.horizontal(|ui| {
+                ui.style_mut().visuals.button_frame = true;
+                if ui.button("Add").clicked() {
+                    patterns.push(String::new());
+                }
+            });
+        });
+}
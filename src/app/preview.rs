@@ -0,0 +1,252 @@
+use super::sizes::FolderSizes;
+use eframe::egui::{text::LayoutJob, *};
+use ex::{
+    preview::{self, Content},
+    vfs::Vfs,
+};
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Receiver, Sender},
+        Arc,
+    },
+    thread,
+};
+use syntect::{
+    easy::HighlightLines,
+    highlighting::{Theme, ThemeSet},
+    parsing::SyntaxSet,
+    util::LinesWithEndings,
+};
+
+//Items are read through the file system of the tab they were selected in.
+type Request = (PathBuf, Arc<dyn Vfs>);
+
+struct Loaded {
+    path: PathBuf,
+    preview: preview::Preview,
+    highlighted: Option<LayoutJob>,
+}
+
+pub struct Preview {
+    pub open: bool,
+    path: Option<PathBuf>,
+    loaded: Option<Loaded>,
+    texture: Option<TextureHandle>,
+    worker: Option<(Sender<Request>, Receiver<Loaded>)>,
+}
+
+impl Preview {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            path: None,
+            loaded: None,
+            texture: None,
+            worker: None,
+        }
+    }
+    pub fn ui(
+        &mut self,
+        ctx: &Context,
+        vfs: &Arc<dyn Vfs>,
+        selected: Option<&Path>,
+        sizes: Option<&mut FolderSizes>,
+    ) {
+        if !self.open {
+            return;
+        }
+
+        if selected != self.path.as_deref() {
+            self.path = selected.map(Path::to_path_buf);
+            self.loaded = None;
+            self.texture = None;
+            if let Some(path) = selected {
+                self.request(ctx, vfs, path);
+            }
+        }
+
+        if let Some((_, receiver)) = &self.worker {
+            while let Ok(loaded) = receiver.try_recv() {
+                //Ignore previews for entries that are no longer selected.
+                if Some(&loaded.path) != self.path.as_ref() {
+                    continue;
+                }
+                if let Content::Image { size, rgba } = &loaded.preview.content {
+                    let image = ColorImage::from_rgba_unmultiplied(*size, rgba);
+                    self.texture = Some(ctx.load_texture("preview", image, Default::default()));
+                }
+                self.loaded = Some(loaded);
+            }
+        }
+
+        SidePanel::right("preview_panel")
+            .resizable(true)
+            .default_width(350.0)
+            .show(ctx, |ui| {
+                if self.path.is_none() {
+                    ui.centered_and_justified(|ui| ui.label("Select a file to preview."));
+                    return;
+                }
+
+                let Some(loaded) = &self.loaded else {
+                    ui.centered_and_justified(|ui| ui.spinner());
+                    return;
+                };
+
+                if let Some(properties) = &loaded.preview.properties {
+                    //Folders are only counted when asked, it can take a while.
+                    let sizes =
+                        sizes.filter(|_| matches!(loaded.preview.content, Content::Directory));
+                    Self::properties(ui, properties, sizes);
+                    ui.separator();
+                }
+
+                match &loaded.preview.content {
+                    Content::Directory => (),
+                    Content::Text {
+                        text, truncated, ..
+                    } => {
+                        ScrollArea::both().show(ui, |ui| {
+                            match &loaded.highlighted {
+                                Some(job) => ui.label(job.clone()),
+                                None => ui.label(RichText::new(text).font(FontId::monospace(13.0))),
+                            };
+                            if *truncated {
+                                ui.weak(format!(
+                                    "Only the first {} KB are shown.",
+                                    preview::TEXT_LIMIT / 1024
+                                ));
+                            }
+                        });
+                    }
+                    Content::Image { size, .. } => {
+                        if let Some(texture) = &self.texture {
+                            let size = Vec2::new(size[0] as f32, size[1] as f32);
+                            let available = ui.available_size();
+                            let scale = (available.x / size.x).min(available.y / size.y).min(1.0);
+                            ui.centered_and_justified(|ui| ui.image(texture.id(), size * scale));
+                        }
+                    }
+                    Content::Binary(dump) => {
+                        ScrollArea::both().show(ui, |ui| {
+                            ui.label(RichText::new(dump).font(FontId::monospace(13.0)));
+                        });
+                    }
+                    Content::Error(e) => {
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                    }
+                }
+            });
+    }
+    fn properties(ui: &mut Ui, properties: &preview::Properties, sizes: Option<&mut FolderSizes>) {
+        Grid::new("properties")
+            .num_columns(2)
+            .striped(true)
+            .show(ui, |ui| {
+                let row = |ui: &mut Ui, label: &str, value: &str| {
+                    ui.weak(label);
+                    ui.add(Label::new(value).wrap(true));
+                    ui.end_row();
+                };
+
+                row(ui, "Path", &properties.path.to_string_lossy());
+                let path = &properties.path;
+                match sizes.map(|sizes| (sizes.get(path), sizes)) {
+                    Some((Some((size, done)), _)) => {
+                        let counting = if done { "" } else { " (counting…)" };
+                        let bytes = ex::format_bytes(size.bytes);
+                        row(ui, "Size", &format!("{bytes}{counting}"));
+                        let contains = format!("{} files, {} folders", size.files, size.folders);
+                        row(ui, "Contains", &contains);
+                    }
+                    Some((None, sizes)) => {
+                        ui.weak("Size");
+                        if ui.button("Calculate").clicked() {
+                            sizes.calculate(ui.ctx(), path);
+                        }
+                        ui.end_row();
+                    }
+                    None => row(ui, "Size", &ex::format_bytes(properties.size)),
+                }
+                if let Some(created) = &properties.created {
+                    row(ui, "Created", created);
+                }
+                if let Some(modified) = &properties.modified {
+                    row(ui, "Modified", modified);
+                }
+                if let Some(accessed) = &properties.accessed {
+                    row(ui, "Accessed", accessed);
+                }
+                row(ui, "Permissions", &properties.permissions);
+                row(ui, "Type", &properties.mime);
+            });
+    }
+    fn request(&mut self, ctx: &Context, vfs: &Arc<dyn Vfs>, path: &Path) {
+        let (sender, _) = self.worker.get_or_insert_with(|| spawn(ctx.clone()));
+        let _ = sender.send((path.to_path_buf(), vfs.clone()));
+    }
+}
+
+fn spawn(ctx: Context) -> (Sender<Request>, Receiver<Loaded>) {
+    let (request, requests) = channel::<Request>();
+    let (send, receive) = channel();
+
+    thread::spawn(move || {
+        let syntaxes = SyntaxSet::load_defaults_newlines();
+        let themes = ThemeSet::load_defaults();
+        let theme = &themes.themes["base16-ocean.dark"];
+
+        while let Ok(mut request) = requests.recv() {
+            //Skip anything that was selected while the last preview was loading.
+            while let Ok(newer) = requests.try_recv() {
+                request = newer;
+            }
+
+            let (path, vfs) = request;
+            let preview = preview::load(vfs.as_ref(), &path);
+            let highlighted = match &preview.content {
+                Content::Text {
+                    text, extension, ..
+                } => highlight(&syntaxes, theme, text, extension),
+                _ => None,
+            };
+
+            let loaded = Loaded {
+                path,
+                preview,
+                highlighted,
+            };
+            if send.send(loaded).is_err() {
+                break;
+            }
+            ctx.request_repaint();
+        }
+    });
+
+    (request, receive)
+}
+
+fn highlight(
+    syntaxes: &SyntaxSet,
+    theme: &Theme,
+    text: &str,
+    extension: &str,
+) -> Option<LayoutJob> {
+    let syntax = syntaxes.find_syntax_by_extension(extension)?;
+    let mut highlighter = HighlightLines::new(syntax, theme);
+    let mut job = LayoutJob::default();
+
+    for line in LinesWithEndings::from(text) {
+        for (style, range) in highlighter.highlight_line(line, syntaxes).ok()? {
+            let color = style.foreground;
+            let format = TextFormat::simple(
+                FontId::monospace(13.0),
+                Color32::from_rgb(color.r, color.g, color.b),
+            );
+            job.append(range, 0.0, format);
+        }
+    }
+
+    Some(job)
+}
@@ -0,0 +1,226 @@
+use eframe::egui::*;
+use egui_extras::*;
+use ex::recycle::{self, Item};
+use std::path::{Path, PathBuf};
+
+enum Confirm {
+    Purge,
+    Empty,
+}
+
+pub enum Action {
+    //Something came back, the folder may need to be reloaded.
+    Restored,
+    Close,
+}
+
+pub struct RecycleBin {
+    //The folder the browser was in, the trash closes when it navigates somewhere else.
+    pub dir: PathBuf,
+    items: Vec<Item>,
+    selected: Vec<bool>,
+    restore_to: Option<String>,
+    confirm: Option<Confirm>,
+    error: Option<String>,
+}
+
+impl RecycleBin {
+    pub fn new(dir: &Path) -> Self {
+        let mut bin = Self {
+            dir: dir.to_path_buf(),
+            items: Vec::new(),
+            selected: Vec::new(),
+            restore_to: None,
+            confirm: None,
+            error: None,
+        };
+        bin.reload();
+        bin
+    }
+    fn reload(&mut self) {
+        match recycle::list() {
+            Ok(items) => self.items = items,
+            Err(e) => {
+                self.items.clear();
+                self.error = Some(format!("Failed to read the Trash: {e}"));
+            }
+        }
+        self.selected = vec![false; self.items.len()];
+    }
+    fn selection(&self) -> Vec<Item> {
+        self.items
+            .iter()
+            .zip(&self.selected)
+            .filter(|(_, selected)| **selected)
+            .map(|(item, _)| item.clone())
+            .collect()
+    }
+    //Runs an action on the trash and reloads it, returns true if it worked.
+    fn run(&mut self, verb: &str, result: Result<(), recycle::Error>) -> bool {
+        self.error = result
+            .as_ref()
+            .err()
+            .map(|e| format!("Failed to {verb}: {e}"));
+        self.reload();
+        result.is_ok()
+    }
+    pub fn ui(&mut self, ui: &mut Ui) -> Option<Action> {
+        let mut action = None;
+        let mut restored = false;
+        let count = self.selected.iter().filter(|s| **s).count();
+
+        ui.horizontal(|ui| {
+            ui.style_mut().visuals.button_frame = true;
+
+            ui.heading("Trash");
+            ui.label(format!("{} items", self.items.len()));
+
+            let enabled = count > 0;
+            if ui.add_enabled(enabled, Button::new("Restore")).clicked() {
+                let items = self.selection();
+                restored |= self.run("restore", recycle::restore(&items));
+            }
+            if ui
+                .add_enabled(enabled, Button::new("Restore To…"))
+                .clicked()
+            {
+                self.restore_to = Some(self.dir.display().to_string());
+            }
+            if ui
+                .add_enabled(enabled, Button::new("Delete Permanently"))
+                .clicked()
+            {
+                self.confirm = Some(Confirm::Purge);
+            }
+
+            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                if ui.button("Close").clicked() {
+                    action = Some(Action::Close);
+                }
+                if ui.button("Refresh").clicked() {
+                    self.error = None;
+                    self.reload();
+                }
+                let empty = Button::new("Empty Trash");
+                if ui.add_enabled(!self.items.is_empty(), empty).clicked() {
+                    self.confirm = Some(Confirm::Empty);
+                }
+            });
+        });
+
+        if let Some(mut dir) = self.restore_to.take() {
+            let mut restore = false;
+            let mut cancel = false;
+            ui.horizontal(|ui| {
+                ui.style_mut().visuals.button_frame = true;
+                ui.label(format!("Restore {count} items to"));
+                ui.add(TextEdit::singleline(&mut dir).desired_width(300.0));
+                restore = ui.button("Restore").clicked();
+                cancel = ui.button("Cancel").clicked();
+            });
+
+            let path = PathBuf::from(dir.trim());
+            if restore && path.is_dir() {
+                let items = self.selection();
+                restored |= self.run("restore", recycle::restore_to(&items, &path));
+            } else if restore {
+                self.error = Some(format!("{} is not a folder", path.display()));
+                self.restore_to = Some(dir);
+            } else if !cancel {
+                self.restore_to = Some(dir);
+            }
+        }
+
+        if let Some(confirm) = self.confirm.take() {
+            let text = match confirm {
+                Confirm::Purge => format!("Permanently delete {count} items?"),
+                Confirm::Empty => format!("Permanently delete all {} items?", self.items.len()),
+            };
+            let mut answer = None;
+            Window::new("Delete permanently?")
+                .resizable(false)
+                .collapsible(false)
+                .show(ui.ctx(), |ui| {
+                    ui.label(text);
+                    ui.label("This can't be undone.");
+                    ui.horizontal(|ui| {
+                        if ui.button("Yes").clicked() {
+                            answer = Some(true);
+                        }
+                        if ui.button("No").clicked() {
+                            answer = Some(false);
+                        }
+                    });
+                });
+
+            match answer {
+                Some(true) => {
+                    let result = match confirm {
+                        Confirm::Purge => recycle::purge(&self.selection()),
+                        Confirm::Empty => recycle::empty(),
+                    };
+                    self.run("delete", result);
+                }
+                Some(false) => (),
+                None => self.confirm = Some(confirm),
+            }
+        }
+
+        if let Some(e) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, e);
+        }
+
+        ui.separator();
+
+        if self.items.is_empty() {
+            ui.centered_and_justified(|ui| ui.label("Trash is empty."));
+            return action.or(restored.then_some(Action::Restored));
+        }
+
+        TableBuilder::new(ui)
+            .striped(true)
+            .column(Column::auto())
+            .column(Column::initial(250.0).clip(true).resizable(true))
+            .column(Column::initial(350.0).clip(true).resizable(true))
+            .column(Column::remainder())
+            .header(20.0, |mut header| {
+                header.col(|ui| {
+                    let mut all = self.selected.iter().all(|s| *s);
+                    if ui.checkbox(&mut all, "").changed() {
+                        self.selected.fill(all);
+                    }
+                });
+                header.col(|ui| {
+                    ui.strong("Name");
+                });
+                header.col(|ui| {
+                    ui.strong("Original location");
+                });
+                header.col(|ui| {
+                    ui.strong("Date deleted");
+                });
+            })
+            .body(|body| {
+                body.rows(20.0, self.items.len(), |i, mut row| {
+                    let item = &self.items[i];
+                    row.col(|ui| {
+                        ui.checkbox(&mut self.selected[i], "");
+                    });
+                    row.col(|ui| {
+                        ui.add(Label::new(&item.name).wrap(false));
+                    });
+                    row.col(|ui| {
+                        let parent = item.original.parent().unwrap_or(&item.original);
+                        ui.add(Label::new(parent.display().to_string()).wrap(false));
+                    });
+                    row.col(|ui| {
+                        if let Some(deleted) = item.deleted {
+                            ui.label(ex::format_time(deleted));
+                        }
+                    });
+                });
+            });
+
+        action.or(restored.then_some(Action::Restored))
+    }
+}
@@ -0,0 +1,213 @@
+use eframe::egui::*;
+use egui_extras::*;
+use ex::{
+    history::{History, Operation},
+    rename::{self, Case, Rename, Rule},
+};
+use std::path::{Path, PathBuf};
+
+pub struct RenameDialog {
+    pub open: bool,
+    files: Vec<PathBuf>,
+    rule: Rule,
+    change_extension: bool,
+    extension: String,
+    preview: Result<Vec<Rename>, String>,
+    error: Option<String>,
+}
+
+impl RenameDialog {
+    pub fn new(files: Vec<PathBuf>) -> Self {
+        let mut dialog = Self {
+            open: true,
+            files,
+            rule: Rule::default(),
+            change_extension: false,
+            extension: String::new(),
+            preview: Ok(Vec::new()),
+            error: None,
+        };
+        dialog.update();
+        dialog
+    }
+    fn update(&mut self) {
+        self.rule.extension = if self.change_extension {
+            Some(self.extension.clone())
+        } else {
+            None
+        };
+        self.preview = rename::preview(&self.files, &self.rule).map_err(|e| e.to_string());
+    }
+    //Returns true once the files have been renamed.
+    pub fn show(&mut self, ctx: &Context, history: &mut History) -> bool {
+        let mut open = self.open;
+        let mut renamed = false;
+
+        let title = match self.files.len() {
+            1 => String::from("Rename"),
+            n => format!("Rename {n} items"),
+        };
+
+        Window::new(title)
+            .id(Id::new("rename"))
+            .open(&mut open)
+            .default_size([700.0, 450.0])
+            .show(ctx, |ui| {
+                let mut changed = false;
+
+                Grid::new("rename_rule").num_columns(2).show(ui, |ui| {
+                    ui.label("Find");
+                    ui.horizontal(|ui| {
+                        changed |= ui.text_edit_singleline(&mut self.rule.find).changed();
+                        changed |= ui.checkbox(&mut self.rule.regex, "Regex").changed();
+                    });
+                    ui.end_row();
+
+                    ui.label("Replace with");
+                    changed |= ui.text_edit_singleline(&mut self.rule.replace).changed();
+                    ui.end_row();
+
+                    ui.label("Case");
+                    ui.horizontal(|ui| {
+                        for (case, label) in [
+                            (Case::Unchanged, "Unchanged"),
+                            (Case::Lower, "lower"),
+                            (Case::Upper, "UPPER"),
+                            (Case::Title, "Title"),
+                        ] {
+                            changed |= ui.radio_value(&mut self.rule.case, case, label).changed();
+                        }
+                    });
+                    ui.end_row();
+
+                    ui.label("Name");
+                    ui.horizontal(|ui| {
+                        changed |= ui.text_edit_singleline(&mut self.rule.template).changed();
+                        ui.weak("ⓘ").on_hover_text(
+                            "{name} the name after find and replace\n\
+                             {n} or {n:3} a number starting from below, padded to 3 digits\n\
+                             {date} or {date:%d-%m-%Y} the date modified\n\
+                             {ext} the original extension",
+                        );
+                    });
+                    ui.end_row();
+
+                    ui.label("Start at");
+                    changed |= ui.add(DragValue::new(&mut self.rule.start)).changed();
+                    ui.end_row();
+
+                    ui.checkbox(&mut self.change_extension, "Extension")
+                        .changed()
+                        .then(|| changed = true);
+                    changed |= ui
+                        .add_enabled(
+                            self.change_extension,
+                            TextEdit::singleline(&mut self.extension).hint_text("none"),
+                        )
+                        .changed();
+                    ui.end_row();
+                });
+
+                if changed {
+                    self.update();
+                    self.error = None;
+                }
+
+                ui.separator();
+
+                let renames = match &self.preview {
+                    Ok(renames) => renames,
+                    Err(e) => {
+                        ui.colored_label(ui.visuals().error_fg_color, e);
+                        return;
+                    }
+                };
+
+                let conflicts = renames.iter().filter(|r| r.conflict.is_some()).count();
+                let changes = renames.iter().filter(|r| !r.is_unchanged()).count();
+
+                ui.horizontal(|ui| {
+                    ui.style_mut().visuals.button_frame = true;
+
+                    let enabled = conflicts == 0 && changes > 0;
+                    if ui.add_enabled(enabled, Button::new("Rename")).clicked() {
+                        match rename::apply(renames) {
+                            Ok(_) => {
+                                let renames = renames
+                                    .iter()
+                                    .filter(|r| !r.is_unchanged())
+                                    .map(|r| (r.from.clone(), r.to.clone()))
+                                    .collect();
+                                history.record(Operation::Rename(renames));
+                                renamed = true;
+                            }
+                            Err(e) => self.error = Some(e.to_string()),
+                        }
+                    }
+
+                    if conflicts > 0 {
+                        ui.colored_label(
+                            ui.visuals().error_fg_color,
+                            format!("{conflicts} conflicts"),
+                        );
+                    } else {
+                        ui.label(format!("{changes} of {} will be renamed", renames.len()));
+                    }
+                });
+
+                if let Some(e) = &self.error {
+                    ui.colored_label(ui.visuals().error_fg_color, e);
+                }
+
+                TableBuilder::new(ui)
+                    .striped(true)
+                    .column(Column::initial(250.0).clip(true).resizable(true))
+                    .column(Column::initial(250.0).clip(true).resizable(true))
+                    .column(Column::remainder())
+                    .header(20.0, |mut header| {
+                        header.col(|ui| {
+                            ui.strong("Old name");
+                        });
+                        header.col(|ui| {
+                            ui.strong("New name");
+                        });
+                        header.col(|ui| {
+                            ui.strong("");
+                        });
+                    })
+                    .body(|body| {
+                        body.rows(20.0, renames.len(), |i, mut row| {
+                            let r = &renames[i];
+                            row.col(|ui| {
+                                ui.label(name(&r.from));
+                            });
+                            row.col(|ui| {
+                                if r.is_unchanged() {
+                                    ui.weak(name(&r.to));
+                                } else {
+                                    ui.label(name(&r.to));
+                                }
+                            });
+                            row.col(|ui| {
+                                if let Some(conflict) = &r.conflict {
+                                    ui.colored_label(
+                                        ui.visuals().error_fg_color,
+                                        conflict.to_string(),
+                                    );
+                                }
+                            });
+                        });
+                    });
+            });
+
+        self.open = open && !renamed;
+        renamed
+    }
+}
+
+fn name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .to_string()
+}
@@ -0,0 +1,49 @@
+use super::git::Ignored;
+use ex::{
+    external::{self, External},
+    vfs::Connection,
+    Units,
+};
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, path::Path};
+
+//App wide preferences, saved between sessions by eframe.
+#[derive(Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub units: Units,
+    pub secure_delete: bool,
+    pub commands: Vec<External>,
+    //Lowercase extension to the program that always opens it.
+    pub open_with: BTreeMap<String, External>,
+    //Servers in the side panel.
+    pub connections: Vec<Connection>,
+    pub ignored: Ignored,
+    //Globs for names that are hidden like dotfiles.
+    pub hidden: Vec<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            units: Units::default(),
+            secure_delete: false,
+            commands: external::detect(),
+            open_with: BTreeMap::new(),
+            connections: Vec::new(),
+            ignored: Ignored::default(),
+            hidden: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn open_with(&self, file: &Path) -> Option<&External> {
+        self.open_with.get(&extension(file)?)
+    }
+}
+
+pub fn extension(file: &Path) -> Option<String> {
+    let extension = file.extension()?.to_str()?;
+    Some(extension.to_lowercase())
+}
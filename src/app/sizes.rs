@@ -0,0 +1,112 @@
+use eframe::egui::Context;
+use ex::DirSize;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+//How long a finished size is trusted before the folder is checked for changes again.
+const RECHECK: Duration = Duration::from_secs(2);
+
+struct Entry {
+    modified: Option<SystemTime>,
+    checked: Instant,
+    size: DirSize,
+    done: bool,
+}
+
+type Update = (PathBuf, DirSize, bool);
+
+//Recursive folder sizes, calculated one folder at a time in the background.
+pub struct FolderSizes {
+    cache: HashMap<PathBuf, Entry>,
+    worker: Option<(Sender<PathBuf>, Receiver<Update>)>,
+}
+
+impl FolderSizes {
+    pub fn new() -> Self {
+        Self {
+            cache: HashMap::new(),
+            worker: None,
+        }
+    }
+    //Returns the size and whether it's finished counting.
+    pub fn get(&mut self, path: &Path) -> Option<(DirSize, bool)> {
+        let entry = self.cache.get_mut(path)?;
+        //The cached size is out of date once the folder has been changed.
+        if entry.done && entry.checked.elapsed() > RECHECK {
+            if entry.modified != modified(path) {
+                self.cache.remove(path);
+                return None;
+            }
+            entry.checked = Instant::now();
+        }
+        Some((entry.size, entry.done))
+    }
+    //Only the total of a finished folder is worth sorting by.
+    pub fn bytes(&self, path: &Path) -> Option<u64> {
+        self.cache
+            .get(path)
+            .filter(|entry| entry.done)
+            .map(|entry| entry.size.bytes)
+    }
+    pub fn calculate(&mut self, ctx: &Context, path: &Path) {
+        if self.get(path).is_some() {
+            return;
+        }
+
+        self.cache.insert(
+            path.to_path_buf(),
+            Entry {
+                modified: modified(path),
+                checked: Instant::now(),
+                size: DirSize::default(),
+                done: false,
+            },
+        );
+
+        let (sender, _) = self.worker.get_or_insert_with(|| spawn(ctx.clone()));
+        let _ = sender.send(path.to_path_buf());
+    }
+    //Called once per frame, returns true when a folder has finished counting.
+    pub fn update(&mut self) -> bool {
+        let mut finished = false;
+        if let Some((_, receiver)) = &self.worker {
+            while let Ok((path, size, done)) = receiver.try_recv() {
+                if let Some(entry) = self.cache.get_mut(&path) {
+                    entry.size = size;
+                    entry.done = done;
+                    finished |= done;
+                }
+            }
+        }
+        finished
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    path.metadata().and_then(|m| m.modified()).ok()
+}
+
+fn spawn(ctx: Context) -> (Sender<PathBuf>, Receiver<Update>) {
+    let (request, requests) = channel::<PathBuf>();
+    let (send, receive) = channel();
+
+    thread::spawn(move || {
+        while let Ok(path) = requests.recv() {
+            let size = ex::dir_size(&path, |partial| {
+                let _ = send.send((path.clone(), *partial, false));
+                ctx.request_repaint();
+            });
+            if send.send((path, size, true)).is_err() {
+                break;
+            }
+            ctx.request_repaint();
+        }
+    });
+
+    (request, receive)
+}
@@ -0,0 +1,210 @@
+use super::browser::Browser;
+use eframe::egui::*;
+use ex::{
+    history::{Action, History},
+    Units,
+};
+use std::{
+    path::PathBuf,
+    time::{Duration, Instant, SystemTime},
+};
+
+//How long a message stays in the status bar.
+const TOAST: Duration = Duration::from_secs(5);
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum Level {
+    Info,
+    Error,
+}
+
+pub struct Message {
+    pub time: SystemTime,
+    pub level: Level,
+    pub text: String,
+}
+
+//The bar along the bottom of the window and every message shown in it this session.
+pub struct Status {
+    messages: Vec<Message>,
+    //When the newest message was posted.
+    shown: Option<Instant>,
+    log: bool,
+    //History entries that have already been turned into messages.
+    seen: usize,
+    free: Option<(PathBuf, Instant, Option<u64>)>,
+    selected: (Vec<PathBuf>, u64),
+}
+
+impl Status {
+    pub fn new() -> Self {
+        Self {
+            messages: Vec::new(),
+            shown: None,
+            log: false,
+            seen: 0,
+            free: None,
+            selected: (Vec::new(), 0),
+        }
+    }
+    pub fn info(&mut self, text: impl Into<String>) {
+        self.push(Level::Info, text.into());
+    }
+    pub fn error(&mut self, text: impl Into<String>) {
+        self.push(Level::Error, text.into());
+    }
+    fn push(&mut self, level: Level, text: String) {
+        self.messages.push(Message {
+            time: SystemTime::now(),
+            level,
+            text,
+        });
+        self.shown = Some(Instant::now());
+    }
+    //Everything done to files goes through the history, so that's where most messages come from.
+    //Returns true if there's anything new to show.
+    pub fn sync(&mut self, history: &History) -> bool {
+        let new = history.count() - self.seen;
+        self.seen = history.count();
+
+        let log = history.log();
+        for entry in &log[log.len().saturating_sub(new)..] {
+            let action = match entry.action {
+                Action::Did => "",
+                Action::Undid => "Undo: ",
+                Action::Redid => "Redo: ",
+            };
+            match &entry.error {
+                Some(e) => self.error(format!("{action}{} failed: {e}", entry.description)),
+                None => self.info(format!("{action}{}", entry.description)),
+            }
+        }
+        new > 0
+    }
+    fn free_space(&mut self, dir: PathBuf) -> Option<u64> {
+        //Checked every few seconds since other programs fill the drive too.
+        let stale = match &self.free {
+            Some((path, time, _)) => *path != dir || time.elapsed() > TOAST,
+            None => true,
+        };
+        if stale {
+            let free = ex::free_space(&dir).ok();
+            self.free = Some((dir, Instant::now(), free));
+        }
+        self.free.as_ref().and_then(|(_, _, free)| *free)
+    }
+    fn selected_size(&mut self, browser: &Browser, selection: Vec<PathBuf>) -> u64 {
+        if self.selected.0 != selection {
+            let size = selection
+                .iter()
+                .filter_map(|path| browser.ex.metadata(path))
+                .map(|metadata| metadata.len)
+                .sum();
+            self.selected = (selection, size);
+        }
+        self.selected.1
+    }
+    pub fn ui(&mut self, ctx: &Context, browser: &Browser, units: Units) {
+        let items = browser.ex.files.len();
+        let selection = browser.selection();
+        let selected = selection.len();
+        let size = self.selected_size(browser, selection);
+        let free = self.free_space(browser.ex.current_path().to_path_buf());
+
+        TopBottomPanel::bottom("status").show(ctx, |ui| {
+            ui.horizontal(|ui| {
+                ui.label(match items {
+                    1 => String::from("1 item"),
+                    n => format!("{n} items"),
+                });
+
+                if selected > 0 {
+                    ui.separator();
+                    let size = ex::format_size(size, units);
+                    ui.label(match selected {
+                        1 => format!("1 item selected {size}"),
+                        n => format!("{n} items selected {size}"),
+                    });
+                }
+
+                if let Some(free) = free {
+                    ui.separator();
+                    ui.label(format!("{} free", ex::format_size(free, units)));
+                }
+
+                ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                    let log = ui
+                        .selectable_label(self.log, format!("🗊 {}", self.messages.len()))
+                        .on_hover_text("Messages");
+                    if log.clicked() {
+                        self.log = !self.log;
+                    }
+
+                    let Some(shown) = self.shown else {
+                        return;
+                    };
+                    let remaining = TOAST.saturating_sub(shown.elapsed());
+                    if remaining.is_zero() {
+                        self.shown = None;
+                        return;
+                    }
+                    ctx.request_repaint_after(remaining);
+
+                    if let Some(message) = self.messages.last() {
+                        let text = match message.level {
+                            Level::Info => RichText::new(&message.text),
+                            Level::Error => {
+                                RichText::new(&message.text).color(ui.visuals().error_fg_color)
+                            }
+                        };
+                        if ui.add(Label::new(text).sense(Sense::click())).clicked() {
+                            self.log = true;
+                        }
+                    }
+                });
+            });
+        });
+
+        self.messages_ui(ctx);
+    }
+    fn messages_ui(&mut self, ctx: &Context) {
+        let mut open = self.log;
+        Window::new("Messages")
+            .open(&mut open)
+            .default_size([500.0, 300.0])
+            .show(ctx, |ui| {
+                if self.messages.is_empty() {
+                    ui.label("Nothing to report.");
+                    return;
+                }
+
+                ui.horizontal(|ui| {
+                    ui.style_mut().visuals.button_frame = true;
+                    if ui.button("Clear").clicked() {
+                        self.messages.clear();
+                        self.shown = None;
+                    }
+                });
+                ui.separator();
+
+                ScrollArea::vertical()
+                    .auto_shrink([false; 2])
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        Grid::new("messages").striped(true).show(ui, |ui| {
+                            for message in &self.messages {
+                                ui.weak(ex::format_time(message.time));
+                                match message.level {
+                                    Level::Info => ui.label(&message.text),
+                                    Level::Error => {
+                                        ui.colored_label(ui.visuals().error_fg_color, &message.text)
+                                    }
+                                };
+                                ui.end_row();
+                            }
+                        });
+                    });
+            });
+        self.log = open;
+    }
+}
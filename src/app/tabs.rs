@@ -1,10 +1,27 @@
-use super::browser::Browser;
+use super::{
+    browser::Browser,
+    connect::{ConnectDialog, Connecting},
+    connections::ConnectionDialog,
+    drag,
+    history::HistoryPanel,
+    jobs::Jobs,
+    preview::Preview,
+    settings::Settings,
+    status::Status,
+};
 use eframe::egui::*;
+use ex::{clipboard::Clipboard, history::History};
 use std::path::Path;
 
 pub struct Tabs {
     browsers: Vec<Browser>,
     index: usize,
+    preview: Preview,
+    history: HistoryPanel,
+    connect: Option<ConnectDialog>,
+    connection: Option<ConnectionDialog>,
+    //A saved server being opened, in a new tab if it was middle clicked.
+    connecting: Option<(Connecting, bool)>,
 }
 
 impl Tabs {
@@ -12,6 +29,11 @@ impl Tabs {
         Self {
             browsers: vec![Browser::new()],
             index: 0,
+            preview: Preview::new(),
+            history: HistoryPanel::new(),
+            connect: None,
+            connection: None,
+            connecting: None,
         }
     }
     pub fn add(&mut self, path: &Path) {
@@ -28,11 +50,74 @@ impl Tabs {
             self.index = self.index.saturating_sub(1);
         }
     }
-    pub fn body(&mut self, ctx: &Context) {
-        if let Some(path) = self.browsers[self.index].ui(ctx) {
+    pub fn body(
+        &mut self,
+        ctx: &Context,
+        settings: &mut Settings,
+        history: &mut History,
+        clipboard: &mut Clipboard,
+        status: &mut Status,
+        jobs: &mut Jobs,
+    ) {
+        self.history.ui(ctx, history, jobs);
+
+        //Text boxes have their own undo.
+        if ctx.memory(|m| m.focus().is_none()) {
+            let (undo, redo) = ctx.input_mut(|i| {
+                let undo = i.consume_key(Modifiers::COMMAND, Key::Z);
+                let redo = i.consume_key(Modifiers::COMMAND, Key::Y)
+                    || i.consume_key(Modifiers::COMMAND | Modifiers::SHIFT, Key::Z);
+                (undo, redo)
+            });
+            //Folders are refreshed once the job is done.
+            if undo {
+                jobs.undo(ctx, history);
+            }
+            if redo {
+                jobs.redo(ctx, history);
+            }
+        }
+
+        let browser = &mut self.browsers[self.index];
+
+        let path = browser.ui(ctx, settings, history, clipboard, status, jobs);
+
+        //Drops are handled last, once every panel has had a chance to be the target.
+        let dir = browser.ex.current_path().to_path_buf();
+        if drag::finish(ctx, history, jobs)
+            | drag::dropped_files(ctx, &dir, history, jobs)
+            | jobs.show(ctx, history, status)
+        {
+            for browser in &mut self.browsers {
+                browser.refresh();
+            }
+            ctx.request_repaint();
+        }
+
+        if let Some(path) = path {
             self.add(&path);
         };
     }
+    pub fn preview(&mut self, ctx: &Context) {
+        if ctx.input_mut(|i| i.consume_key(Modifiers::ALT, Key::P)) {
+            self.preview.open = !self.preview.open;
+        }
+
+        if !self.preview.open {
+            return;
+        }
+
+        let browser = &mut self.browsers[self.index];
+        let selected = browser.selected().map(Path::to_path_buf);
+        let vfs = browser.ex.vfs().clone();
+        //Only folders on the local disk can be counted.
+        let local = browser.local();
+        let sizes = local.then_some(&mut browser.sizes);
+        self.preview.ui(ctx, &vfs, selected.as_deref(), sizes);
+    }
+    pub fn status(&mut self, ctx: &Context, status: &mut Status, settings: &Settings) {
+        status.ui(ctx, &self.browsers[self.index], settings.units);
+    }
     pub fn header(&mut self, ctx: &Context) {
         TopBottomPanel::top("top_panel").show(ctx, |ui| {
             ui.horizontal(|ui| {
@@ -41,6 +126,7 @@ impl Tabs {
                     let selected = i == self.index;
 
                     let label = ui.selectable_label(selected, b.title());
+                    drag::target(&label, b.ex.current_path());
                     if label.clicked() {
                         self.index = i;
                     };
@@ -67,6 +153,22 @@ impl Tabs {
                 };
 
                 ui.with_layout(Layout::right_to_left(Align::TOP), |ui| {
+                    if ui
+                        .selectable_label(self.history.open, "History")
+                        .on_hover_text("Ctrl+Z to undo, Ctrl+Y to redo")
+                        .clicked()
+                    {
+                        self.history.open = !self.history.open;
+                    }
+
+                    if ui
+                        .selectable_label(self.preview.open, "Preview")
+                        .on_hover_text("Alt+P")
+                        .clicked()
+                    {
+                        self.preview.open = !self.preview.open;
+                    }
+
                     let search = &mut self.browsers[self.index].search;
 
                     //TODO: highlight the borders
@@ -85,21 +187,22 @@ impl Tabs {
                     {
                         let browser = &mut self.browsers[self.index];
                         let path = browser.ex.current_path().to_path_buf();
-                        browser.ex.set_directory(&path, &browser.search);
+                        browser.set_directory(&path);
                     }
                 });
             });
         });
     }
-    pub fn quick_access(&mut self, ctx: &Context) {
+    pub fn quick_access(&mut self, ctx: &Context, settings: &mut Settings, status: &mut Status) {
         SidePanel::left("side_panel").show(ctx, |ui| {
             let mut item = |ui: &mut Ui, label: &str, path: &str| {
                 let item = ui.button(label);
                 let path = Path::new(path);
+                drag::target(&item, path);
 
                 if item.clicked() {
                     let browser = &mut self.browsers[self.index];
-                    browser.ex.set_directory(path, &browser.search);
+                    browser.set_directory(path);
                 }
 
                 if item.middle_clicked() {
@@ -121,7 +224,80 @@ impl Tabs {
                     item(ui, "C:\\", "C:\\");
                     item(ui, "D:\\", "D:\\");
                 });
+
+            CollapsingHeader::new("Connections")
+                .default_open(true)
+                .show(ui, |ui| {
+                    let mut remove = None;
+                    for (i, connection) in settings.connections.iter().enumerate() {
+                        let root = connection.root();
+                        let item = ui
+                            .button(format!("🖧 {}", connection.name))
+                            .on_hover_text(&connection.endpoint);
+                        drag::target(&item, &root);
+
+                        if item.clicked() || item.middle_clicked() {
+                            let connecting = Connecting::new(ctx, root);
+                            self.connecting = Some((connecting, item.middle_clicked()));
+                        }
+
+                        item.context_menu(|ui| {
+                            if ui.button("Edit").clicked() {
+                                self.connection = Some(ConnectionDialog::new(settings, Some(i)));
+                                ui.close_menu();
+                            }
+                            if ui.button("Remove").clicked() {
+                                remove = Some(i);
+                                ui.close_menu();
+                            }
+                        });
+                    }
+                    if let Some(i) = remove {
+                        settings.connections.remove(i);
+                        ex::vfs::save_connections(&settings.connections);
+                    }
+
+                    if ui.button("＋ Add connection…").clicked() {
+                        self.connection = Some(ConnectionDialog::new(settings, None));
+                    }
+                    if ui.button("Connect to server…").clicked() {
+                        self.connect = Some(ConnectDialog::new());
+                    }
+                });
+
+            if ui.button("🗑 Trash").clicked() {
+                self.browsers[self.index].open_trash();
+            }
         });
+
+        if let Some(dialog) = &mut self.connection {
+            dialog.show(ctx, settings);
+            if !dialog.open {
+                self.connection = None;
+            }
+        }
+
+        if let Some((connecting, new_tab)) = &self.connecting {
+            let new_tab = *new_tab;
+            if let Some(result) = connecting.poll() {
+                self.connecting = None;
+                match result {
+                    Ok(path) if new_tab => self.add(&path),
+                    Ok(path) => self.browsers[self.index].set_directory(&path),
+                    Err(e) => status.error(e.to_string()),
+                }
+            }
+        }
+
+        if let Some(dialog) = &mut self.connect {
+            let connected = dialog.show(ctx);
+            if !dialog.open {
+                self.connect = None;
+            }
+            if let Some(path) = connected {
+                self.add(&path);
+            }
+        }
     }
 
     pub fn side_buttons(&mut self, ctx: &Context) {
@@ -0,0 +1,171 @@
+use eframe::egui::*;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::{
+        mpsc::{channel, Receiver},
+        Arc, Condvar, Mutex,
+    },
+    thread,
+};
+
+const WORKERS: usize = 4;
+//Roughly 128 MB of textures before old ones are evicted.
+const CAPACITY: usize = 2048;
+
+enum Thumbnail {
+    Pending,
+    Loaded(TextureHandle, u64),
+    Failed,
+}
+
+#[derive(Default)]
+struct Queue {
+    paths: Vec<PathBuf>,
+    closed: bool,
+}
+
+type Shared = Arc<(Mutex<Queue>, Condvar)>;
+
+pub struct Thumbnails {
+    dir: PathBuf,
+    frame: u64,
+    thumbnails: HashMap<PathBuf, Thumbnail>,
+    queue: Shared,
+    receiver: Option<Receiver<(PathBuf, Result<ColorImage, String>)>>,
+}
+
+impl Thumbnails {
+    pub fn new() -> Self {
+        Self {
+            dir: PathBuf::new(),
+            frame: 0,
+            thumbnails: HashMap::new(),
+            queue: Shared::default(),
+            receiver: None,
+        }
+    }
+    //Called once per frame before any tiles are drawn.
+    pub fn prepare(&mut self, ctx: &Context, dir: &Path) {
+        self.frame += 1;
+
+        if self.dir != dir {
+            self.dir = dir.to_path_buf();
+            self.thumbnails.clear();
+        }
+
+        //Anything still waiting was only visible last frame, the tiles that
+        //are still on screen will ask for it again.
+        let (lock, _) = &*self.queue;
+        for path in lock.lock().unwrap().paths.drain(..) {
+            self.thumbnails.remove(&path);
+        }
+
+        if let Some(receiver) = &self.receiver {
+            while let Ok((path, image)) = receiver.try_recv() {
+                let thumbnail = match image {
+                    Ok(image) => {
+                        let name = path.to_string_lossy();
+                        let texture = ctx.load_texture(name, image, Default::default());
+                        Thumbnail::Loaded(texture, self.frame)
+                    }
+                    Err(_) => Thumbnail::Failed,
+                };
+                self.thumbnails.insert(path, thumbnail);
+            }
+        }
+
+        self.evict();
+    }
+    pub fn get(&mut self, ctx: &Context, path: &Path) -> Option<&TextureHandle> {
+        if !self.thumbnails.contains_key(path) {
+            self.request(ctx, path);
+        }
+
+        match self.thumbnails.get_mut(path) {
+            Some(Thumbnail::Loaded(texture, used)) => {
+                *used = self.frame;
+                Some(texture)
+            }
+            _ => None,
+        }
+    }
+    fn request(&mut self, ctx: &Context, path: &Path) {
+        if self.receiver.is_none() {
+            self.receiver = Some(spawn(ctx, &self.queue));
+        }
+
+        self.thumbnails
+            .insert(path.to_path_buf(), Thumbnail::Pending);
+
+        let (lock, ready) = &*self.queue;
+        lock.lock().unwrap().paths.push(path.to_path_buf());
+        ready.notify_one();
+    }
+    fn evict(&mut self) {
+        let mut loaded: Vec<_> = self
+            .thumbnails
+            .iter()
+            .filter_map(|(path, t)| match t {
+                Thumbnail::Loaded(_, used) => Some((*used, path.clone())),
+                _ => None,
+            })
+            .collect();
+
+        if loaded.len() <= CAPACITY {
+            return;
+        }
+
+        loaded.sort_unstable();
+        for (_, path) in &loaded[..loaded.len() - CAPACITY] {
+            self.thumbnails.remove(path);
+        }
+    }
+}
+
+impl Drop for Thumbnails {
+    fn drop(&mut self) {
+        let (lock, ready) = &*self.queue;
+        lock.lock().unwrap().closed = true;
+        ready.notify_all();
+    }
+}
+
+fn spawn(ctx: &Context, queue: &Shared) -> Receiver<(PathBuf, Result<ColorImage, String>)> {
+    let (sender, receiver) = channel();
+
+    for _ in 0..WORKERS {
+        let ctx = ctx.clone();
+        let queue = queue.clone();
+        let sender = sender.clone();
+
+        thread::spawn(move || loop {
+            let path = {
+                let (lock, ready) = &*queue;
+                let mut queue = lock.lock().unwrap();
+                loop {
+                    if queue.closed {
+                        return;
+                    }
+                    //Newest requests first, they're the tiles on screen right now.
+                    if let Some(path) = queue.paths.pop() {
+                        break path;
+                    }
+                    queue = ready.wait(queue).unwrap();
+                }
+            };
+
+            let image = ex::thumbnail::load(&path).map(|image| {
+                let size = [image.width() as usize, image.height() as usize];
+                ColorImage::from_rgba_unmultiplied(size, image.as_raw())
+            });
+
+            if sender.send((path, image)).is_err() {
+                return;
+            }
+            ctx.request_repaint();
+        });
+    }
+
+    receiver
+}
@@ -0,0 +1,85 @@
+use eframe::egui::Context;
+use ex::file_type::{self, FileType};
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::mpsc::{channel, Receiver, Sender},
+    thread,
+};
+
+//A file and where it is on disk.
+type Request = (PathBuf, PathBuf);
+type Worker = (Sender<Request>, Receiver<(PathBuf, FileType)>);
+
+//The types of the files in a folder. Names are looked up right away, files on the local disk
+//whose name says nothing are sniffed in the background.
+pub struct Types {
+    dir: PathBuf,
+    types: HashMap<PathBuf, FileType>,
+    worker: Option<Worker>,
+}
+
+impl Types {
+    pub fn new() -> Self {
+        Self {
+            dir: PathBuf::new(),
+            types: HashMap::new(),
+            worker: None,
+        }
+    }
+    //Called once per frame, returns true when sniffed types came in.
+    pub fn prepare(&mut self, ctx: &Context, dir: &Path) -> bool {
+        if self.dir != dir {
+            self.dir = dir.to_path_buf();
+            self.types.clear();
+        }
+
+        let (_, receiver) = self.worker.get_or_insert_with(|| spawn(ctx.clone()));
+        let mut changed = false;
+        while let Ok((path, file_type)) = receiver.try_recv() {
+            //Types for a folder that was left are stale.
+            if path.parent() == Some(dir) {
+                self.types.insert(path, file_type);
+                changed = true;
+            }
+        }
+        changed
+    }
+    //`local` is where the file is on disk, servers and archives are never sniffed.
+    pub fn get(&mut self, path: &Path, is_dir: bool, local: Option<PathBuf>) -> FileType {
+        if let Some(file_type) = self.types.get(path) {
+            return file_type.clone();
+        }
+
+        let file_type = if is_dir {
+            file_type::folder()
+        } else {
+            file_type::from_name(path).unwrap_or_else(|| {
+                if let (Some(local), Some((sender, _))) = (local, &self.worker) {
+                    let _ = sender.send((path.to_path_buf(), local));
+                }
+                file_type::from_path(path)
+            })
+        };
+        self.types.insert(path.to_path_buf(), file_type.clone());
+        file_type
+    }
+}
+
+fn spawn(ctx: Context) -> Worker {
+    let (request, requests) = channel::<Request>();
+    let (send, receive) = channel();
+
+    thread::spawn(move || {
+        while let Ok((path, local)) = requests.recv() {
+            if let Some(file_type) = file_type::sniff(&local) {
+                if send.send((path, file_type)).is_err() {
+                    break;
+                }
+                ctx.request_repaint();
+            }
+        }
+    });
+
+    (request, receive)
+}
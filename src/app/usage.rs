@@ -0,0 +1,426 @@
+use eframe::egui::*;
+use ex::{
+    file_type::Category,
+    usage::{self, Node, Usage},
+    Units,
+};
+use std::{
+    path::{Path, PathBuf},
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        mpsc::{channel, Receiver},
+        Arc,
+    },
+    thread,
+};
+
+//Anything smaller than the largest few hundred entries is too small to see anyway.
+const MAX_CELLS: usize = 300;
+
+enum Message {
+    Progress(u64, u64),
+    Done(Option<Usage>),
+}
+
+enum Scan {
+    Running {
+        files: u64,
+        bytes: u64,
+        cancel: Arc<AtomicBool>,
+        receiver: Receiver<Message>,
+    },
+    Done(Usage),
+    Cancelled,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Tab {
+    Treemap,
+    Folders,
+    Types,
+}
+
+pub enum Action {
+    Navigate(PathBuf),
+    Trash(PathBuf),
+    Delete(PathBuf),
+    Close,
+}
+
+pub struct Analyzer {
+    root: PathBuf,
+    tab: Tab,
+    scan: Scan,
+    pub error: Option<String>,
+}
+
+impl Analyzer {
+    pub fn new(ctx: &Context, root: &Path) -> Self {
+        Self {
+            root: root.to_path_buf(),
+            tab: Tab::Treemap,
+            scan: scan(ctx, root),
+            error: None,
+        }
+    }
+    pub fn remove(&mut self, path: &Path) {
+        if let Scan::Done(usage) = &mut self.scan {
+            usage.remove(path);
+        }
+    }
+    //`current` is the folder the browser is in, it's shown if it's part of the scan.
+    pub fn ui(&mut self, ui: &mut Ui, current: &Path, units: Units) -> Option<Action> {
+        if let Scan::Running {
+            files,
+            bytes,
+            receiver,
+            ..
+        } = &mut self.scan
+        {
+            let mut done = None;
+            while let Ok(message) = receiver.try_recv() {
+                match message {
+                    Message::Progress(f, b) => (*files, *bytes) = (f, b),
+                    Message::Done(usage) => done = Some(usage),
+                }
+            }
+            match done {
+                Some(Some(usage)) => self.scan = Scan::Done(usage),
+                Some(None) => self.scan = Scan::Cancelled,
+                None => (),
+            }
+        }
+
+        let mut action = None;
+
+        let usage = match &self.scan {
+            Scan::Running { files, bytes, .. } => {
+                ui.horizontal(|ui| {
+                    ui.spinner();
+                    ui.label(format!(
+                        "Scanning {}: {} files, {}",
+                        self.root.display(),
+                        files,
+                        ex::format_size(*bytes, units)
+                    ));
+                    if ui.button("Cancel").clicked() {
+                        action = Some(Action::Close);
+                    }
+                });
+                return action;
+            }
+            Scan::Cancelled => return Some(Action::Close),
+            Scan::Done(usage) => usage,
+        };
+
+        let node = usage.root.find(current).unwrap_or(&usage.root);
+        let mut rescan = false;
+
+        ui.horizontal(|ui| {
+            ui.style_mut().visuals.button_frame = true;
+
+            let up = ui.add_enabled(node.path != usage.root.path, Button::new("⏶ Up"));
+            if up.clicked() {
+                if let Some(parent) = node.path.parent() {
+                    action = Some(Action::Navigate(parent.to_path_buf()));
+                }
+            }
+
+            ui.label(format!(
+                "{}  {} ({} files)",
+                node.path.display(),
+                ex::format_size(node.bytes, units),
+                usage.files
+            ));
+
+            ui.with_layout(Layout::right_to_left(Align::Center), |ui| {
+                if ui.button("Close").clicked() {
+                    action = Some(Action::Close);
+                }
+                if ui.button("Rescan").clicked() {
+                    rescan = true;
+                }
+                ui.separator();
+                ui.selectable_value(&mut self.tab, Tab::Types, "By type");
+                ui.selectable_value(&mut self.tab, Tab::Folders, "List");
+                ui.selectable_value(&mut self.tab, Tab::Treemap, "Treemap");
+            });
+        });
+
+        if let Some(e) = &self.error {
+            ui.colored_label(ui.visuals().error_fg_color, e);
+        }
+
+        ui.separator();
+
+        if rescan {
+            *self = Analyzer::new(ui.ctx(), &self.root.clone());
+            return action;
+        }
+
+        let inner = match self.tab {
+            Tab::Treemap => treemap(ui, node, units),
+            Tab::Folders => list(ui, node, units),
+            Tab::Types => {
+                types(ui, usage, units);
+                None
+            }
+        };
+
+        action.or(inner)
+    }
+}
+
+impl Drop for Analyzer {
+    fn drop(&mut self) {
+        if let Scan::Running { cancel, .. } = &self.scan {
+            cancel.store(true, Ordering::Relaxed);
+        }
+    }
+}
+
+fn scan(ctx: &Context, root: &Path) -> Scan {
+    let (sender, receiver) = channel();
+    let cancel = Arc::new(AtomicBool::new(false));
+
+    let ctx = ctx.clone();
+    let root = root.to_path_buf();
+    let stop = cancel.clone();
+    thread::spawn(move || {
+        let usage = usage::scan(&root, &stop, |files, bytes| {
+            let _ = sender.send(Message::Progress(files, bytes));
+            ctx.request_repaint();
+        });
+        let _ = sender.send(Message::Done(usage));
+        ctx.request_repaint();
+    });
+
+    Scan::Running {
+        files: 0,
+        bytes: 0,
+        cancel,
+        receiver,
+    }
+}
+
+fn treemap(ui: &mut Ui, node: &Node, units: Units) -> Option<Action> {
+    let mut action = None;
+
+    let (rect, _) = ui.allocate_exact_size(ui.available_size(), Sense::hover());
+    let children: Vec<&Node> = node
+        .children
+        .iter()
+        .filter(|child| child.bytes > 0)
+        .take(MAX_CELLS)
+        .collect();
+    let values: Vec<f32> = children.iter().map(|child| child.bytes as f32).collect();
+
+    for (child, cell) in children.into_iter().zip(squarify(&values, rect)) {
+        let response = ui.interact(cell, ui.id().with(&child.path), Sense::click());
+
+        let mut fill = color(child);
+        if response.hovered() {
+            fill = fill.linear_multiply(1.3);
+        }
+        let painter = ui.painter_at(cell);
+        painter.rect(cell, 0.0, fill, Stroke::new(1.0, Color32::from_gray(20)));
+
+        if cell.width() > 40.0 && cell.height() > 20.0 {
+            painter.text(
+                cell.left_top() + Vec2::new(4.0, 2.0),
+                Align2::LEFT_TOP,
+                format!("{}\n{}", child.name(), ex::format_size(child.bytes, units)),
+                FontId::proportional(13.0),
+                Color32::WHITE,
+            );
+        }
+
+        let response = response.on_hover_text(format!(
+            "{}\n{}",
+            child.path.display(),
+            ex::format_size(child.bytes, units)
+        ));
+
+        if response.clicked() && child.is_dir {
+            action = Some(Action::Navigate(child.path.clone()));
+        }
+
+        response.context_menu(|ui| {
+            if let Some(a) = entry_menu(ui, child) {
+                action = Some(a);
+            }
+        });
+    }
+
+    action
+}
+
+fn list(ui: &mut Ui, node: &Node, units: Units) -> Option<Action> {
+    let mut action = None;
+    let total = node.bytes.max(1) as f32;
+
+    ScrollArea::vertical().auto_shrink([false; 2]).show_rows(
+        ui,
+        20.0,
+        node.children.len(),
+        |ui, range| {
+            for child in &node.children[range] {
+                ui.horizontal(|ui| {
+                    let icon = if child.is_dir { "🗀" } else { "🖹" };
+                    let name = ui.add_sized(
+                        Vec2::new(250.0, 20.0),
+                        Button::new(format!("{icon}  {}", child.name())).wrap(false),
+                    );
+                    if name.clicked() && child.is_dir {
+                        action = Some(Action::Navigate(child.path.clone()));
+                    }
+                    name.context_menu(|ui| {
+                        if let Some(a) = entry_menu(ui, child) {
+                            action = Some(a);
+                        }
+                    });
+
+                    let fraction = child.bytes as f32 / total;
+                    ui.add(
+                        ProgressBar::new(fraction)
+                            .text(ex::format_size(child.bytes, units))
+                            .desired_width(ui.available_width()),
+                    );
+                });
+            }
+        },
+    );
+
+    action
+}
+
+fn types(ui: &mut Ui, usage: &Usage, units: Units) {
+    let total = usage.root.bytes.max(1) as f32;
+
+    ScrollArea::vertical().auto_shrink([false; 2]).show_rows(
+        ui,
+        20.0,
+        usage.types.len(),
+        |ui, range| {
+            for t in &usage.types[range] {
+                ui.horizontal(|ui| {
+                    ui.add_sized(
+                        Vec2::new(250.0, 20.0),
+                        Label::new(format!("{}  {}", t.category.icon(), t.name)).wrap(false),
+                    );
+                    let fraction = t.bytes as f32 / total;
+                    let text = format!("{} in {} files", ex::format_size(t.bytes, units), t.files);
+                    ui.add(
+                        ProgressBar::new(fraction)
+                            .text(text)
+                            .desired_width(ui.available_width()),
+                    );
+                });
+            }
+        },
+    );
+}
+
+fn entry_menu(ui: &mut Ui, node: &Node) -> Option<Action> {
+    let mut action = None;
+
+    if node.is_dir && ui.button("Open").clicked() {
+        action = Some(Action::Navigate(node.path.clone()));
+        ui.close_menu();
+    }
+
+    if ui.button("Move to Trash").clicked() {
+        action = Some(Action::Trash(node.path.clone()));
+        ui.close_menu();
+    }
+
+    if ui.button("Delete permanently").clicked() {
+        action = Some(Action::Delete(node.path.clone()));
+        ui.close_menu();
+    }
+
+    action
+}
+
+fn color(node: &Node) -> Color32 {
+    if node.is_dir {
+        return Color32::from_rgb(60, 100, 160);
+    }
+
+    match ex::file_type::from_path(&node.path).category {
+        Category::Image => Color32::from_rgb(170, 110, 50),
+        Category::Audio => Color32::from_rgb(140, 70, 150),
+        Category::Video => Color32::from_rgb(160, 60, 60),
+        Category::Archive => Color32::from_rgb(150, 130, 50),
+        Category::Code => Color32::from_rgb(60, 140, 100),
+        Category::Document => Color32::from_rgb(70, 130, 160),
+        Category::Executable => Color32::from_rgb(110, 110, 60),
+        _ => Color32::from_gray(100),
+    }
+}
+
+//Squarified treemap, `values` must be sorted largest first.
+//https://www.win.tue.nl/~vanwijk/stm.pdf
+fn squarify(values: &[f32], rect: Rect) -> Vec<Rect> {
+    let total: f32 = values.iter().sum();
+    if total <= 0.0 || rect.area() <= 0.0 {
+        return Vec::new();
+    }
+
+    let scale = rect.area() / total;
+    let areas: Vec<f32> = values.iter().map(|v| v * scale).collect();
+
+    let worst = |row: &[f32], side: f32| {
+        let sum: f32 = row.iter().sum();
+        let max = row.iter().cloned().fold(f32::MIN, f32::max);
+        let min = row.iter().cloned().fold(f32::MAX, f32::min);
+        let side = side * side;
+        let sum = sum * sum;
+        (side * max / sum).max(sum / (side * min))
+    };
+
+    let mut rects = Vec::with_capacity(areas.len());
+    let mut rect = rect;
+    let mut i = 0;
+
+    while i < areas.len() {
+        let side = rect.width().min(rect.height());
+
+        //Keep adding to the row while it makes the cells more square.
+        let mut end = i + 1;
+        while end < areas.len() && worst(&areas[i..end + 1], side) <= worst(&areas[i..end], side) {
+            end += 1;
+        }
+
+        let row = &areas[i..end];
+        let thickness = row.iter().sum::<f32>() / side;
+
+        if rect.width() >= rect.height() {
+            let mut y = rect.top();
+            for area in row {
+                let height = area / thickness;
+                rects.push(Rect::from_min_size(
+                    Pos2::new(rect.left(), y),
+                    Vec2::new(thickness, height),
+                ));
+                y += height;
+            }
+            rect.min.x += thickness;
+        } else {
+            let mut x = rect.left();
+            for area in row {
+                let width = area / thickness;
+                rects.push(Rect::from_min_size(
+                    Pos2::new(x, rect.top()),
+                    Vec2::new(width, thickness),
+                ));
+                x += width;
+            }
+            rect.min.y += thickness;
+        }
+
+        i = end;
+    }
+
+    rects
+}
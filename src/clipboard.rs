@@ -0,0 +1,291 @@
+use crate::vfs;
+use std::{
+    path::PathBuf,
+    sync::mpsc::{channel, Sender},
+    thread,
+    time::Duration,
+};
+use url::Url;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Mode {
+    Copy,
+    Cut,
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Files {
+    pub mode: Mode,
+    pub paths: Vec<PathBuf>,
+}
+
+//Files copied or cut in any tab. On Linux they're also put on the desktop clipboard so other
+//file managers can paste them and the other way around.
+#[derive(Default)]
+pub struct Clipboard {
+    files: Option<Files>,
+    //What was put on the desktop clipboard, files on servers can't be.
+    shared: Option<Files>,
+    worker: Option<Sender<Command>>,
+}
+
+//The desktop clipboard is reached through other programs, so it's used from a thread of its
+//own. Commands run in order, a paste always sees the copy before it.
+enum Command {
+    Set(Files),
+    //Only what was set is cleared, anything copied since in another program stays.
+    Clear(Files),
+    //What's there now was copied before the files in the app, so it's ignored until it changes.
+    Forget,
+    Get(Sender<Option<Files>>),
+}
+
+//How long a paste waits for the desktop clipboard before using the files copied in the app.
+const TIMEOUT: Duration = Duration::from_millis(500);
+
+impl Clipboard {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn set(&mut self, mode: Mode, paths: Vec<PathBuf>) {
+        let local: Vec<PathBuf> = paths
+            .iter()
+            .filter(|path| !vfs::is_remote(path))
+            .cloned()
+            .collect();
+        if let Some(shared) = self.shared.take() {
+            self.send(Command::Clear(shared));
+        }
+        //Whatever was there before would win over files that are all on servers.
+        if local.is_empty() {
+            self.send(Command::Forget);
+        } else {
+            let shared = Files { mode, paths: local };
+            //The desktop clipboard is a bonus, the files can still be pasted inside the app.
+            self.send(Command::Set(shared.clone()));
+            self.shared = Some(shared);
+        }
+        self.files = Some(Files { mode, paths });
+    }
+
+    //Files on the desktop clipboard win since they may have been copied somewhere else. If
+    //they're still the ones put there by `set` the full list is used, servers included.
+    pub fn get(&mut self) -> Option<Files> {
+        let (sender, receiver) = channel();
+        self.send(Command::Get(sender));
+        match receiver.recv_timeout(TIMEOUT) {
+            Ok(Some(files)) if Some(&files) != self.shared.as_ref() => Some(files),
+            _ => self.files.clone(),
+        }
+    }
+
+    //Files that were cut are gone once they've been pasted.
+    pub fn clear(&mut self) {
+        if let Some(shared) = self.shared.take() {
+            self.send(Command::Clear(shared));
+        }
+        self.files = None;
+    }
+
+    fn send(&mut self, command: Command) {
+        let worker = self.worker.get_or_insert_with(spawn);
+        let _ = worker.send(command);
+    }
+}
+
+fn spawn() -> Sender<Command> {
+    let (sender, receiver) = channel();
+    thread::spawn(move || {
+        let mut forgotten = None;
+        for command in receiver {
+            match command {
+                Command::Set(files) => {
+                    forgotten = None;
+                    let _ = system::set(&files);
+                }
+                Command::Forget => forgotten = system::get().ok().flatten(),
+                Command::Clear(files) => {
+                    if let Ok(Some(current)) = system::get() {
+                        if current == files {
+                            let _ = system::clear();
+                        }
+                    }
+                }
+                Command::Get(reply) => {
+                    let files = system::get().ok().flatten();
+                    let _ = reply.send(files.filter(|files| Some(files) != forgotten.as_ref()));
+                }
+            }
+        }
+    });
+    sender
+}
+
+pub fn uri_list(paths: &[PathBuf]) -> String {
+    paths
+        .iter()
+        .filter_map(|path| Url::from_file_path(path).ok())
+        .map(String::from)
+        .collect::<Vec<String>>()
+        .join("\r\n")
+}
+
+//The format used by Nautilus, Nemo, Caja and Thunar, it's the only common way to say "cut".
+pub fn gnome_copied_files(files: &Files) -> String {
+    let mode = match files.mode {
+        Mode::Copy => "copy",
+        Mode::Cut => "cut",
+    };
+    format!("{mode}\n{}", uri_list(&files.paths).replace("\r\n", "\n"))
+}
+
+//Reads either of the formats above, plain absolute paths are accepted too.
+pub fn parse(text: &str) -> Option<Files> {
+    let mut lines = text.lines().map(str::trim).peekable();
+    let mode = match lines.peek() {
+        Some(&"cut") => Mode::Cut,
+        _ => Mode::Copy,
+    };
+    if matches!(lines.peek(), Some(&"copy") | Some(&"cut")) {
+        lines.next();
+    }
+
+    let mut paths = Vec::new();
+    for line in lines.filter(|line| !line.is_empty() && !line.starts_with('#')) {
+        let path = match Url::parse(line) {
+            Ok(url) if url.scheme() == "file" => url.to_file_path().ok()?,
+            _ if PathBuf::from(line).is_absolute() => PathBuf::from(line),
+            _ => return None,
+        };
+        paths.push(path);
+    }
+
+    if paths.is_empty() {
+        None
+    } else {
+        Some(Files { mode, paths })
+    }
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod system {
+    use super::{gnome_copied_files, parse, uri_list, Files, Mode};
+    use std::{
+        env,
+        io::{self, Write},
+        process::{Command, Stdio},
+    };
+
+    const GNOME: &str = "x-special/gnome-copied-files";
+    const URI_LIST: &str = "text/uri-list";
+
+    fn wayland() -> bool {
+        env::var_os("WAYLAND_DISPLAY").is_some()
+    }
+
+    //Only one format can be offered at a time, so the cut flag is kept when it matters and
+    //the more widely understood uri list is used otherwise.
+    pub fn set(files: &Files) -> io::Result<()> {
+        let (target, text) = match files.mode {
+            Mode::Copy => (URI_LIST, uri_list(&files.paths)),
+            Mode::Cut => (GNOME, gnome_copied_files(files)),
+        };
+        write(target, &text)
+    }
+
+    pub fn clear() -> io::Result<()> {
+        if wayland() {
+            Command::new("wl-copy").arg("--clear").status().map(|_| ())
+        } else {
+            write("text/plain", "")
+        }
+    }
+
+    //`wl-copy` and `xclip` both fork into the background to serve the clipboard.
+    fn write(target: &str, text: &str) -> io::Result<()> {
+        let mut command = if wayland() {
+            let mut command = Command::new("wl-copy");
+            command.args(["--type", target]);
+            command
+        } else {
+            let mut command = Command::new("xclip");
+            command.args(["-selection", "clipboard", "-t", target, "-i"]);
+            command
+        };
+
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(text.as_bytes())?;
+        }
+        child.wait()?;
+        Ok(())
+    }
+
+    fn read(args: &[&str]) -> io::Result<String> {
+        let output = if wayland() {
+            Command::new("wl-paste")
+                .arg("--no-newline")
+                .args(args)
+                .output()?
+        } else {
+            Command::new("xclip")
+                .args(["-selection", "clipboard", "-o"])
+                .args(args)
+                .output()?
+        };
+        if !output.status.success() {
+            return Err(io::Error::other("Clipboard is empty"));
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    pub fn get() -> io::Result<Option<Files>> {
+        let types = if wayland() {
+            read(&["--list-types"])?
+        } else {
+            read(&["-t", "TARGETS"])?
+        };
+
+        for target in [GNOME, URI_LIST] {
+            if types.lines().any(|line| line.trim() == target) {
+                let args: &[&str] = if wayland() {
+                    &["--type", target]
+                } else {
+                    &["-t", target]
+                };
+                return Ok(parse(&read(args)?));
+            }
+        }
+        Ok(None)
+    }
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+mod system {
+    use super::Files;
+    use std::io;
+
+    fn unsupported() -> io::Error {
+        io::Error::new(
+            io::ErrorKind::Unsupported,
+            "Files can't be put on the clipboard on this platform",
+        )
+    }
+
+    pub fn set(_: &Files) -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn clear() -> io::Result<()> {
+        Err(unsupported())
+    }
+
+    pub fn get() -> io::Result<Option<Files>> {
+        Err(unsupported())
+    }
+}
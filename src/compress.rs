@@ -0,0 +1,527 @@
+//Creating and extracting archives. Anything that can be browsed can be extracted.
+use crate::{
+    vfs::archive::{self, clean, zip_time},
+    Error, Result,
+};
+use chrono::{DateTime as Time, Datelike, Local, Timelike};
+use flate2::{write::GzEncoder, Compression};
+use jwalk::WalkDir;
+use std::{
+    cell::Cell,
+    fs::{self, File},
+    io::{self, BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    ops::RangeInclusive,
+    path::{Path, PathBuf},
+    rc::Rc,
+    time::SystemTime,
+};
+use zip::{write::FileOptions, CompressionMethod, ZipArchive, ZipWriter};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Zip,
+    TarGz,
+    TarZst,
+}
+
+impl Format {
+    pub const ALL: [Format; 3] = [Format::Zip, Format::TarGz, Format::TarZst];
+
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Zip => "zip",
+            Format::TarGz => "tar.gz",
+            Format::TarZst => "tar.zst",
+        }
+    }
+
+    pub fn levels(self) -> RangeInclusive<u32> {
+        match self {
+            Format::Zip | Format::TarGz => 0..=9,
+            Format::TarZst => 1..=19,
+        }
+    }
+
+    pub fn default_level(self) -> u32 {
+        match self {
+            Format::Zip | Format::TarGz => 6,
+            Format::TarZst => 3,
+        }
+    }
+}
+
+pub enum Update {
+    //How much there is to get through, sent before anything else. Bytes here, items for jobs
+    //that can't know the size up front.
+    Total(u64),
+    //An entry was written along with how much is done so far.
+    Done { path: PathBuf, done: u64 },
+    //The entry was skipped, everything else carries on.
+    Failed(Error),
+}
+
+enum Writer {
+    Zip(ZipWriter<BufWriter<File>>, FileOptions),
+    TarGz(tar::Builder<GzEncoder<BufWriter<File>>>),
+    TarZst(tar::Builder<zstd::Encoder<'static, BufWriter<File>>>),
+}
+
+//Zip files can't hold anything from before 1980.
+fn zip_date(time: SystemTime) -> zip::DateTime {
+    let time: Time<Local> = time.into();
+    zip::DateTime::from_date_and_time(
+        time.year().try_into().unwrap_or(0),
+        time.month() as u8,
+        time.day() as u8,
+        time.hour() as u8,
+        time.minute() as u8,
+        time.second() as u8,
+    )
+    .unwrap_or_default()
+}
+
+//Zip and tar both separate folders with a forward slash.
+fn entry_name(name: &Path) -> String {
+    let parts: Vec<_> = name.iter().map(|part| part.to_string_lossy()).collect();
+    parts.join("/")
+}
+
+impl Writer {
+    fn new(file: File, format: Format, level: u32) -> io::Result<Self> {
+        let file = BufWriter::new(file);
+        let mut writer = match format {
+            Format::Zip => {
+                let options = FileOptions::default()
+                    .compression_method(CompressionMethod::Deflated)
+                    .compression_level(Some(level as i32));
+                return Ok(Writer::Zip(ZipWriter::new(file), options));
+            }
+            Format::TarGz => Writer::TarGz(tar::Builder::new(GzEncoder::new(
+                file,
+                Compression::new(level),
+            ))),
+            Format::TarZst => {
+                Writer::TarZst(tar::Builder::new(zstd::Encoder::new(file, level as i32)?))
+            }
+        };
+        //Links are stored as links.
+        match &mut writer {
+            Writer::TarGz(builder) => builder.follow_symlinks(false),
+            Writer::TarZst(builder) => builder.follow_symlinks(false),
+            Writer::Zip(..) => (),
+        }
+        Ok(writer)
+    }
+
+    fn add(&mut self, name: &Path, item: Item, metadata: &fs::Metadata) -> io::Result<()> {
+        match self {
+            Writer::Zip(zip, options) => {
+                let mut options = options
+                    .last_modified_time(zip_date(metadata.modified()?))
+                    .large_file(metadata.len() > u32::MAX as u64);
+                #[cfg(unix)]
+                {
+                    use std::os::unix::fs::PermissionsExt;
+                    options = options.unix_permissions(metadata.permissions().mode() & 0o7777);
+                }
+
+                let name = entry_name(name);
+                match item {
+                    Item::Dir => zip.add_directory(name, options)?,
+                    Item::Link(target) => {
+                        zip.add_symlink(name, target.to_string_lossy(), options)?
+                    }
+                    Item::File(mut file) => {
+                        zip.start_file(name, options)?;
+                        io::copy(&mut file, zip)?;
+                    }
+                }
+                Ok(())
+            }
+            Writer::TarGz(builder) => append(builder, name, item, metadata),
+            Writer::TarZst(builder) => append(builder, name, item, metadata),
+        }
+    }
+
+    fn finish(self) -> io::Result<()> {
+        let mut file = match self {
+            Writer::Zip(mut zip, _) => zip.finish()?,
+            Writer::TarGz(builder) => builder.into_inner()?.finish()?,
+            Writer::TarZst(builder) => builder.into_inner()?.finish()?,
+        };
+        file.flush()
+    }
+}
+
+fn append<W: Write>(
+    builder: &mut tar::Builder<W>,
+    name: &Path,
+    item: Item,
+    metadata: &fs::Metadata,
+) -> io::Result<()> {
+    let mut header = tar::Header::new_gnu();
+    header.set_metadata(metadata);
+    match item {
+        Item::Dir => builder.append_data(&mut header, name, io::empty()),
+        Item::Link(target) => builder.append_link(&mut header, name, target),
+        Item::File(file) => builder.append_data(&mut header, name, file),
+    }
+}
+
+//Anything that can fail is opened before it's added, so a half written entry always means
+//the whole archive failed.
+enum Item {
+    Dir,
+    Link(PathBuf),
+    File(File),
+}
+
+fn item(path: &Path, metadata: &fs::Metadata) -> io::Result<Item> {
+    if metadata.is_dir() {
+        Ok(Item::Dir)
+    } else if metadata.is_symlink() {
+        fs::read_link(path).map(Item::Link)
+    } else {
+        File::open(path).map(Item::File)
+    }
+}
+
+//Everything in `paths` goes into a new archive at `to`, named relative to the folder they're in.
+//Items that can't be read are skipped, the archive is removed if it can't be written.
+pub fn compress(
+    paths: &[PathBuf],
+    to: &Path,
+    format: Format,
+    level: u32,
+    mut update: impl FnMut(Update),
+) -> Result<()> {
+    let mut entries = Vec::new();
+    let mut total = 0;
+    for path in paths {
+        let base = path.parent().unwrap_or(path);
+        for entry in WalkDir::new(path)
+            .skip_hidden(false)
+            .follow_links(false)
+            .sort(true)
+        {
+            match entry {
+                Ok(entry) => {
+                    let path = entry.path();
+                    let Ok(name) = path.strip_prefix(base).map(Path::to_path_buf) else {
+                        continue;
+                    };
+                    match path.symlink_metadata() {
+                        Ok(metadata) => {
+                            if metadata.is_file() {
+                                total += metadata.len();
+                            }
+                            entries.push((path, name, metadata));
+                        }
+                        Err(e) => update(Update::Failed(Error::io(&path, e))),
+                    }
+                }
+                Err(e) => update(Update::Failed(Error::io(path, io::Error::other(e)))),
+            }
+        }
+    }
+    update(Update::Total(total));
+
+    let file = File::options()
+        .write(true)
+        .create_new(true)
+        .open(to)
+        .map_err(|e| Error::io(to, e))?;
+    let result = write(file, format, level, entries, &mut update);
+    if result.is_err() {
+        let _ = fs::remove_file(to);
+    }
+    result.map_err(|e| Error::io(to, e))
+}
+
+fn write(
+    file: File,
+    format: Format,
+    level: u32,
+    entries: Vec<(PathBuf, PathBuf, fs::Metadata)>,
+    update: &mut impl FnMut(Update),
+) -> io::Result<()> {
+    let mut writer = Writer::new(file, format, level)?;
+    let mut bytes = 0;
+    for (path, name, metadata) in entries {
+        match item(&path, &metadata) {
+            Ok(item) => writer.add(&name, item, &metadata)?,
+            Err(e) => {
+                update(Update::Failed(Error::io(&path, e)));
+                continue;
+            }
+        }
+        if metadata.is_file() {
+            bytes += metadata.len();
+        }
+        update(Update::Done { path, done: bytes });
+    }
+    writer.finish()
+}
+
+//Keeps track of how far into the archive extraction is.
+struct Counter<R> {
+    inner: R,
+    position: Rc<Cell<u64>>,
+}
+
+impl<R: Read> Read for Counter<R> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.inner.read(buf)?;
+        self.position.set(self.position.get() + n as u64);
+        Ok(n)
+    }
+}
+
+impl<R: Seek> Seek for Counter<R> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let position = self.inner.seek(pos)?;
+        self.position.set(position);
+        Ok(position)
+    }
+}
+
+fn outside(path: &Path) -> Error {
+    Error::io(
+        path,
+        io::Error::new(
+            io::ErrorKind::InvalidInput,
+            "Entry would end up outside the destination",
+        ),
+    )
+}
+
+//Entries can't escape `root`, either with `..` in their name or through a link that's already
+//there.
+fn inside(root: &Path, target: &Path) -> Result<()> {
+    let existing = target
+        .ancestors()
+        .find(|path| path.symlink_metadata().is_ok())
+        .unwrap_or(root);
+    match existing.canonicalize() {
+        Ok(path) if path.starts_with(root) => Ok(()),
+        _ => Err(outside(target)),
+    }
+}
+
+//Unpacks `from` into the folder `to`, which has to exist. Nothing that's already there is
+//replaced, those entries are skipped.
+pub fn extract(from: &Path, to: &Path, mut update: impl FnMut(Update)) -> Result<()> {
+    let format =
+        archive::Format::from_path(from).ok_or_else(|| Error::InvalidName(from.to_path_buf()))?;
+    let file = File::open(from).map_err(|e| Error::io(from, e))?;
+    let len = file.metadata().map_err(|e| Error::io(from, e))?.len();
+    let root = to.canonicalize().map_err(|e| Error::io(to, e))?;
+    update(Update::Total(len));
+
+    let position = Rc::new(Cell::new(0));
+    let reader = Counter {
+        inner: BufReader::new(file),
+        position: position.clone(),
+    };
+
+    if format == archive::Format::Zip {
+        let mut zip = ZipArchive::new(reader).map_err(|e| Error::io(from, e.into()))?;
+        for i in 0..zip.len() {
+            let mut file = match zip.by_index(i) {
+                Ok(file) => file,
+                Err(e) => {
+                    update(Update::Failed(Error::io(from, e.into())));
+                    continue;
+                }
+            };
+            let target = root.join(file.name());
+            let result = match file.enclosed_name().and_then(clean) {
+                Some(name) => unzip(&mut file, &root, &root.join(name)),
+                None => Err(outside(&target)),
+            };
+            match result {
+                Ok(()) => update(Update::Done {
+                    path: target,
+                    done: position.get(),
+                }),
+                Err(e) => update(Update::Failed(e)),
+            }
+        }
+        return Ok(());
+    }
+
+    let decoder = format
+        .decoder(Box::new(reader))
+        .map_err(|e| Error::io(from, e))?;
+    let mut tar = tar::Archive::new(decoder);
+    tar.set_overwrite(false);
+    for entry in tar.entries().map_err(|e| Error::io(from, e))? {
+        let mut entry = match entry {
+            Ok(entry) => entry,
+            Err(e) => {
+                update(Update::Failed(Error::io(from, e)));
+                continue;
+            }
+        };
+        let target = root.join(entry.path().unwrap_or_default());
+        //Links and `..` are checked again while unpacking.
+        let result = inside(&root, &target).and_then(|_| match entry.unpack_in(&root) {
+            Ok(true) => Ok(()),
+            Ok(false) => Err(outside(&target)),
+            Err(e) => Err(Error::io(&target, e)),
+        });
+        match result {
+            Ok(()) => update(Update::Done {
+                path: target,
+                done: position.get(),
+            }),
+            Err(e) => update(Update::Failed(e)),
+        }
+    }
+    Ok(())
+}
+
+fn unzip(file: &mut zip::read::ZipFile, root: &Path, target: &Path) -> Result<()> {
+    inside(root, target)?;
+    if file.is_dir() {
+        return fs::create_dir_all(target).map_err(|e| Error::io(target, e));
+    }
+    if let Some(parent) = target.parent() {
+        fs::create_dir_all(parent).map_err(|e| Error::io(parent, e))?;
+    }
+
+    //Links are written out as files holding their target, they could point anywhere.
+    let mut out = File::options()
+        .write(true)
+        .create_new(true)
+        .open(target)
+        .map_err(|e| Error::io(target, e))?;
+    io::copy(file, &mut out).map_err(|e| Error::io(target, e))?;
+    if let Some(time) = zip_time(file.last_modified()) {
+        out.set_modified(time).map_err(|e| Error::io(target, e))?;
+    }
+    #[cfg(unix)]
+    if let Some(mode) = file.unix_mode() {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(target, fs::Permissions::from_mode(mode & 0o777))
+            .map_err(|e| Error::io(target, e))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //Each test gets its own names for things outside the destination.
+    struct Fixture {
+        dir: tempfile::TempDir,
+        outside: PathBuf,
+        absolute: PathBuf,
+    }
+
+    impl Fixture {
+        fn new(name: &str) -> Self {
+            let dir = tempfile::tempdir().unwrap();
+            fs::create_dir(dir.path().join("to")).unwrap();
+            fs::create_dir(dir.path().join("outside")).unwrap();
+            Self {
+                outside: dir.path().join("outside"),
+                absolute: std::env::temp_dir().join(format!("ex-{name}-absolute")),
+                dir,
+            }
+        }
+
+        fn to(&self) -> PathBuf {
+            self.dir.path().join("to")
+        }
+
+        //Returns the entries that were written and how many failed.
+        fn extract(&self, archive: &Path) -> (Vec<PathBuf>, usize) {
+            let mut done = Vec::new();
+            let mut failed = 0;
+            extract(archive, &self.to(), |update| match update {
+                Update::Done { path, .. } => done.push(path),
+                Update::Failed(_) => failed += 1,
+                Update::Total(_) => (),
+            })
+            .unwrap();
+            (done, failed)
+        }
+
+        fn assert_contained(&self) {
+            assert!(!self.dir.path().join("evil").exists());
+            assert!(!self.absolute.exists());
+            assert_eq!(fs::read_dir(&self.outside).unwrap().count(), 0);
+        }
+    }
+
+    #[test]
+    fn zip_entries_stay_inside() {
+        let fixture = Fixture::new("zip");
+        let archive = fixture.dir.path().join("bad.zip");
+        let mut zip = ZipWriter::new(File::create(&archive).unwrap());
+        let options = FileOptions::default();
+        for name in ["../evil", fixture.absolute.to_str().unwrap()] {
+            zip.start_file(name, options).unwrap();
+            zip.write_all(b"evil").unwrap();
+        }
+        zip.add_symlink("link", fixture.outside.to_str().unwrap(), options)
+            .unwrap();
+        zip.start_file("link/file", options).unwrap();
+        zip.write_all(b"evil").unwrap();
+        zip.start_file("ok", options).unwrap();
+        zip.write_all(b"ok").unwrap();
+        zip.finish().unwrap();
+
+        let (done, failed) = fixture.extract(&archive);
+        assert_eq!(failed, 3);
+        fixture.assert_contained();
+        //Links are written out as plain files.
+        let to = fixture.to().canonicalize().unwrap();
+        assert_eq!(done, [to.join("link"), to.join("ok")]);
+        assert!(to.join("link").symlink_metadata().unwrap().is_file());
+        assert_eq!(fs::read(to.join("ok")).unwrap(), b"ok");
+    }
+
+    #[cfg(unix)]
+    //`tar::Builder` refuses names like these, so they're written into the header directly.
+    fn append(tar: &mut tar::Builder<File>, name: &str, kind: tar::EntryType, link: Option<&Path>) {
+        let mut header = tar::Header::new_gnu();
+        let bytes = name.as_bytes();
+        header.as_gnu_mut().unwrap().name[..bytes.len()].copy_from_slice(bytes);
+        header.set_entry_type(kind);
+        header.set_mode(0o644);
+        if let Some(link) = link {
+            header.set_link_name(link).unwrap();
+        }
+        let data: &[u8] = if kind.is_file() { b"evil" } else { b"" };
+        header.set_size(data.len() as u64);
+        header.set_cksum();
+        tar.append(&header, data).unwrap();
+    }
+
+    //Making links needs extra rights on Windows.
+    #[test]
+    #[cfg(unix)]
+    fn tar_entries_stay_inside() {
+        let fixture = Fixture::new("tar");
+        let archive = fixture.dir.path().join("bad.tar");
+        let mut tar = tar::Builder::new(File::create(&archive).unwrap());
+        let regular = tar::EntryType::Regular;
+        append(&mut tar, "../evil", regular, None);
+        append(&mut tar, fixture.absolute.to_str().unwrap(), regular, None);
+        let symlink = tar::EntryType::Symlink;
+        append(&mut tar, "link", symlink, Some(&fixture.outside));
+        append(&mut tar, "link/file", regular, None);
+        append(&mut tar, "ok", regular, None);
+        tar.finish().unwrap();
+        drop(tar);
+
+        let (done, failed) = fixture.extract(&archive);
+        assert_eq!(failed, 3);
+        fixture.assert_contained();
+        let to = fixture.to().canonicalize().unwrap();
+        assert_eq!(done, [to.join("link"), to.join("ok")]);
+    }
+}
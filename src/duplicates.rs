@@ -0,0 +1,266 @@
+use jwalk::WalkDir;
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::SystemTime,
+};
+
+//Files are first compared on this many bytes from the start before hashing them whole.
+const PARTIAL: usize = 16 * 1024;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Progress {
+    Scanning { files: u64 },
+    Hashing { done: u64, total: u64 },
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Keep {
+    Newest,
+    Oldest,
+    ShortestPath,
+}
+
+#[derive(Clone, Debug)]
+pub struct Duplicate {
+    pub path: PathBuf,
+    pub modified: Option<SystemTime>,
+}
+
+//Files with identical contents.
+#[derive(Clone, Debug)]
+pub struct Group {
+    pub size: u64,
+    pub files: Vec<Duplicate>,
+}
+
+impl Group {
+    //Space that would be freed by keeping a single copy.
+    pub fn wasted(&self) -> u64 {
+        self.size * (self.files.len() as u64).saturating_sub(1)
+    }
+
+    //Index of the file that the rule would keep.
+    pub fn keep(&self, keep: Keep) -> usize {
+        let files = self.files.iter().enumerate();
+        let i = match keep {
+            Keep::Newest => files.max_by_key(|(_, file)| file.modified),
+            Keep::Oldest => files.min_by_key(|(_, file)| file.modified),
+            Keep::ShortestPath => files.min_by_key(|(_, file)| file.path.as_os_str().len()),
+        };
+        i.map(|(i, _)| i).unwrap_or(0)
+    }
+}
+
+//Groups by size, then by a hash of the first few KB, then by a hash of the whole file.
+//Returns `None` if cancelled.
+pub fn find(
+    root: &Path,
+    cancel: &AtomicBool,
+    mut progress: impl FnMut(Progress),
+) -> Option<Vec<Group>> {
+    let mut sizes: HashMap<u64, Vec<PathBuf>> = HashMap::new();
+    let mut files: u64 = 0;
+    //Hard links to a file already found share its space, so they aren't duplicates.
+    let mut seen = HashSet::new();
+
+    for entry in WalkDir::new(root)
+        .skip_hidden(false)
+        .follow_links(false)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.file_type().is_file())
+    {
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let Ok(metadata) = entry.metadata() else {
+            continue;
+        };
+        //Every empty file is the same, that isn't useful.
+        let size = metadata.len();
+        if size == 0 {
+            continue;
+        }
+        if let Some(id) = file_id(&metadata) {
+            if !seen.insert(id) {
+                continue;
+            }
+        }
+        sizes.entry(size).or_default().push(entry.path());
+
+        files += 1;
+        if files.is_multiple_of(1000) {
+            progress(Progress::Scanning { files });
+        }
+    }
+
+    let candidates: Vec<(u64, Vec<PathBuf>)> = sizes
+        .into_iter()
+        .filter(|(_, paths)| paths.len() > 1)
+        .collect();
+
+    let total = candidates.iter().map(|(_, paths)| paths.len() as u64).sum();
+    let mut done = 0;
+    let mut groups = Vec::new();
+
+    for (size, paths) in candidates {
+        let mut partial: HashMap<blake3::Hash, Vec<PathBuf>> = HashMap::new();
+        for path in paths {
+            if cancel.load(Ordering::Relaxed) {
+                return None;
+            }
+            if let Ok(hash) = hash(&path, Some(PARTIAL)) {
+                partial.entry(hash).or_default().push(path);
+            }
+            done += 1;
+            progress(Progress::Hashing { done, total });
+        }
+
+        for paths in partial.into_values().filter(|paths| paths.len() > 1) {
+            //Small files were already hashed completely.
+            let full: Vec<Vec<PathBuf>> = if size as usize <= PARTIAL {
+                vec![paths]
+            } else {
+                let mut full: HashMap<blake3::Hash, Vec<PathBuf>> = HashMap::new();
+                for path in paths {
+                    if cancel.load(Ordering::Relaxed) {
+                        return None;
+                    }
+                    if let Ok(hash) = hash(&path, None) {
+                        full.entry(hash).or_default().push(path);
+                    }
+                }
+                full.into_values().collect()
+            };
+
+            for paths in full.into_iter().filter(|paths| paths.len() > 1) {
+                let files = paths
+                    .into_iter()
+                    .map(|path| Duplicate {
+                        modified: path.metadata().and_then(|m| m.modified()).ok(),
+                        path,
+                    })
+                    .collect();
+                groups.push(Group { size, files });
+            }
+        }
+    }
+
+    groups.sort_unstable_by_key(|group| std::cmp::Reverse(group.wasted()));
+    Some(groups)
+}
+
+fn hash(path: &Path, limit: Option<usize>) -> io::Result<blake3::Hash> {
+    let mut hasher = blake3::Hasher::new();
+    let file = fs::File::open(path)?;
+    match limit {
+        Some(limit) => {
+            let mut bytes = Vec::with_capacity(limit);
+            file.take(limit as u64).read_to_end(&mut bytes)?;
+            hasher.update(&bytes);
+        }
+        None => {
+            hasher.update_reader(file)?;
+        }
+    }
+    Ok(hasher.finalize())
+}
+
+//The device and inode, which hard links to the same file share.
+#[cfg(unix)]
+fn file_id(metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    use std::os::unix::fs::MetadataExt;
+    Some((metadata.dev(), metadata.ino()))
+}
+
+#[cfg(not(unix))]
+fn file_id(_metadata: &fs::Metadata) -> Option<(u64, u64)> {
+    None
+}
+
+//Replaces `duplicate` with a hard link to `original`, both must be on the same drive.
+//Files from a group of `size` bytes that have changed since the scan are left alone.
+pub fn hard_link(original: &Duplicate, duplicate: &Duplicate, size: u64) -> io::Result<()> {
+    let (from, to) = (original.path.as_path(), duplicate.path.as_path());
+    let (a, b) = (fs::metadata(from)?, fs::metadata(to)?);
+
+    //Renaming onto another name for the same file does nothing, the temporary link would stay.
+    if file_id(&a).is_some() && file_id(&a) == file_id(&b) {
+        return Ok(());
+    }
+
+    let unchanged = |file: &Duplicate, metadata: &fs::Metadata| {
+        metadata.len() == size && metadata.modified().ok() == file.modified
+    };
+    if !unchanged(original, &a) || !unchanged(duplicate, &b) || hash(from, None)? != hash(to, None)?
+    {
+        return Err(io::Error::other(
+            "The file has changed since it was found, scan again",
+        ));
+    }
+
+    //Link next to the duplicate first so it's never missing if something fails.
+    let mut temp = to.as_os_str().to_os_string();
+    temp.push(".ex-link");
+    let temp = PathBuf::from(temp);
+
+    fs::hard_link(from, &temp)?;
+    if let Err(e) = fs::rename(&temp, to) {
+        let _ = fs::remove_file(&temp);
+        return Err(e);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn scan(root: &Path) -> Vec<Group> {
+        find(root, &AtomicBool::new(false), |_| ()).unwrap()
+    }
+
+    #[test]
+    fn hard_links_are_not_duplicates() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+        fs::write(&a, "same").unwrap();
+        fs::hard_link(&a, &b).unwrap();
+
+        let groups = scan(dir.path());
+        if cfg!(unix) {
+            assert!(groups.is_empty());
+        }
+
+        //Even when asked to, linking them again leaves nothing behind.
+        let file = |path: &Path| Duplicate {
+            path: path.to_path_buf(),
+            modified: path.metadata().and_then(|m| m.modified()).ok(),
+        };
+        hard_link(&file(&a), &file(&b), 4).unwrap();
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+
+    #[test]
+    fn changed_files_are_not_replaced() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+        fs::write(&a, "same").unwrap();
+        fs::write(&b, "same").unwrap();
+
+        let groups = scan(dir.path());
+        assert_eq!(groups.len(), 1);
+        let group = &groups[0];
+
+        //Same size, but different contents now.
+        fs::write(&group.files[1].path, "diff").unwrap();
+        assert!(hard_link(&group.files[0], &group.files[1], group.size).is_err());
+        assert_eq!(fs::read(&group.files[1].path).unwrap(), b"diff");
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 2);
+    }
+}
@@ -0,0 +1,168 @@
+use crate::rename;
+use std::{
+    fmt, io,
+    path::{Path, PathBuf},
+};
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+//Every failure names the file it happened to, so callers can point at it.
+#[derive(Debug)]
+pub enum Error {
+    NotFound(PathBuf),
+    PermissionDenied(PathBuf),
+    AlreadyExists(PathBuf),
+    //Renames can't cross drives, the file has to be copied instead.
+    CrossDevice(PathBuf),
+    NotADirectory(PathBuf),
+    //Paths like "/" or ".." have no name to copy or move.
+    InvalidName(PathBuf),
+    //The drive may not have a trash, or the desktop doesn't support one. Only these can be
+    //deleted permanently instead.
+    TrashUnavailable { path: PathBuf, error: trash::Error },
+    Rename(rename::Error),
+    Io { path: PathBuf, error: io::Error },
+}
+
+impl Error {
+    pub fn io(path: &Path, error: io::Error) -> Self {
+        let path = path.to_path_buf();
+        match error.kind() {
+            io::ErrorKind::NotFound => Error::NotFound(path),
+            io::ErrorKind::PermissionDenied => Error::PermissionDenied(path),
+            io::ErrorKind::AlreadyExists => Error::AlreadyExists(path),
+            io::ErrorKind::CrossesDevices => Error::CrossDevice(path),
+            io::ErrorKind::NotADirectory => Error::NotADirectory(path),
+            _ => Error::Io { path, error },
+        }
+    }
+
+    //Only failures of the trash itself are `TrashUnavailable`, the rest are about the file.
+    pub fn trash(path: &Path, error: trash::Error) -> Self {
+        match error {
+            #[cfg(all(
+                unix,
+                not(target_os = "macos"),
+                not(target_os = "ios"),
+                not(target_os = "android")
+            ))]
+            trash::Error::FileSystem { source, .. } => Error::io(path, source),
+            trash::Error::TargetedRoot | trash::Error::ConvertOsString { .. } => {
+                Error::InvalidName(path.to_path_buf())
+            }
+            trash::Error::CouldNotAccess { .. } | trash::Error::CanonicalizePath { .. } => {
+                match path.symlink_metadata() {
+                    Err(e) => Error::io(path, e),
+                    Ok(_) => Error::PermissionDenied(path.to_path_buf()),
+                }
+            }
+            trash::Error::RestoreCollision { path, .. }
+            | trash::Error::RestoreTwins { path, .. } => Error::AlreadyExists(path),
+            error => Error::TrashUnavailable {
+                path: path.to_path_buf(),
+                error,
+            },
+        }
+    }
+
+    pub fn path(&self) -> &Path {
+        match self {
+            Error::NotFound(path)
+            | Error::PermissionDenied(path)
+            | Error::AlreadyExists(path)
+            | Error::CrossDevice(path)
+            | Error::NotADirectory(path)
+            | Error::InvalidName(path)
+            | Error::TrashUnavailable { path, .. }
+            | Error::Io { path, .. } => path,
+            Error::Rename(error) => &error.path,
+        }
+    }
+
+    //Errors from another file system name paths the way that file system sees them.
+    pub(crate) fn map_path(mut self, f: impl FnOnce(&Path) -> PathBuf) -> Self {
+        match &mut self {
+            Error::NotFound(path)
+            | Error::PermissionDenied(path)
+            | Error::AlreadyExists(path)
+            | Error::CrossDevice(path)
+            | Error::NotADirectory(path)
+            | Error::InvalidName(path)
+            | Error::TrashUnavailable { path, .. }
+            | Error::Io { path, .. } => *path = f(path),
+            Error::Rename(error) => error.path = f(&error.path),
+        }
+        self
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.path().display();
+        match self {
+            Error::NotFound(_) => write!(f, "{path} doesn't exist"),
+            Error::PermissionDenied(_) => write!(f, "Permission denied: {path}"),
+            Error::AlreadyExists(_) => write!(f, "{path} already exists"),
+            Error::CrossDevice(_) => write!(f, "{path} can't be moved to another drive"),
+            Error::NotADirectory(_) => write!(f, "{path} is not a folder"),
+            Error::InvalidName(_) => write!(f, "{path} has no file name"),
+            Error::TrashUnavailable { error, .. } => {
+                write!(f, "Couldn't move {path} to the Trash: {error}")
+            }
+            Error::Rename(error) => write!(f, "{error}"),
+            Error::Io { error, .. } => write!(f, "{path}: {error}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            Error::TrashUnavailable { error, .. } => Some(error),
+            Error::Rename(error) => Some(&*error.error),
+            Error::Io { error, .. } => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<rename::Error> for Error {
+    fn from(error: rename::Error) -> Self {
+        Error::Rename(error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trash_errors() {
+        let path = Path::new("/missing");
+        let unknown = trash::Error::Unknown {
+            description: String::from("No trash"),
+        };
+        assert!(matches!(
+            Error::trash(path, unknown),
+            Error::TrashUnavailable { .. }
+        ));
+        let root = Error::trash(path, trash::Error::TargetedRoot);
+        assert!(matches!(root, Error::InvalidName(_)));
+        let access = trash::Error::CouldNotAccess {
+            target: String::from("/missing"),
+        };
+        assert!(matches!(Error::trash(path, access), Error::NotFound(_)));
+
+        #[cfg(target_os = "linux")]
+        {
+            let source = io::Error::from(io::ErrorKind::PermissionDenied);
+            let error = trash::Error::FileSystem {
+                path: PathBuf::from("/.Trash-1000"),
+                source,
+            };
+            let error = Error::trash(path, error);
+            assert!(matches!(error, Error::PermissionDenied(_)));
+            assert_eq!(error.path(), path);
+        }
+    }
+}
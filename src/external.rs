@@ -0,0 +1,224 @@
+use serde::{Deserialize, Serialize};
+use std::{
+    env, io,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    thread,
+};
+
+//A user defined program shown in the context menu as "Open in {name}".
+//`command` is split like a shell would and these are replaced:
+//{dir}   the current folder
+//{file}  the first selected file
+//{files} every selected file, each as its own argument
+//{line}  the line to open at, always 1 for now
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct External {
+    pub name: String,
+    pub command: String,
+}
+
+impl External {
+    pub fn new(name: &str, command: &str) -> Self {
+        Self {
+            name: name.to_string(),
+            command: command.to_string(),
+        }
+    }
+
+    //Commands that open files can't do anything without a selection.
+    pub fn needs_files(&self) -> bool {
+        self.command.contains("{file}") || self.command.contains("{files}")
+    }
+
+    pub fn args(&self, dir: &Path, files: &[PathBuf]) -> Vec<String> {
+        let dir = dir.display().to_string();
+        let file = files
+            .first()
+            .map(|file| file.display().to_string())
+            .unwrap_or_default();
+
+        let mut args = Vec::new();
+        for word in split(&self.command) {
+            if word == "{files}" {
+                args.extend(files.iter().map(|file| file.display().to_string()));
+                continue;
+            }
+            let all = files
+                .iter()
+                .map(|file| file.display().to_string())
+                .collect::<Vec<String>>()
+                .join(" ");
+            args.push(
+                word.replace("{dir}", &dir)
+                    .replace("{files}", &all)
+                    .replace("{file}", &file)
+                    .replace("{line}", "1"),
+            );
+        }
+        args
+    }
+
+    //Starts the program in `dir` without waiting for it.
+    pub fn spawn(&self, dir: &Path, files: &[PathBuf]) -> io::Result<()> {
+        let args = self.args(dir, files);
+        let Some((program, args)) = args.split_first() else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("\"{}\" has no command", self.name),
+            ));
+        };
+
+        let mut child = Command::new(program)
+            .args(args)
+            .current_dir(dir)
+            .stdin(Stdio::null())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+            .map_err(|e| io::Error::new(e.kind(), format!("Failed to run {program}: {e}")))?;
+
+        //Someone has to wait on the child or it's left as a zombie once it exits.
+        thread::spawn(move || child.wait());
+        Ok(())
+    }
+}
+
+//Splits on whitespace, quotes keep words together.
+fn split(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = String::new();
+    let mut quote = None;
+    let mut in_word = false;
+
+    for c in command.chars() {
+        match (quote, c) {
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                in_word = true;
+            }
+            (Some(q), c) if c == q => quote = None,
+            (None, c) if c.is_whitespace() => {
+                if in_word {
+                    words.push(std::mem::take(&mut word));
+                    in_word = false;
+                }
+            }
+            (_, c) => {
+                word.push(c);
+                in_word = true;
+            }
+        }
+    }
+    if in_word {
+        words.push(word);
+    }
+    words
+}
+
+fn installed(program: &str) -> bool {
+    let Some(paths) = env::var_os("PATH") else {
+        return false;
+    };
+    env::split_paths(&paths).any(|dir| {
+        let path = dir.join(program);
+        path.is_file() || (cfg!(windows) && path.with_extension("exe").is_file())
+    })
+}
+
+//The first terminal and editor that are installed.
+pub fn detect() -> Vec<External> {
+    if cfg!(windows) {
+        return vec![
+            External::new("Terminal", "wt.exe -d {dir}"),
+            External::new("VSCode", "cmd /c code {dir}"),
+        ];
+    }
+
+    const TERMINALS: &[(&str, &str)] = &[
+        ("gnome-terminal", "gnome-terminal --working-directory={dir}"),
+        ("konsole", "konsole --workdir {dir}"),
+        ("xfce4-terminal", "xfce4-terminal --working-directory={dir}"),
+        ("kitty", "kitty --directory {dir}"),
+        ("alacritty", "alacritty --working-directory {dir}"),
+        ("wezterm", "wezterm start --cwd {dir}"),
+        ("foot", "foot --working-directory={dir}"),
+        ("x-terminal-emulator", "x-terminal-emulator"),
+        ("xterm", "xterm"),
+    ];
+    const EDITORS: &[(&str, &str, &str)] = &[
+        ("code", "VSCode", "code {dir}"),
+        ("codium", "VSCodium", "codium {dir}"),
+        ("zed", "Zed", "zed {dir}"),
+        ("subl", "Sublime Text", "subl {dir}"),
+        ("kate", "Kate", "kate {files}"),
+        ("gedit", "gedit", "gedit {files}"),
+    ];
+
+    let mut commands = Vec::new();
+    if let Some((_, command)) = TERMINALS.iter().find(|(program, _)| installed(program)) {
+        commands.push(External::new("Terminal", command));
+    }
+    if let Some((_, name, command)) = EDITORS.iter().find(|(program, ..)| installed(program)) {
+        commands.push(External::new(name, command));
+    }
+    commands
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_quotes() {
+        assert_eq!(split("  code   {dir} "), ["code", "{dir}"]);
+        assert_eq!(split("sh -c 'echo \"a b\"'"), ["sh", "-c", "echo \"a b\""]);
+        assert_eq!(
+            split("open \"My Files\"/{file}"),
+            ["open", "My Files/{file}"]
+        );
+        //An empty pair of quotes is still an argument.
+        assert_eq!(split("run '' x"), ["run", "", "x"]);
+        assert!(split("   ").is_empty());
+    }
+
+    #[test]
+    fn placeholders() {
+        let dir = Path::new("/home/me/My Project");
+        let files = [dir.join("a b.txt"), dir.join("c.txt")];
+
+        let edit = External::new("Editor", "edit --line={line} {file}");
+        assert!(edit.needs_files());
+        assert_eq!(
+            edit.args(dir, &files),
+            ["edit", "--line=1", "/home/me/My Project/a b.txt"]
+        );
+
+        //Each file is its own argument, spaces and all.
+        let diff = External::new("Diff", "diff {files} --cwd={dir}");
+        assert_eq!(
+            diff.args(dir, &files),
+            [
+                "diff",
+                "/home/me/My Project/a b.txt",
+                "/home/me/My Project/c.txt",
+                "--cwd=/home/me/My Project"
+            ]
+        );
+        //Unless it's part of a bigger word.
+        let joined = External::new("Joined", "'echo {files}'");
+        assert_eq!(
+            joined.args(dir, &files),
+            ["echo /home/me/My Project/a b.txt /home/me/My Project/c.txt"]
+        );
+
+        let terminal = External::new("Terminal", "kitty --directory {dir}");
+        assert!(!terminal.needs_files());
+        assert_eq!(
+            terminal.args(dir, &[]),
+            ["kitty", "--directory", "/home/me/My Project"]
+        );
+        assert_eq!(edit.args(dir, &[]), ["edit", "--line=1", ""]);
+        assert_eq!(diff.args(dir, &[]), ["diff", "--cwd=/home/me/My Project"]);
+    }
+}
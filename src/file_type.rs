@@ -0,0 +1,565 @@
+use std::{
+    collections::HashMap,
+    path::Path,
+    sync::{Mutex, OnceLock},
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Category {
+    Folder,
+    Image,
+    Audio,
+    Video,
+    Archive,
+    Code,
+    Document,
+    Text,
+    Executable,
+    Shortcut,
+    Unknown,
+}
+
+impl Category {
+    pub fn icon(&self) -> &'static str {
+        match self {
+            Category::Folder => "🗀",
+            Category::Image => "🖼",
+            Category::Audio => "🎵",
+            Category::Video => "🎞",
+            Category::Archive => "📦",
+            Category::Code => "📜",
+            Category::Document => "📄",
+            Category::Text => "🖹",
+            Category::Executable => "⚙",
+            Category::Shortcut => "🔗",
+            Category::Unknown => "🖹",
+        }
+    }
+
+    pub fn from_mime(mime: &str) -> Self {
+        let (top, sub) = mime.split_once('/').unwrap_or((mime, ""));
+        match top {
+            "inode" if sub == "directory" => return Category::Folder,
+            "image" => return Category::Image,
+            "audio" => return Category::Audio,
+            "video" => return Category::Video,
+            _ => (),
+        }
+
+        match mime {
+            "application/zip"
+            | "application/gzip"
+            | "application/x-tar"
+            | "application/x-compressed-tar"
+            | "application/x-xz"
+            | "application/x-xz-compressed-tar"
+            | "application/zstd"
+            | "application/x-zstd-compressed-tar"
+            | "application/x-bzip2"
+            | "application/x-7z-compressed"
+            | "application/vnd.rar"
+            | "application/x-rar-compressed"
+            | "application/java-archive"
+            | "application/x-iso9660-image" => Category::Archive,
+            "application/pdf"
+            | "application/rtf"
+            | "application/msword"
+            | "application/vnd.ms-excel"
+            | "application/vnd.ms-powerpoint"
+            | "application/epub+zip"
+            | "text/markdown"
+            | "text/csv" => Category::Document,
+            "application/json"
+            | "application/xml"
+            | "application/javascript"
+            | "application/toml"
+            | "application/x-yaml"
+            | "application/x-shellscript"
+            | "text/html"
+            | "text/css"
+            | "text/javascript" => Category::Code,
+            "application/x-executable"
+            | "application/x-msdownload"
+            | "application/vnd.microsoft.portable-executable"
+            | "application/x-sharedlib"
+            | "application/x-msi" => Category::Executable,
+            "application/x-ms-shortcut" | "application/x-desktop" | "inode/symlink" => {
+                Category::Shortcut
+            }
+            _ if sub.starts_with("vnd.openxmlformats") || sub.starts_with("vnd.oasis") => {
+                Category::Document
+            }
+            _ if top == "text" && (sub.starts_with("x-") || sub.starts_with("rust")) => {
+                Category::Code
+            }
+            _ if top == "text" => Category::Text,
+            _ => Category::Unknown,
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct FileType {
+    pub mime: String,
+    pub name: String,
+    pub category: Category,
+}
+
+impl FileType {
+    fn new(mime: &str, name: String) -> Self {
+        Self {
+            category: Category::from_mime(mime),
+            mime: mime.to_string(),
+            name,
+        }
+    }
+}
+
+//Extension, MIME type and the name shown in the Type column.
+const BUILTIN: &[(&str, &str, &str)] = &[
+    ("lnk", "application/x-ms-shortcut", "Shortcut"),
+    ("url", "application/x-ms-shortcut", "Internet Shortcut"),
+    ("desktop", "application/x-desktop", "Desktop Entry"),
+    (
+        "exe",
+        "application/vnd.microsoft.portable-executable",
+        "Application",
+    ),
+    ("msi", "application/x-msi", "Windows Installer Package"),
+    ("dll", "application/x-msdownload", "Application Extension"),
+    ("so", "application/x-sharedlib", "Shared Library"),
+    ("zip", "application/zip", "zip Archive"),
+    ("7z", "application/x-7z-compressed", "7z Archive"),
+    ("rar", "application/vnd.rar", "RAR Archive"),
+    ("tar", "application/x-tar", "tar Archive"),
+    ("gz", "application/gzip", "gzip Archive"),
+    ("tgz", "application/x-compressed-tar", "tar.gz Archive"),
+    ("tar.gz", "application/x-compressed-tar", "tar.gz Archive"),
+    ("xz", "application/x-xz", "xz Archive"),
+    (
+        "tar.xz",
+        "application/x-xz-compressed-tar",
+        "tar.xz Archive",
+    ),
+    ("zst", "application/zstd", "zstd Archive"),
+    (
+        "tar.zst",
+        "application/x-zstd-compressed-tar",
+        "tar.zst Archive",
+    ),
+    ("bz2", "application/x-bzip2", "bzip2 Archive"),
+    ("jar", "application/java-archive", "Java Archive"),
+    ("iso", "application/x-iso9660-image", "Disc Image"),
+    ("png", "image/png", "PNG Image"),
+    ("jpg", "image/jpeg", "JPEG Image"),
+    ("jpeg", "image/jpeg", "JPEG Image"),
+    ("gif", "image/gif", "GIF Image"),
+    ("webp", "image/webp", "WebP Image"),
+    ("bmp", "image/bmp", "Bitmap Image"),
+    ("ico", "image/vnd.microsoft.icon", "Icon"),
+    ("svg", "image/svg+xml", "SVG Image"),
+    ("tif", "image/tiff", "TIFF Image"),
+    ("tiff", "image/tiff", "TIFF Image"),
+    ("psd", "image/vnd.adobe.photoshop", "Photoshop Image"),
+    ("mp3", "audio/mpeg", "MP3 Audio"),
+    ("flac", "audio/flac", "FLAC Audio"),
+    ("wav", "audio/wav", "Wave Audio"),
+    ("ogg", "audio/ogg", "Ogg Audio"),
+    ("opus", "audio/opus", "Opus Audio"),
+    ("m4a", "audio/mp4", "MPEG-4 Audio"),
+    ("mp4", "video/mp4", "MPEG-4 Video"),
+    ("mkv", "video/x-matroska", "Matroska Video"),
+    ("webm", "video/webm", "WebM Video"),
+    ("avi", "video/x-msvideo", "AVI Video"),
+    ("mov", "video/quicktime", "QuickTime Video"),
+    ("pdf", "application/pdf", "PDF Document"),
+    ("doc", "application/msword", "Word Document"),
+    (
+        "docx",
+        "application/vnd.openxmlformats-officedocument.wordprocessingml.document",
+        "Word Document",
+    ),
+    ("xls", "application/vnd.ms-excel", "Excel Spreadsheet"),
+    (
+        "xlsx",
+        "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet",
+        "Excel Spreadsheet",
+    ),
+    (
+        "ppt",
+        "application/vnd.ms-powerpoint",
+        "PowerPoint Presentation",
+    ),
+    (
+        "pptx",
+        "application/vnd.openxmlformats-officedocument.presentationml.presentation",
+        "PowerPoint Presentation",
+    ),
+    (
+        "odt",
+        "application/vnd.oasis.opendocument.text",
+        "OpenDocument Text",
+    ),
+    ("rtf", "application/rtf", "Rich Text Document"),
+    ("epub", "application/epub+zip", "EPUB Book"),
+    ("md", "text/markdown", "Markdown Document"),
+    ("csv", "text/csv", "CSV Document"),
+    ("txt", "text/plain", "Text Document"),
+    ("log", "text/plain", "Log File"),
+    ("rs", "text/rust", "Rust Source File"),
+    ("c", "text/x-csrc", "C Source File"),
+    ("h", "text/x-chdr", "C Header File"),
+    ("cpp", "text/x-c++src", "C++ Source File"),
+    ("hpp", "text/x-c++hdr", "C++ Header File"),
+    ("cs", "text/x-csharp", "C# Source File"),
+    ("go", "text/x-go", "Go Source File"),
+    ("py", "text/x-python", "Python Script"),
+    ("java", "text/x-java", "Java Source File"),
+    ("lua", "text/x-lua", "Lua Script"),
+    ("js", "text/javascript", "JavaScript File"),
+    ("ts", "text/x-typescript", "TypeScript File"),
+    ("html", "text/html", "HTML Document"),
+    ("css", "text/css", "CSS Stylesheet"),
+    ("json", "application/json", "JSON File"),
+    ("xml", "application/xml", "XML Document"),
+    ("toml", "application/toml", "TOML File"),
+    ("yaml", "application/x-yaml", "YAML File"),
+    ("yml", "application/x-yaml", "YAML File"),
+    ("sh", "application/x-shellscript", "Shell Script"),
+    ("bat", "application/x-msdownload", "Windows Batch File"),
+    ("ps1", "text/x-powershell", "PowerShell Script"),
+];
+
+//Dot files without an extension are matched on their whole name.
+const BUILTIN_NAMES: &[(&str, &str, &str)] = &[
+    (".gitignore", "text/plain", "Git Ignore"),
+    (".gitconfig", "text/plain", "Git Config"),
+    (".gitattributes", "text/plain", "Git Attributes"),
+    (".editorconfig", "text/plain", "EditorConfig File"),
+    (".bashrc", "application/x-shellscript", "Bash Config"),
+    (".profile", "application/x-shellscript", "Shell Profile"),
+    ("makefile", "text/x-makefile", "Makefile"),
+    ("dockerfile", "text/x-dockerfile", "Dockerfile"),
+    ("license", "text/plain", "License"),
+];
+
+#[derive(Default)]
+struct Registry {
+    //Lower case extension or file name to MIME type.
+    extensions: HashMap<String, String>,
+    names: HashMap<String, String>,
+    //MIME type to friendly name.
+    comments: Mutex<HashMap<String, Option<String>>>,
+}
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let mut registry = Registry::default();
+        shared_mime_info::load(&mut registry);
+
+        //Sniffed files get the same names as the ones matched by extension.
+        let comments = registry.comments.get_mut().unwrap();
+        for (_, mime, name) in BUILTIN {
+            comments
+                .entry(mime.to_string())
+                .or_insert_with(|| Some(name.to_string()));
+        }
+
+        registry
+    })
+}
+
+impl Registry {
+    //Parses a globs2 file. Lines are sorted by weight so the first match wins.
+    #[cfg_attr(not(all(unix, not(target_os = "macos"))), allow(dead_code))]
+    fn add_globs(&mut self, globs: &str) {
+        for line in globs.lines().filter(|line| !line.starts_with('#')) {
+            let mut parts = line.split(':');
+            let (Some(_weight), Some(mime), Some(glob)) =
+                (parts.next(), parts.next(), parts.next())
+            else {
+                continue;
+            };
+
+            if let Some(ex) = glob.strip_prefix("*.") {
+                if !ex.contains(['*', '?', '[']) {
+                    self.extensions
+                        .entry(ex.to_ascii_lowercase())
+                        .or_insert_with(|| mime.to_string());
+                }
+            } else if !glob.contains(['*', '?', '[']) {
+                self.names
+                    .entry(glob.to_ascii_lowercase())
+                    .or_insert_with(|| mime.to_string());
+            }
+        }
+    }
+
+    fn lookup(&self, path: &Path) -> Option<FileType> {
+        let file_name = path.file_name()?.to_string_lossy().to_ascii_lowercase();
+
+        if let Some((_, mime, name)) = BUILTIN_NAMES.iter().find(|(n, _, _)| *n == file_name) {
+            return Some(FileType::new(mime, name.to_string()));
+        }
+        if let Some(mime) = self.names.get(&file_name) {
+            return Some(FileType::new(mime, self.comment(mime)));
+        }
+
+        //Try "tar.gz" before "gz".
+        let mut ex = file_name.as_str();
+        while let Some((_, rest)) = ex.split_once('.') {
+            if let Some((_, mime, name)) = BUILTIN.iter().find(|(e, _, _)| *e == rest) {
+                return Some(FileType::new(mime, name.to_string()));
+            }
+            if let Some(mime) = self.extensions.get(rest) {
+                return Some(FileType::new(mime, self.comment(mime)));
+            }
+            ex = rest;
+        }
+
+        None
+    }
+
+    fn comment(&self, mime: &str) -> String {
+        let mut comments = self.comments.lock().unwrap();
+        let comment = comments
+            .entry(mime.to_string())
+            .or_insert_with(|| shared_mime_info::comment(mime));
+
+        match comment {
+            Some(comment) => comment.clone(),
+            None => mime.to_string(),
+        }
+    }
+}
+
+//Cheap lookup from the file name alone, suitable for every row of a large folder.
+pub fn from_path(path: &Path) -> FileType {
+    from_name(path).unwrap_or_else(|| fallback(path))
+}
+
+//The type the name says the file is, if it says anything.
+pub fn from_name(path: &Path) -> Option<FileType> {
+    registry().lookup(path)
+}
+
+//Reads the first bytes of a file on the local disk.
+pub fn sniff(local: &Path) -> Option<FileType> {
+    let kind = infer::get_from_path(local).ok()??;
+    let mime = kind.mime_type();
+    let name = registry().comment(mime);
+    let name = if name == mime {
+        format!("{} file", kind.extension().to_ascii_uppercase())
+    } else {
+        name
+    };
+    Some(FileType::new(mime, name))
+}
+
+//Like `from_path` but sniffs the first bytes of the file when the name isn't enough. Only files
+//on the local disk are sniffed, `local` is where that is.
+pub fn detect(path: &Path, is_dir: bool, local: Option<&Path>) -> FileType {
+    if is_dir {
+        return folder();
+    }
+    from_name(path)
+        .or_else(|| local.and_then(sniff))
+        .unwrap_or_else(|| fallback(path))
+}
+
+pub fn folder() -> FileType {
+    FileType::new("inode/directory", String::from("File folder"))
+}
+
+fn fallback(path: &Path) -> FileType {
+    let name = if let Some(ex) = path.extension() {
+        format!(".{} file", ex.to_string_lossy())
+    } else if path
+        .file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .starts_with('.')
+    {
+        String::from("Unknown dot file")
+    } else {
+        String::from("File")
+    };
+
+    FileType::new("application/octet-stream", name)
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+pub(crate) use shared_mime_info::data_dirs;
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod shared_mime_info {
+    use super::Registry;
+    use std::{env, fs, path::PathBuf};
+
+    //$XDG_DATA_HOME followed by $XDG_DATA_DIRS, most important first.
+    pub(crate) fn data_dirs() -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+
+        match env::var_os("XDG_DATA_HOME") {
+            Some(home) => dirs.push(PathBuf::from(home)),
+            None => {
+                if let Some(home) = env::var_os("HOME") {
+                    dirs.push(PathBuf::from(home).join(".local/share"));
+                }
+            }
+        }
+
+        let data = env::var("XDG_DATA_DIRS").unwrap_or_default();
+        let data = if data.is_empty() {
+            "/usr/local/share:/usr/share"
+        } else {
+            data.as_str()
+        };
+        dirs.extend(data.split(':').map(PathBuf::from));
+
+        dirs
+    }
+
+    fn dirs() -> Vec<PathBuf> {
+        data_dirs()
+            .into_iter()
+            .map(|dir| dir.join("mime"))
+            .collect()
+    }
+
+    //https://specifications.freedesktop.org/shared-mime-info-spec/latest/ar01s02.html
+    pub fn load(registry: &mut Registry) {
+        for dir in dirs() {
+            let Ok(globs) = fs::read_to_string(dir.join("globs2")) else {
+                continue;
+            };
+
+            registry.add_globs(&globs);
+        }
+    }
+
+    pub fn comment(mime: &str) -> Option<String> {
+        for dir in dirs() {
+            let Ok(xml) = fs::read_to_string(dir.join(format!("{mime}.xml"))) else {
+                continue;
+            };
+
+            //The first comment without an xml:lang attribute is the English one.
+            let start = xml.find("<comment>")? + "<comment>".len();
+            let end = start + xml[start..].find("</comment>")?;
+            let mut comment = xml[start..end].to_string();
+            if let Some(first) = comment.get_mut(..1) {
+                first.make_ascii_uppercase();
+            }
+            return Some(comment.replace("&amp;", "&"));
+        }
+        None
+    }
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+mod shared_mime_info {
+    use super::Registry;
+
+    pub fn load(_: &mut Registry) {}
+
+    pub fn comment(_: &str) -> Option<String> {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GLOBS: &str = "\
+# This file was automatically generated
+80:application/x-krita:*.kra
+50:application/x-krita-old:*.KRA
+50:text/x-readme:README
+50:text/x-nix:*.nix
+50:text/x-csrc:*.[ch]
+50:text/x-rust:*.rs
+50:application/gzip-alt:*.gz
+not a glob line
+";
+
+    fn registry() -> Registry {
+        let mut registry = Registry::default();
+        registry.add_globs(GLOBS);
+        //Don't read the comments of the machine running the tests.
+        let comments = registry.comments.get_mut().unwrap();
+        comments.insert(String::from("application/x-krita"), None);
+        comments.insert(String::from("text/x-nix"), None);
+        comments.insert(
+            String::from("text/x-readme"),
+            Some(String::from("README document")),
+        );
+        registry
+    }
+
+    fn lookup(registry: &Registry, path: &str) -> Option<FileType> {
+        registry.lookup(Path::new(path))
+    }
+
+    #[test]
+    fn globs2() {
+        let registry = registry();
+        assert_eq!(registry.extensions["nix"], "text/x-nix");
+        assert_eq!(registry.names["readme"], "text/x-readme");
+        //The first and heaviest match wins over later ones with different case.
+        assert_eq!(registry.extensions["kra"], "application/x-krita");
+        //Only plain extensions and names are used.
+        assert!(!registry.extensions.keys().any(|ex| ex.contains('[')));
+        assert_eq!(registry.extensions.len(), 4);
+        assert_eq!(registry.names.len(), 1);
+    }
+
+    #[test]
+    fn lookup_globs() {
+        let registry = registry();
+        let krita = lookup(&registry, "/art/Drawing.KRA").unwrap();
+        assert_eq!(krita.mime, "application/x-krita");
+        //Without a comment the MIME type is shown.
+        assert_eq!(krita.name, "application/x-krita");
+        assert_eq!(lookup(&registry, "README").unwrap().name, "README document");
+        assert_eq!(
+            lookup(&registry, "default.old.nix").unwrap().category,
+            Category::Code
+        );
+        assert!(lookup(&registry, "main.q").is_none());
+        assert!(lookup(&registry, "Cargo").is_none());
+    }
+
+    #[test]
+    fn lookup_builtin() {
+        let registry = registry();
+        //Built in extensions win over shared-mime-info.
+        assert_eq!(
+            lookup(&registry, "lib.rs").unwrap().name,
+            "Rust Source File"
+        );
+        assert_eq!(lookup(&registry, "a.gz").unwrap().mime, "application/gzip");
+        //The longest extension is tried first.
+        let archive = lookup(&registry, "backup.tar.gz").unwrap();
+        assert_eq!(archive.mime, "application/x-compressed-tar");
+        assert_eq!(
+            lookup(&registry, "Game.LNK").unwrap().category,
+            Category::Shortcut
+        );
+        assert_eq!(lookup(&registry, ".gitignore").unwrap().name, "Git Ignore");
+        assert_eq!(lookup(&registry, "Makefile").unwrap().name, "Makefile");
+    }
+
+    #[test]
+    fn fallbacks() {
+        assert_eq!(fallback(Path::new("data.xyz123")).name, ".xyz123 file");
+        assert_eq!(fallback(Path::new(".hidden")).name, "Unknown dot file");
+        assert_eq!(fallback(Path::new("/bin/thing")).name, "File");
+        assert_eq!(
+            detect(Path::new("src.rs"), true, None).category,
+            Category::Folder
+        );
+    }
+}
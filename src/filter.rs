@@ -0,0 +1,125 @@
+use crate::git::Ignores;
+use globset::{Glob, GlobSet, GlobSetBuilder};
+use std::{ffi::OsStr, path::Path};
+
+//Which entries count as hidden. They're left out of listings unless `show_hidden` is set.
+#[derive(Clone, Default)]
+pub struct Filter {
+    pub show_hidden: bool,
+    //Hide whatever the `.gitignore` files of the repository ignore.
+    pub gitignore: bool,
+    patterns: Vec<String>,
+    globs: GlobSet,
+}
+
+impl Filter {
+    //Patterns that aren't valid globs are skipped.
+    pub fn new(show_hidden: bool, patterns: &[String], gitignore: bool) -> Self {
+        let mut builder = GlobSetBuilder::new();
+        for pattern in patterns {
+            if let Ok(glob) = Glob::new(pattern.trim()) {
+                builder.add(glob);
+            }
+        }
+        Self {
+            show_hidden,
+            gitignore,
+            patterns: patterns.to_vec(),
+            globs: builder.build().unwrap_or_default(),
+        }
+    }
+
+    pub fn patterns(&self) -> &[String] {
+        &self.patterns
+    }
+
+    //Only the name is matched, so `target` hides every folder called that.
+    pub fn matches(&self, name: &OsStr) -> bool {
+        self.globs.is_match(name)
+    }
+
+    //`ignores` is the repository of the folder `path` is in, if `gitignore` is set.
+    pub fn hides(&self, path: &Path, ignores: Option<&Ignores>) -> bool {
+        let name = path.file_name().unwrap_or(path.as_os_str());
+        is_hidden(path)
+            || self.matches(name)
+            || (self.gitignore && ignores.is_some_and(|ignores| ignores.ignored(name)))
+    }
+}
+
+//Why a pattern isn't a valid glob.
+pub fn pattern_error(pattern: &str) -> Option<String> {
+    Glob::new(pattern.trim())
+        .err()
+        .map(|e| e.kind().to_string())
+}
+
+//Dotfiles everywhere, and files with the hidden attribute on Windows.
+pub fn is_hidden(path: &Path) -> bool {
+    let dotfile = path
+        .file_name()
+        .is_some_and(|name| name.to_string_lossy().starts_with('.'));
+    dotfile || hidden_attribute(path)
+}
+
+#[cfg(windows)]
+fn hidden_attribute(path: &Path) -> bool {
+    use std::os::windows::fs::MetadataExt;
+    use windows_sys::Win32::Storage::FileSystem::FILE_ATTRIBUTE_HIDDEN;
+    path.symlink_metadata()
+        .is_ok_and(|m| m.file_attributes() & FILE_ATTRIBUTE_HIDDEN != 0)
+}
+
+#[cfg(not(windows))]
+fn hidden_attribute(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn filter(patterns: &[&str], gitignore: bool) -> Filter {
+        let patterns: Vec<String> = patterns.iter().map(|p| p.to_string()).collect();
+        Filter::new(false, &patterns, gitignore)
+    }
+
+    #[test]
+    fn patterns() {
+        let filter = filter(&[" *.tmp ", "target", "[invalid"], false);
+        assert_eq!(filter.patterns().len(), 3);
+        assert!(pattern_error("[invalid").is_some());
+        assert!(pattern_error(" *.tmp ").is_none());
+
+        assert!(filter.hides(Path::new("/code/build.tmp"), None));
+        assert!(filter.hides(Path::new("/code/target"), None));
+        assert!(!filter.hides(Path::new("/code/target.rs"), None));
+        assert!(!filter.hides(Path::new("/code/[invalid"), None));
+        //Dot files are always hidden, whatever the patterns say.
+        assert!(filter.hides(Path::new("/code/.git"), None));
+        assert!(Filter::default().hides(Path::new("/.config"), None));
+        assert!(!Filter::default().hides(Path::new("/config"), None));
+    }
+
+    #[test]
+    fn gitignore() {
+        let dir = tempfile::tempdir().unwrap();
+        git2::Repository::init(dir.path()).unwrap();
+        fs::write(dir.path().join(".gitignore"), "*.log\n").unwrap();
+        let ignores = Ignores::open(dir.path());
+        let log = dir.path().join("debug.log");
+
+        assert!(filter(&[], true).hides(&log, ignores.as_ref()));
+        assert!(!filter(&[], true).hides(&dir.path().join("main.rs"), ignores.as_ref()));
+        //Off unless asked for.
+        assert!(!filter(&[], false).hides(&log, ignores.as_ref()));
+        //Folders outside a repository have nothing to ignore.
+        assert!(!filter(&[], true).hides(&log, None));
+        //Either one is enough.
+        let both = filter(&["*.rs"], true);
+        assert!(both.hides(&log, ignores.as_ref()));
+        assert!(both.hides(&dir.path().join("main.rs"), ignores.as_ref()));
+        assert!(!both.hides(&dir.path().join("Cargo.toml"), ignores.as_ref()));
+    }
+}
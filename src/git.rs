@@ -0,0 +1,314 @@
+use git2::{Repository, StatusOptions};
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    fs,
+    path::{Path, PathBuf},
+};
+
+//Ordered by importance, a folder shows the most important status inside it.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Debug)]
+pub enum Status {
+    Ignored,
+    Untracked,
+    Staged,
+    Modified,
+    Conflicted,
+}
+
+impl Status {
+    pub fn label(self) -> &'static str {
+        match self {
+            Status::Ignored => "Ignored",
+            Status::Untracked => "Untracked",
+            Status::Staged => "Staged",
+            Status::Modified => "Modified",
+            Status::Conflicted => "Conflicted",
+        }
+    }
+
+    pub fn marker(self) -> &'static str {
+        match self {
+            Status::Ignored => "I",
+            Status::Untracked => "U",
+            Status::Staged => "S",
+            Status::Modified => "M",
+            Status::Conflicted => "C",
+        }
+    }
+
+    fn from_flags(flags: git2::Status) -> Option<Status> {
+        if flags.is_conflicted() {
+            Some(Status::Conflicted)
+        } else if flags.intersects(
+            git2::Status::WT_MODIFIED
+                | git2::Status::WT_DELETED
+                | git2::Status::WT_RENAMED
+                | git2::Status::WT_TYPECHANGE,
+        ) {
+            Some(Status::Modified)
+        } else if flags.intersects(
+            git2::Status::INDEX_NEW
+                | git2::Status::INDEX_MODIFIED
+                | git2::Status::INDEX_DELETED
+                | git2::Status::INDEX_RENAMED
+                | git2::Status::INDEX_TYPECHANGE,
+        ) {
+            Some(Status::Staged)
+        } else if flags.is_wt_new() {
+            Some(Status::Untracked)
+        } else if flags.is_ignored() {
+            Some(Status::Ignored)
+        } else {
+            None
+        }
+    }
+}
+
+//The git status of everything in one folder of a repository.
+pub struct Repo {
+    pub root: PathBuf,
+    //`None` when HEAD can't be read at all.
+    pub branch: Option<String>,
+    dir: PathBuf,
+    statuses: HashMap<PathBuf, Status>,
+    //Set when the whole folder is ignored, git only reports the top of those.
+    inherited: Option<Status>,
+}
+
+impl Repo {
+    //Reads the index and work tree once for all of `dir`, rather than asking git per item.
+    //Returns `None` if `dir` isn't inside a repository.
+    pub fn open(dir: &Path) -> Option<Repo> {
+        let repo = Repository::discover(dir).ok()?;
+        let root = repo.workdir()?.to_path_buf();
+        let prefix = prefix(&repo, dir)?;
+        let pathspec = prefix.to_string_lossy().replace('\\', "/");
+
+        let inherited = if pathspec.is_empty() {
+            None
+        } else if repo.is_path_ignored(&prefix).unwrap_or(false) {
+            Some(Status::Ignored)
+        } else if repo
+            .index()
+            .ok()?
+            .find_prefix(format!("{pathspec}/"))
+            .is_err()
+        {
+            Some(Status::Untracked)
+        } else {
+            None
+        };
+
+        let mut statuses = HashMap::new();
+        if inherited == Some(Status::Untracked) {
+            //Nothing here is tracked, but some of it might still be ignored.
+            for entry in fs::read_dir(dir).ok()?.flatten() {
+                let ignored = repo.is_path_ignored(prefix.join(entry.file_name()));
+                let status = match ignored {
+                    Ok(true) => Status::Ignored,
+                    _ => Status::Untracked,
+                };
+                statuses.insert(entry.path(), status);
+            }
+        } else if inherited.is_none() {
+            let mut options = StatusOptions::new();
+            options
+                .include_untracked(true)
+                .include_ignored(true)
+                .recurse_untracked_dirs(false)
+                .recurse_ignored_dirs(false);
+            if !pathspec.is_empty() {
+                options.pathspec(&pathspec);
+            }
+
+            for entry in repo.statuses(Some(&mut options)).ok()?.iter() {
+                let Some(status) = Status::from_flags(entry.status()) else {
+                    continue;
+                };
+                let Some(path) = entry.path() else {
+                    continue;
+                };
+                let Ok(rest) = Path::new(path).strip_prefix(&prefix) else {
+                    continue;
+                };
+                let mut components = rest.components();
+                let Some(child) = components.next() else {
+                    continue;
+                };
+                //Something ignored inside a folder doesn't make the folder ignored.
+                if components.next().is_some() && status == Status::Ignored {
+                    continue;
+                }
+                statuses
+                    .entry(dir.join(child))
+                    .and_modify(|current: &mut Status| *current = (*current).max(status))
+                    .or_insert(status);
+            }
+        }
+
+        Some(Repo {
+            root,
+            branch: branch(&repo),
+            dir: dir.to_path_buf(),
+            statuses,
+            inherited: inherited.filter(|status| *status == Status::Ignored),
+        })
+    }
+
+    //Only items directly inside the folder `Repo` was opened on have a status.
+    pub fn status(&self, path: &Path) -> Option<Status> {
+        if path.parent() != Some(self.dir.as_path()) {
+            return None;
+        }
+        self.inherited.or_else(|| self.statuses.get(path).copied())
+    }
+}
+
+//Checks names in one folder against the `.gitignore` files of its repository.
+pub struct Ignores {
+    repo: Repository,
+    prefix: PathBuf,
+}
+
+impl Ignores {
+    pub fn open(dir: &Path) -> Option<Ignores> {
+        let repo = Repository::discover(dir).ok()?;
+        let prefix = prefix(&repo, dir)?;
+        Some(Ignores { repo, prefix })
+    }
+
+    pub fn ignored(&self, name: &OsStr) -> bool {
+        self.repo
+            .is_path_ignored(self.prefix.join(name))
+            .unwrap_or(false)
+    }
+}
+
+//Where `dir` is relative to the root of the work tree.
+fn prefix(repo: &Repository, dir: &Path) -> Option<PathBuf> {
+    //Both are canonical so links to folders inside the repository still line up.
+    let root = repo.workdir()?.canonicalize().ok()?;
+    let dir = dir.canonicalize().ok()?;
+    Some(dir.strip_prefix(root).ok()?.to_path_buf())
+}
+
+fn branch(repo: &Repository) -> Option<String> {
+    match repo.head() {
+        Ok(head) if head.is_branch() => head.shorthand().map(str::to_string),
+        Ok(head) => {
+            let id = head.target()?.to_string();
+            Some(format!("{} (detached)", &id[..7]))
+        }
+        //A new repository has no commits for HEAD to point to yet.
+        Err(_) => {
+            let head = repo.find_reference("HEAD").ok()?;
+            let target = head.symbolic_target()?;
+            Some(target.trim_start_matches("refs/heads/").to_string())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    //A repository with `tracked` committed and the rest written afterwards.
+    fn repo(tracked: &[&str], untracked: &[&str]) -> tempfile::TempDir {
+        let dir = tempfile::tempdir().unwrap();
+        let repo = Repository::init(dir.path()).unwrap();
+        let mut index = repo.index().unwrap();
+        for path in tracked {
+            let full = dir.path().join(path);
+            fs::create_dir_all(full.parent().unwrap()).unwrap();
+            fs::write(&full, "tracked").unwrap();
+            index.add_path(Path::new(path)).unwrap();
+        }
+        index.write().unwrap();
+        let tree = repo.find_tree(index.write_tree().unwrap()).unwrap();
+        let signature = git2::Signature::now("ex", "ex@example.com").unwrap();
+        repo.commit(Some("HEAD"), &signature, &signature, "Initial", &tree, &[])
+            .unwrap();
+
+        for path in untracked {
+            let full = dir.path().join(path);
+            fs::create_dir_all(full.parent().unwrap()).unwrap();
+            fs::write(&full, "untracked").unwrap();
+        }
+        dir
+    }
+
+    #[test]
+    fn folders_take_the_most_important_status() {
+        let dir = repo(
+            &["src/main.rs", "src/lib.rs", "docs/readme.md", "clean/a.txt"],
+            &["src/new.rs", "docs/notes.md", ".gitignore"],
+        );
+        let root = dir.path();
+        fs::write(root.join("src/main.rs"), "modified").unwrap();
+        fs::write(root.join(".gitignore"), "target/\n*.log\n").unwrap();
+        fs::write(root.join("clean/debug.log"), "ignored").unwrap();
+
+        let repo = Repo::open(root).unwrap();
+        //Modified beats the untracked file next to it.
+        assert_eq!(repo.status(&root.join("src")), Some(Status::Modified));
+        assert_eq!(repo.status(&root.join("docs")), Some(Status::Untracked));
+        //An ignored file doesn't make its folder ignored.
+        assert_eq!(repo.status(&root.join("clean")), None);
+        //Only direct children have a status.
+        assert_eq!(repo.status(&root.join("src/main.rs")), None);
+
+        let src = Repo::open(&root.join("src")).unwrap();
+        assert_eq!(
+            src.status(&root.join("src/main.rs")),
+            Some(Status::Modified)
+        );
+        assert_eq!(
+            src.status(&root.join("src/new.rs")),
+            Some(Status::Untracked)
+        );
+        assert_eq!(src.status(&root.join("src/lib.rs")), None);
+    }
+
+    #[test]
+    fn ignored_folders() {
+        let dir = repo(
+            &["Cargo.toml"],
+            &["target/debug/ex", "target/.rustc_info.json"],
+        );
+        let root = dir.path();
+        fs::write(root.join(".gitignore"), "target/\n").unwrap();
+
+        let repo = Repo::open(root).unwrap();
+        assert_eq!(repo.status(&root.join("target")), Some(Status::Ignored));
+        assert_eq!(
+            repo.status(&root.join(".gitignore")),
+            Some(Status::Untracked)
+        );
+
+        //Everything inside an ignored folder is ignored too.
+        let target = Repo::open(&root.join("target")).unwrap();
+        let debug = root.join("target/debug");
+        assert_eq!(target.status(&debug), Some(Status::Ignored));
+        let debug = Repo::open(&debug).unwrap();
+        assert_eq!(
+            debug.status(&root.join("target/debug/ex")),
+            Some(Status::Ignored)
+        );
+    }
+
+    #[test]
+    fn untracked_folders() {
+        let dir = repo(&["Cargo.toml"], &["new/a.rs", "new/b.log"]);
+        let root = dir.path();
+        fs::write(root.join(".gitignore"), "*.log\n").unwrap();
+
+        let new = Repo::open(&root.join("new")).unwrap();
+        assert_eq!(new.status(&root.join("new/a.rs")), Some(Status::Untracked));
+        assert_eq!(new.status(&root.join("new/b.log")), Some(Status::Ignored));
+
+        let ignores = Ignores::open(&root.join("new")).unwrap();
+        assert!(ignores.ignored(OsStr::new("c.log")));
+        assert!(!ignores.ignored(OsStr::new("c.rs")));
+    }
+}
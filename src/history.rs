@@ -0,0 +1,282 @@
+use crate::{
+    recycle,
+    rename::{self, Rename},
+    vfs::{self, Vfs},
+    Error, Result,
+};
+use std::{
+    fmt,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+//Older operations are forgotten.
+const LIMIT: usize = 100;
+
+#[derive(Clone, Debug)]
+pub enum Operation {
+    //Every file in a bulk rename is undone together.
+    Rename(Vec<(PathBuf, PathBuf)>),
+    Move { from: PathBuf, to: PathBuf },
+    Copy { from: PathBuf, to: PathBuf },
+    Create { path: PathBuf, dir: bool },
+    Trash(PathBuf),
+}
+
+impl fmt::Display for Operation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Operation::Rename(renames) => match renames.as_slice() {
+                [(from, to)] => write!(f, "Rename {} to {}", name(from), name(to)),
+                renames => write!(f, "Rename {} items", renames.len()),
+            },
+            Operation::Move { from, to } => {
+                write!(f, "Move {} to {}", name(from), parent(to))
+            }
+            Operation::Copy { from, to } => {
+                write!(f, "Copy {} to {}", name(from), parent(to))
+            }
+            Operation::Create { path, dir: true } => write!(f, "New folder {}", name(path)),
+            Operation::Create { path, dir: false } => write!(f, "New file {}", name(path)),
+            Operation::Trash(path) => write!(f, "Move {} to Trash", name(path)),
+        }
+    }
+}
+
+fn name(path: &Path) -> String {
+    path.file_name()
+        .unwrap_or(path.as_os_str())
+        .to_string_lossy()
+        .to_string()
+}
+
+fn parent(path: &Path) -> String {
+    path.parent()
+        .map(|parent| parent.display().to_string())
+        .unwrap_or_default()
+}
+
+impl Operation {
+    pub fn undo(&self) -> Result<()> {
+        match self {
+            Operation::Rename(renames) => {
+                let renames: Vec<(PathBuf, PathBuf)> = renames
+                    .iter()
+                    .map(|(from, to)| (to.clone(), from.clone()))
+                    .collect();
+                rename_all(&renames)
+            }
+            Operation::Move { from, to } => move_path(to, from),
+            //Anything that was added is trashed rather than deleted in case it was changed since.
+            //Servers have no trash, so it's removed there.
+            Operation::Copy { to: path, .. } | Operation::Create { path, .. } => {
+                if vfs::is_remote(path) {
+                    vfs::disk().remove(path)
+                } else {
+                    crate::delete(path)
+                }
+            }
+            Operation::Trash(path) => {
+                recycle::restore_path(path).map_err(|e| Error::trash(path, e))
+            }
+        }
+    }
+
+    pub fn redo(&self) -> Result<()> {
+        match self {
+            Operation::Rename(renames) => rename_all(renames),
+            Operation::Move { from, to } => move_path(from, to),
+            Operation::Copy { from, to } => {
+                crate::vacant(to)?;
+                vfs::disk().copy(from, to)
+            }
+            Operation::Create { path, dir: true } => crate::create_dir(path),
+            Operation::Create { path, dir: false } => crate::create_file(path),
+            Operation::Trash(path) => crate::delete(path),
+        }
+    }
+}
+
+fn move_path(from: &Path, to: &Path) -> Result<()> {
+    crate::vacant(to)?;
+    crate::move_path(from, to)
+}
+
+fn rename_all(renames: &[(PathBuf, PathBuf)]) -> Result<()> {
+    let renames: Vec<Rename> = renames
+        .iter()
+        .map(|(from, to)| Rename {
+            from: from.clone(),
+            to: to.clone(),
+            conflict: None,
+        })
+        .collect();
+    Ok(rename::apply(&renames)?)
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Action {
+    Did,
+    Undid,
+    Redid,
+}
+
+#[derive(Clone, Debug)]
+pub struct Entry {
+    pub time: SystemTime,
+    pub action: Action,
+    pub description: String,
+    pub error: Option<String>,
+}
+
+//Operations that can be undone and redone, along with a log of everything that happened.
+#[derive(Default)]
+pub struct History {
+    undo: Vec<Operation>,
+    redo: Vec<Operation>,
+    log: Vec<Entry>,
+    //Entries logged since startup, the log only keeps the latest.
+    count: usize,
+    //An operation is being undone or redone, the next one has to wait for it.
+    busy: bool,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    //Records an operation that has already succeeded.
+    pub fn record(&mut self, operation: Operation) {
+        self.push_log(Action::Did, operation.to_string(), None);
+        self.redo.clear();
+        self.undo.push(operation);
+        if self.undo.len() > LIMIT {
+            self.undo.remove(0);
+        }
+    }
+
+    //Logs an operation that failed, there is nothing to undo.
+    pub fn error(&mut self, description: impl Into<String>, error: impl fmt::Display) {
+        self.push_log(Action::Did, description.into(), Some(error.to_string()));
+    }
+
+    //Hands out the operation to undo, which can take a while. It's put back with `undone` once
+    //it's been tried.
+    pub fn take_undo(&mut self) -> Option<Operation> {
+        if self.busy {
+            return None;
+        }
+        let operation = self.undo.pop()?;
+        self.busy = true;
+        Some(operation)
+    }
+
+    pub fn undone(&mut self, operation: Operation, result: Result<()>) {
+        self.busy = false;
+        match result {
+            Ok(_) => {
+                self.push_log(Action::Undid, operation.to_string(), None);
+                self.redo.push(operation);
+            }
+            Err(e) => {
+                //Leave it where it was so it can be tried again.
+                self.push_log(Action::Undid, operation.to_string(), Some(e.to_string()));
+                self.undo.push(operation);
+            }
+        }
+    }
+
+    //Like `take_undo`, put back with `redone`.
+    pub fn take_redo(&mut self) -> Option<Operation> {
+        if self.busy {
+            return None;
+        }
+        let operation = self.redo.pop()?;
+        self.busy = true;
+        Some(operation)
+    }
+
+    pub fn redone(&mut self, operation: Operation, result: Result<()>) {
+        self.busy = false;
+        match result {
+            Ok(_) => {
+                self.push_log(Action::Redid, operation.to_string(), None);
+                self.undo.push(operation);
+            }
+            Err(e) => {
+                self.push_log(Action::Redid, operation.to_string(), Some(e.to_string()));
+                self.redo.push(operation);
+            }
+        }
+    }
+
+    pub fn next_undo(&self) -> Option<&Operation> {
+        self.undo.last().filter(|_| !self.busy)
+    }
+
+    pub fn next_redo(&self) -> Option<&Operation> {
+        self.redo.last().filter(|_| !self.busy)
+    }
+
+    //Oldest first.
+    pub fn log(&self) -> &[Entry] {
+        &self.log
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    fn push_log(&mut self, action: Action, description: String, error: Option<String>) {
+        self.log.push(Entry {
+            time: SystemTime::now(),
+            action,
+            description,
+            error,
+        });
+        self.count += 1;
+        if self.log.len() > LIMIT * 10 {
+            self.log.remove(0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::sync::Arc;
+
+    #[test]
+    fn copies_to_servers_are_undone() {
+        vfs::mount("sftp", "history", Arc::new(vfs::Memory::new()));
+        let to = PathBuf::from("sftp://history/file");
+        vfs::disk().write(&to, b"data").unwrap();
+
+        let copy = Operation::Copy {
+            from: PathBuf::from("/file"),
+            to: to.clone(),
+        };
+        copy.undo().unwrap();
+        assert!(matches!(vfs::disk().stat(&to), Err(Error::NotFound(_))));
+    }
+
+    #[test]
+    fn one_step_at_a_time() {
+        let mut history = History::new();
+        let path = PathBuf::from("/folder");
+        history.record(Operation::Create { path, dir: true });
+
+        let operation = history.take_undo().unwrap();
+        assert!(history.take_undo().is_none());
+        assert!(history.next_undo().is_none());
+
+        //Failed steps can be tried again.
+        history.undone(operation, Err(Error::InvalidName(PathBuf::new())));
+        assert!(history.next_undo().is_some());
+        let operation = history.take_undo().unwrap();
+        history.undone(operation, Ok(()));
+        assert!(history.next_undo().is_none());
+        assert!(history.next_redo().is_some());
+    }
+}
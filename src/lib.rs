@@ -1,35 +1,126 @@
 use chrono::prelude::*;
 use jwalk::WalkDir;
+use serde::{Deserialize, Serialize};
 use std::{
-    env, fs,
+    collections::{HashMap, HashSet},
+    fs,
     io::{self},
-    os::windows::prelude::MetadataExt,
     path::{Path, PathBuf},
+    sync::Arc,
+    time::{Duration, Instant, SystemTime},
 };
 
-pub struct Ex {
+pub mod clipboard;
+pub mod compress;
+pub mod duplicates;
+mod error;
+pub mod external;
+pub mod file_type;
+pub mod filter;
+pub mod git;
+pub mod history;
+pub mod open_with;
+pub mod preview;
+pub mod recycle;
+pub mod rename;
+pub mod thumbnail;
+pub mod usage;
+pub mod vfs;
+
+pub use error::{Error, Result};
+use filter::Filter;
+use vfs::{Metadata, Vfs};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Sort {
+    Name,
+    Modified,
+    Type,
+    Size,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Units {
+    //1 KB = 1000 bytes
+    #[default]
+    Decimal,
+    //1 KiB = 1024 bytes
+    Binary,
+}
+
+//One folder's listing. The folder is only tracked here, never in the process's working directory,
+//so any number of these can be used at once, each on any file system.
+pub struct Ex<V: Vfs = vfs::Local> {
     pub files: Vec<PathBuf>,
+    //Always absolute.
     current: PathBuf,
+    metadata: HashMap<PathBuf, Metadata>,
+    //Listed entries that are only there because hidden ones are shown.
+    hidden: HashSet<PathBuf>,
+    filter: Filter,
+    sort: Sort,
+    ascending: bool,
+    vfs: V,
 }
 
 impl Ex {
     #[allow(clippy::new_without_default)]
     pub fn new() -> Self {
+        Ex::with_vfs(vfs::Local, &start())
+    }
+}
+
+impl Ex<Arc<dyn Vfs>> {
+    //Archives on the local disk open as folders.
+    pub fn disk() -> Self {
+        Ex::with_vfs(vfs::disk(), &start())
+    }
+}
+
+impl<V: Vfs> Ex<V> {
+    //An unreadable starting folder just shows up empty.
+    pub fn with_vfs(vfs: V, path: &Path) -> Self {
         let mut s = Self {
             files: Vec::new(),
-            current: PathBuf::default(),
+            current: path.to_path_buf(),
+            metadata: HashMap::new(),
+            hidden: HashSet::new(),
+            filter: Filter::default(),
+            sort: Sort::Name,
+            ascending: true,
+            vfs,
         };
-        s.set_directory(Path::new("C:\\"), "");
+        let _ = s.set_directory(path, "");
         s
     }
 
-    pub fn previous(&mut self) {
-        let path = if let Some(parent) = self.current.parent() {
-            parent.to_path_buf()
+    pub fn vfs(&self) -> &V {
+        &self.vfs
+    }
+
+    pub fn previous(&mut self) -> Result<()> {
+        match self.parent() {
+            Some(parent) => self.set_directory(&parent, ""),
+            None => Ok(()),
+        }
+    }
+
+    pub fn parent(&self) -> Option<PathBuf> {
+        //`sftp:` is the parent of a server's root.
+        if vfs::Location::parse(&self.current).is_some_and(|l| l.path == Path::new("/")) {
+            return None;
+        }
+        self.current.parent().map(Path::to_path_buf)
+    }
+
+    //Relative paths are taken from the current folder.
+    pub fn resolve(&self, path: &Path) -> PathBuf {
+        //Server paths look relative on Unix.
+        if vfs::is_remote(path) {
+            path.to_path_buf()
         } else {
-            return;
-        };
-        self.set_directory(&path, "");
+            self.current.join(path)
+        }
     }
 
     pub fn current_path(&self) -> &Path {
@@ -48,134 +139,521 @@ impl Ex {
             .to_string()
     }
 
-    pub fn set_directory(&mut self, path: &Path, search: &str) {
-        if env::set_current_dir(path).is_ok() {
-            let mut files: Vec<_> = WalkDir::new(path)
-                .max_depth(1)
-                .skip_hidden(false)
-                .into_iter()
-                .flatten()
-                //Hide ntfs related files
-                .filter(|dir| dir.depth == 1 && dir.metadata().is_ok())
-                .map(|dir| dir.path())
-                .filter(|file| {
-                    if search.is_empty() {
-                        true
-                    } else {
-                        let file_name = file
-                            .file_name()
-                            .unwrap_or(file.as_os_str())
-                            .to_string_lossy()
-                            .to_ascii_lowercase();
-                        file_name.contains(search)
-                    }
-                })
-                .collect();
-
-            self.current = path.to_path_buf();
-
-            files.sort_by_key(|a| {
-                !a.file_name()
-                    .unwrap_or_default()
-                    .to_string_lossy()
-                    .starts_with('.')
-            });
-
-            files.sort_by_key(|a| !a.is_dir());
-
-            self.files = files;
+    //Metadata from when the folder was listed.
+    pub fn metadata(&self, path: &Path) -> Option<&Metadata> {
+        self.metadata.get(path)
+    }
+
+    pub fn hidden(&self, path: &Path) -> bool {
+        self.hidden.contains(path)
+    }
+
+    pub fn filter(&self) -> &Filter {
+        &self.filter
+    }
+
+    //Takes effect the next time a folder is listed.
+    pub fn set_filter(&mut self, filter: Filter) {
+        self.filter = filter;
+    }
+
+    //Nothing changes if the folder can't be read.
+    pub fn set_directory(&mut self, path: &Path, search: &str) -> Result<()> {
+        let listing = Listing::read(&self.vfs, &self.resolve(path))?;
+        self.show(listing, search);
+        Ok(())
+    }
+
+    //Switches to a folder that was read with `Listing::read`.
+    pub fn show(&mut self, listing: Listing, search: &str) {
+        let Listing { path, entries } = listing;
+
+        //Only folders on the local disk can be in a repository.
+        let ignores = match self.vfs.local_path(&path) {
+            Some(local) if self.filter.gitignore => git::Ignores::open(&local),
+            _ => None,
         };
+        let mut hidden = HashSet::new();
+
+        let metadata: HashMap<PathBuf, Metadata> = entries
+            .into_iter()
+            .filter(|(file, _)| {
+                if search.is_empty() {
+                    true
+                } else {
+                    let file_name = file
+                        .file_name()
+                        .unwrap_or(file.as_os_str())
+                        .to_string_lossy()
+                        .to_ascii_lowercase();
+                    file_name.contains(search)
+                }
+            })
+            .filter(|(file, _)| {
+                if self.filter.hides(file, ignores.as_ref()) {
+                    hidden.insert(file.clone());
+                    self.filter.show_hidden
+                } else {
+                    true
+                }
+            })
+            .collect();
+
+        self.current = path;
+        self.files = metadata.keys().cloned().collect();
+        self.metadata = metadata;
+        self.hidden = hidden;
+        self.sort_files();
+    }
+
+    pub fn sort(&self) -> (Sort, bool) {
+        (self.sort, self.ascending)
+    }
+
+    pub fn set_sort(&mut self, sort: Sort, ascending: bool) {
+        self.sort = sort;
+        self.ascending = ascending;
+        self.sort_files();
+    }
+
+    fn sort_files(&mut self) {
+        let files = &mut self.files;
+        let metadata = &self.metadata;
+        match self.sort {
+            //Dotfiles sort by the rest of their name instead of all ending up at the top.
+            Sort::Name => files.sort_by_cached_key(|file| {
+                let name = file.file_name().unwrap_or_default().to_string_lossy();
+                name.trim_start_matches('.').to_lowercase()
+            }),
+            Sort::Modified => {
+                files.sort_by_cached_key(|file| metadata.get(file).and_then(|m| m.modified))
+            }
+            Sort::Type => files.sort_by_cached_key(|file| file_type::from_path(file).name),
+            //Compare the raw byte count, not the formatted string.
+            Sort::Size => files.sort_by_cached_key(|file| {
+                metadata.get(file).filter(|m| !m.is_dir()).map(|m| m.len)
+            }),
+        }
+
+        self.order();
+    }
+
+    //Sorts by something only the caller knows, like the detected type or a folder's total size.
+    pub fn sort_by<K: Ord>(&mut self, mut key: impl FnMut(&Path, Option<&Metadata>) -> K) {
+        let metadata = &self.metadata;
+        self.files
+            .sort_by_cached_key(|file| key(file, metadata.get(file)));
+        self.order();
+    }
+
+    fn order(&mut self) {
+        if !self.ascending {
+            self.files.reverse();
+        }
+
+        //Folders always come first.
+        let metadata = &self.metadata;
+        self.files
+            .sort_by_key(|file| !metadata.get(file).is_some_and(Metadata::is_dir));
     }
 
     pub fn reset(&mut self) {
         self.files = Vec::new();
+        self.metadata = HashMap::new();
+        self.hidden = HashSet::new();
     }
 }
 
-pub fn file_size(path: &Path) -> Option<String> {
-    if let Ok(metadata) = path.metadata() {
-        let size = metadata.file_size();
-        let size_str = if size < 1_000 {
-            if size == 0 {
-                String::from("0 KB")
-            } else {
-                String::from("1 KB")
+//A folder's items, read separately from `Ex` so servers can be read without holding up the ui.
+pub struct Listing {
+    path: PathBuf,
+    entries: Vec<(PathBuf, Metadata)>,
+}
+
+impl Listing {
+    //`dir` has to be absolute, see `Ex::resolve`.
+    pub fn read<V: Vfs + ?Sized>(vfs: &V, dir: &Path) -> Result<Listing> {
+        if !vfs.stat(dir)?.is_dir() {
+            return Err(Error::NotADirectory(dir.to_path_buf()));
+        }
+        Ok(Listing {
+            path: dir.to_path_buf(),
+            entries: vfs.list(dir)?,
+        })
+    }
+
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+//The system drive on Windows, the home folder everywhere else.
+fn start() -> PathBuf {
+    if cfg!(windows) {
+        PathBuf::from("C:\\")
+    } else {
+        dirs::home_dir().unwrap_or_else(|| PathBuf::from("/"))
+    }
+}
+
+//Returns the size in bytes of a file, folders don't have one.
+pub fn file_size(path: &Path) -> Option<u64> {
+    let metadata = path.metadata().ok()?;
+    if metadata.is_dir() {
+        None
+    } else {
+        Some(metadata.len())
+    }
+}
+
+//Bytes the current user can still write to the drive holding `path`.
+#[cfg(unix)]
+pub fn free_space(path: &Path) -> Result<u64> {
+    use std::{ffi::CString, os::unix::ffi::OsStrExt};
+
+    let c_path =
+        CString::new(path.as_os_str().as_bytes()).map_err(|e| Error::io(path, e.into()))?;
+    let mut stat: libc::statvfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statvfs(c_path.as_ptr(), &mut stat) } != 0 {
+        return Err(Error::io(path, io::Error::last_os_error()));
+    }
+    #[allow(clippy::unnecessary_cast)]
+    Ok(stat.f_bavail as u64 * stat.f_frsize as u64)
+}
+
+#[cfg(windows)]
+pub fn free_space(path: &Path) -> Result<u64> {
+    use std::os::windows::ffi::OsStrExt;
+    use windows_sys::Win32::Storage::FileSystem::GetDiskFreeSpaceExW;
+
+    let wide: Vec<u16> = path.as_os_str().encode_wide().chain(Some(0)).collect();
+    let mut free = 0;
+    let ok = unsafe {
+        GetDiskFreeSpaceExW(
+            wide.as_ptr(),
+            &mut free,
+            std::ptr::null_mut(),
+            std::ptr::null_mut(),
+        )
+    };
+    if ok == 0 {
+        return Err(Error::io(path, io::Error::last_os_error()));
+    }
+    Ok(free)
+}
+
+#[derive(Clone, Copy, Default, Debug, PartialEq, Eq)]
+pub struct DirSize {
+    pub bytes: u64,
+    pub files: u64,
+    pub folders: u64,
+}
+
+//Walks the whole tree, `progress` gets the running total every so often.
+pub fn dir_size(path: &Path, mut progress: impl FnMut(&DirSize)) -> DirSize {
+    let mut size = DirSize::default();
+    let mut last = Instant::now();
+
+    for entry in WalkDir::new(path)
+        .skip_hidden(false)
+        .follow_links(false)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.depth > 0)
+    {
+        if entry.file_type().is_dir() {
+            size.folders += 1;
+        } else {
+            size.files += 1;
+            if let Ok(metadata) = entry.metadata() {
+                size.bytes += metadata.len();
             }
-        } else if size < 1_000_000 {
-            format!("{} KB", size / 1_000)
+        }
+
+        if last.elapsed() > Duration::from_millis(250) {
+            progress(&size);
+            last = Instant::now();
+        }
+    }
+
+    size
+}
+
+pub fn format_size(bytes: u64, units: Units) -> String {
+    let (base, suffixes) = match units {
+        Units::Decimal => (1000.0, ["B", "KB", "MB", "GB", "TB"]),
+        Units::Binary => (1024.0, ["B", "KiB", "MiB", "GiB", "TiB"]),
+    };
+
+    if (bytes as f64) < base {
+        return format!("{bytes} {}", suffixes[0]);
+    }
+
+    let mut size = bytes as f64 / base;
+    let mut i = 1;
+    loop {
+        //Round first, 9.96 is shown as "10" and 999.6 KB as "1.0 MB".
+        let tenths = (size * 10.0).round();
+        let rounded = if tenths < 100.0 {
+            tenths / 10.0
         } else {
-            format!("{} MB", size / 1_000_000)
+            size.round()
         };
+        if rounded >= base && i < suffixes.len() - 1 {
+            size /= base;
+            i += 1;
+        } else if rounded < 10.0 {
+            return format!("{rounded:.1} {}", suffixes[i]);
+        } else {
+            return format!("{rounded:.0} {}", suffixes[i]);
+        }
+    }
+}
 
-        if !path.is_dir() {
-            return Some(size_str);
+//1234567 -> "1,234,567 bytes"
+pub fn format_bytes(bytes: u64) -> String {
+    let digits = bytes.to_string();
+    let mut s = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            s.push(',');
         }
+        s.push(c);
+    }
+    if bytes == 1 {
+        format!("{s} byte")
+    } else {
+        format!("{s} bytes")
     }
-    None
 }
 
 pub fn last_modified(path: &Path) -> Option<String> {
     if let Ok(metadata) = path.metadata() {
-        let last_write_time = metadata.last_write_time();
-        if let Some(date) = windows_date(last_write_time as i64) {
-            return Some(date.format("%d/%m/%Y %H:%M").to_string());
+        if let Ok(time) = metadata.modified() {
+            return Some(format_time(time));
         }
     }
     None
 }
 
-pub fn open(path: &Path) -> Result<(), String> {
-    match open::that(path) {
-        Ok(_) => Ok(()),
-        Err(e) => Err(format!("{e:?}")),
-    }
+pub fn format_time(time: SystemTime) -> String {
+    let date: DateTime<Local> = time.into();
+    date.format("%d/%m/%Y %H:%M").to_string()
+}
+
+pub fn open(path: &Path) -> Result<()> {
+    open::that(path).map_err(|e| Error::io(path, e))
 }
 
-pub fn rename(new_name: &str, file: &Path) -> io::Result<()> {
+pub fn rename(new_name: &str, file: &Path) -> Result<()> {
     let mut new_path = file.to_path_buf();
     new_path.set_file_name(new_name);
 
-    fs::rename(file, new_path)
+    vfs::disk().rename(file, &new_path)
+}
+
+pub fn delete(file: &Path) -> Result<()> {
+    if vfs::is_remote(file) {
+        let description = String::from("Servers have no trash, delete permanently instead");
+        return Err(Error::trash(file, trash::Error::Unknown { description }));
+    }
+    trash::delete(file).map_err(|e| Error::trash(file, e))
+}
+
+//Deletes without going through the trash. With `secure` every file is overwritten with zeros
+//first, this doesn't help on SSDs or copy on write file systems which put the zeros elsewhere.
+pub fn delete_permanently(path: &Path, secure: bool) -> Result<()> {
+    if vfs::is_remote(path) {
+        return vfs::disk().remove(path);
+    }
+
+    //Links are removed, never followed.
+    let metadata = path.symlink_metadata().map_err(|e| Error::io(path, e))?;
+    if !metadata.is_dir() {
+        if secure && metadata.is_file() {
+            overwrite(path, metadata.len()).map_err(|e| Error::io(path, e))?;
+        }
+        return fs::remove_file(path).map_err(|e| Error::io(path, e));
+    }
+
+    if secure {
+        for entry in WalkDir::new(path)
+            .skip_hidden(false)
+            .follow_links(false)
+            .into_iter()
+        {
+            let entry = entry.map_err(|e| Error::io(path, io::Error::other(e)))?;
+            if entry.file_type().is_file() {
+                let file = entry.path();
+                let len = entry
+                    .metadata()
+                    .map_err(|e| Error::io(&file, io::Error::other(e)))?
+                    .len();
+                overwrite(&file, len).map_err(|e| Error::io(&file, e))?;
+            }
+        }
+    }
+    fs::remove_dir_all(path).map_err(|e| Error::io(path, e))
 }
 
-pub fn delete(file: &Path) -> Result<(), trash::Error> {
-    trash::delete(file)
+fn overwrite(path: &Path, len: u64) -> io::Result<()> {
+    use io::Write;
+
+    let mut file = fs::OpenOptions::new().write(true).open(path)?;
+    let zeros = [0; 64 * 1024];
+    let mut remaining = len;
+    while remaining > 0 {
+        let n = remaining.min(zeros.len() as u64) as usize;
+        file.write_all(&zeros[..n])?;
+        remaining -= n as u64;
+    }
+    file.sync_all()
+}
+
+//Where `from` ends up when it's pasted into `dir`, existing files are never replaced.
+fn destination(from: &Path, dir: &Path) -> Result<PathBuf> {
+    let name = from
+        .file_name()
+        .ok_or_else(|| Error::InvalidName(from.to_path_buf()))?;
+    let to = dir.join(name);
+    vacant(&to)?;
+    Ok(to)
 }
 
-pub fn copy(from: &Path, to: &Path) {
-    if let Some(name) = from.file_name() {
-        let mut to = to.to_path_buf();
-        to.push(name);
-        eprintln!("Copying file from: {from:?} to: {to:?}");
-        fs::copy(from, to).unwrap();
+pub(crate) fn vacant(path: &Path) -> Result<()> {
+    //Broken links count as taken.
+    let taken = if vfs::is_remote(path) {
+        vfs::disk().stat(path).is_ok()
+    } else {
+        path.symlink_metadata().is_ok()
+    };
+    if taken {
+        Err(Error::AlreadyExists(path.to_path_buf()))
+    } else {
+        Ok(())
     }
 }
 
-pub fn cut(from: &Path, to: &Path) {
-    if let Some(name) = from.file_name() {
-        let mut to = to.to_path_buf();
-        to.push(name);
-        eprintln!("Moving file from: {from:?} to: {to:?}");
-        fs::rename(from, to).unwrap();
+//Returns the path of the new copy. Items inside archives are copied out.
+pub fn copy(from: &Path, to: &Path) -> Result<PathBuf> {
+    let to = destination(from, to)?;
+    vfs::disk().copy(from, &to)?;
+    Ok(to)
+}
+
+//Returns where the file was moved to.
+pub fn cut(from: &Path, to: &Path) -> Result<PathBuf> {
+    let to = destination(from, to)?;
+    move_path(from, &to)?;
+    Ok(to)
+}
+
+//Servers can't rename to another machine, so those are copied and then removed.
+pub(crate) fn move_path(from: &Path, to: &Path) -> Result<()> {
+    let disk = vfs::disk();
+    match disk.rename(from, to) {
+        Err(Error::CrossDevice(_)) if vfs::is_remote(from) || vfs::is_remote(to) => {
+            disk.copy(from, to)?;
+            disk.remove(from)
+        }
+        result => result,
     }
 }
 
-pub fn create_file(path: &Path) -> io::Result<()> {
-    fs::File::create(path)?;
+pub fn create_file(path: &Path) -> Result<()> {
+    if vfs::is_remote(path) {
+        vacant(path)?;
+        return vfs::disk().write(path, &[]);
+    }
+    fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(path)
+        .map_err(|e| Error::io(path, e))?;
     Ok(())
 }
 
-pub fn create_dir(path: &Path) -> io::Result<()> {
-    fs::create_dir(path)
+pub fn create_dir(path: &Path) -> Result<()> {
+    vfs::disk().mkdir(path)
 }
 
-// 1601-01-01 is 11,644,473,600 seconds before Unix epoch.
-//https://github.com/oylenshpeegul/Epochs-rust/blob/master/src/lib.rs
-fn windows_date(x: i64) -> Option<NaiveDateTime> {
-    let d = 10_000_000;
-    let s = -11_644_473_600;
-    let q = x / d;
-    let n = ((x % d) * (1_000_000_000 / d)) as u32;
-    let t = q.checked_add(s)?;
-    NaiveDateTime::from_timestamp_opt(t, n)
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use vfs::Memory;
+
+    fn ex() -> Ex<Memory> {
+        let memory = Memory::new();
+        memory.mkdir(Path::new("/b")).unwrap();
+        memory.write(Path::new("/b/inner"), b"inner").unwrap();
+        memory.write(Path::new("/a"), b"a").unwrap();
+        memory.write(Path::new("/.hidden"), b"").unwrap();
+        Ex::with_vfs(memory, Path::new("/"))
+    }
+
+    #[test]
+    fn list() {
+        let mut ex = ex();
+        //Folders first, hidden files left out.
+        assert_eq!(ex.files, [Path::new("/b"), Path::new("/a")]);
+        assert!(ex.metadata(Path::new("/b")).unwrap().is_dir());
+
+        ex.set_directory(Path::new("b"), "").unwrap();
+        assert_eq!(ex.current_path(), Path::new("/b"));
+        assert_eq!(ex.files, [Path::new("/b/inner")]);
+        assert!(ex.set_directory(Path::new("/b/inner"), "").is_err());
+        assert_eq!(ex.current_path(), Path::new("/b"));
+    }
+
+    #[test]
+    fn sizes() {
+        let decimal = |bytes| format_size(bytes, Units::Decimal);
+        assert_eq!(decimal(0), "0 B");
+        assert_eq!(decimal(999), "999 B");
+        assert_eq!(decimal(1000), "1.0 KB");
+        assert_eq!(decimal(9_949), "9.9 KB");
+        assert_eq!(decimal(9_950), "10 KB");
+        assert_eq!(decimal(9_960), "10 KB");
+        assert_eq!(decimal(999_499), "999 KB");
+        assert_eq!(decimal(999_500), "1.0 MB");
+        assert_eq!(decimal(999_999), "1.0 MB");
+        assert_eq!(decimal(1_500_000_000), "1.5 GB");
+        //There's nothing past TB to roll over into.
+        assert_eq!(decimal(999_999_999_999_999), "1000 TB");
+        assert_eq!(decimal(u64::MAX), "18446744 TB");
+
+        let binary = |bytes| format_size(bytes, Units::Binary);
+        assert_eq!(binary(1023), "1023 B");
+        assert_eq!(binary(1024), "1.0 KiB");
+        assert_eq!(binary(10_188), "9.9 KiB");
+        assert_eq!(binary(10_189), "10 KiB");
+        assert_eq!(binary(1_048_063), "1023 KiB");
+        assert_eq!(binary(1_048_064), "1.0 MiB");
+    }
+
+    #[test]
+    fn rename() {
+        let mut ex = ex();
+        ex.vfs().rename(Path::new("/b"), Path::new("/c")).unwrap();
+        ex.set_directory(Path::new("/"), "").unwrap();
+        assert_eq!(ex.files, [Path::new("/c"), Path::new("/a")]);
+        assert_eq!(ex.vfs().read(Path::new("/c/inner")).unwrap(), b"inner");
+    }
+
+    #[test]
+    fn copy_into_itself() {
+        let ex = ex();
+        let result = ex.vfs().copy(Path::new("/b"), Path::new("/b/b"));
+        assert!(matches!(result, Err(Error::Io { .. })));
+        assert!(ex.vfs().stat(Path::new("/b/b")).is_err());
+    }
+
+    #[test]
+    fn copy_onto_existing() {
+        let ex = ex();
+        let result = ex.vfs().copy(Path::new("/a"), Path::new("/b"));
+        assert!(matches!(result, Err(Error::AlreadyExists(_))));
+        assert!(ex.vfs().stat(Path::new("/b")).unwrap().is_dir());
+        assert_eq!(ex.vfs().read(Path::new("/a")).unwrap(), b"a");
+    }
 }
@@ -0,0 +1,362 @@
+//Applications that can open a file, from the freedesktop .desktop files and mimeapps.list.
+//Other platforms have no registered applications, only the ones the user adds.
+use crate::external::External;
+use std::{collections::HashMap, sync::OnceLock};
+
+#[derive(Default)]
+struct Registry {
+    //Desktop file id and the application, sorted by name.
+    apps: Vec<(String, External)>,
+    //Mime type to desktop file ids.
+    handlers: HashMap<String, Vec<String>>,
+    defaults: HashMap<String, Vec<String>>,
+    added: HashMap<String, Vec<String>>,
+    removed: HashMap<String, Vec<String>>,
+}
+
+fn registry() -> &'static Registry {
+    static REGISTRY: OnceLock<Registry> = OnceLock::new();
+    REGISTRY.get_or_init(|| {
+        let mut registry = Registry::default();
+        desktop::load(&mut registry);
+        registry
+    })
+}
+
+//Applications for a mime type, the default one first.
+pub fn apps(mime: &str) -> Vec<External> {
+    registry().apps(mime)
+}
+
+//Every application, for picking one that isn't registered for the type.
+pub fn all() -> Vec<External> {
+    registry().apps.iter().map(|(_, app)| app.clone()).collect()
+}
+
+impl Registry {
+    fn apps(&self, mime: &str) -> Vec<External> {
+        let mut ids: Vec<&String> = Vec::new();
+
+        //Anything that can open text can open the more specific text types too.
+        let mut mimes = vec![mime];
+        if mime.starts_with("text/") && mime != "text/plain" {
+            mimes.push("text/plain");
+        }
+
+        for mime in &mimes {
+            for map in [&self.defaults, &self.added, &self.handlers] {
+                for id in map.get(*mime).into_iter().flatten() {
+                    if !ids.contains(&id) {
+                        ids.push(id);
+                    }
+                }
+            }
+        }
+
+        let removed = self.removed.get(mime);
+        ids.into_iter()
+            .filter(|id| !removed.is_some_and(|removed| removed.contains(id)))
+            .filter_map(|id| self.apps.iter().find(|(app, _)| app == id))
+            .map(|(_, app)| app.clone())
+            .collect()
+    }
+}
+
+//Turns a desktop entry's Exec line into a command for `External`.
+//https://specifications.freedesktop.org/desktop-entry-spec/latest/ar01s07.html
+pub fn from_exec(name: &str, exec: &str) -> External {
+    let mut command = String::new();
+    let mut files = false;
+    let exec = exec.replace("\\\\", "\\");
+    let mut chars = exec.chars();
+
+    while let Some(c) = chars.next() {
+        if c != '%' {
+            command.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('f' | 'u') => {
+                command.push_str("{file}");
+                files = true;
+            }
+            Some('F' | 'U') => {
+                command.push_str("{files}");
+                files = true;
+            }
+            Some('%') => command.push('%'),
+            //Icons, names and deprecated codes aren't needed.
+            _ => (),
+        }
+    }
+
+    if !files {
+        command.push_str(" {file}");
+    }
+    External::new(name, command.trim())
+}
+
+#[cfg(all(unix, not(target_os = "macos")))]
+mod desktop {
+    use super::{from_exec, Registry};
+    use crate::file_type::data_dirs;
+    use std::{
+        collections::HashMap,
+        env, fs,
+        path::{Path, PathBuf},
+    };
+
+    fn config_dirs() -> Vec<PathBuf> {
+        let mut dirs = Vec::new();
+        match env::var_os("XDG_CONFIG_HOME") {
+            Some(home) => dirs.push(PathBuf::from(home)),
+            None => {
+                if let Some(home) = env::var_os("HOME") {
+                    dirs.push(PathBuf::from(home).join(".config"));
+                }
+            }
+        }
+        let config = env::var("XDG_CONFIG_DIRS").unwrap_or_default();
+        let config = if config.is_empty() {
+            "/etc/xdg"
+        } else {
+            config.as_str()
+        };
+        dirs.extend(config.split(':').map(PathBuf::from));
+        dirs
+    }
+
+    //Groups of `key=value` lines, keyed by the group's name.
+    fn parse(text: &str) -> HashMap<&str, Vec<(&str, &str)>> {
+        let mut groups: HashMap<&str, Vec<(&str, &str)>> = HashMap::new();
+        let mut group = "";
+        for line in text.lines().map(str::trim) {
+            if line.starts_with('#') || line.is_empty() {
+                continue;
+            }
+            if let Some(name) = line.strip_prefix('[').and_then(|l| l.strip_suffix(']')) {
+                group = name;
+            } else if let Some((key, value)) = line.split_once('=') {
+                groups
+                    .entry(group)
+                    .or_default()
+                    .push((key.trim(), value.trim()));
+            }
+        }
+        groups
+    }
+
+    fn list(value: &str) -> impl Iterator<Item = &str> {
+        value.split(';').filter(|s| !s.is_empty())
+    }
+
+    //Desktop file ids are the path below "applications" with slashes swapped for dashes.
+    fn walk(root: &Path, dir: &Path, files: &mut Vec<(String, PathBuf)>) {
+        let Ok(entries) = fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if path.is_dir() {
+                walk(root, &path, files);
+            } else if path.extension().is_some_and(|ex| ex == "desktop") {
+                if let Ok(relative) = path.strip_prefix(root) {
+                    let id = relative.to_string_lossy().replace('/', "-");
+                    files.push((id, path));
+                }
+            }
+        }
+    }
+
+    pub fn load(registry: &mut Registry) {
+        let applications: Vec<PathBuf> = data_dirs()
+            .into_iter()
+            .map(|dir| dir.join("applications"))
+            .collect();
+
+        let mut seen = Vec::new();
+        for dir in &applications {
+            let mut files = Vec::new();
+            walk(dir, dir, &mut files);
+
+            for (id, path) in files {
+                //Earlier folders override later ones.
+                if seen.contains(&id) {
+                    continue;
+                }
+                seen.push(id.clone());
+
+                let Ok(text) = fs::read_to_string(&path) else {
+                    continue;
+                };
+                let groups = parse(&text);
+                let Some(entry) = groups.get("Desktop Entry") else {
+                    continue;
+                };
+                let get = |key: &str| entry.iter().find(|(k, _)| *k == key).map(|(_, v)| *v);
+
+                if get("Type") != Some("Application") || get("Hidden") == Some("true") {
+                    continue;
+                }
+                let (Some(name), Some(exec)) = (get("Name"), get("Exec")) else {
+                    continue;
+                };
+
+                for mime in list(get("MimeType").unwrap_or_default()) {
+                    registry
+                        .handlers
+                        .entry(mime.to_string())
+                        .or_default()
+                        .push(id.clone());
+                }
+                if get("NoDisplay") != Some("true") {
+                    registry.apps.push((id, from_exec(name, exec)));
+                }
+            }
+        }
+        registry
+            .apps
+            .sort_by_cached_key(|(_, app)| app.name.to_lowercase());
+
+        //Most important first, so the first default for a type wins.
+        let mut lists: Vec<PathBuf> = config_dirs()
+            .into_iter()
+            .map(|dir| dir.join("mimeapps.list"))
+            .collect();
+        lists.extend(applications.iter().map(|dir| dir.join("mimeapps.list")));
+        lists.extend(applications.iter().map(|dir| dir.join("defaults.list")));
+
+        for path in lists {
+            if let Ok(text) = fs::read_to_string(&path) {
+                add_mimeapps(registry, &text);
+            }
+        }
+    }
+
+    //Lists are added most important first.
+    pub(super) fn add_mimeapps(registry: &mut Registry, text: &str) {
+        for (group, entries) in parse(text) {
+            let map = match group {
+                "Default Applications" => &mut registry.defaults,
+                "Added Associations" => &mut registry.added,
+                "Removed Associations" => &mut registry.removed,
+                _ => continue,
+            };
+            for (mime, ids) in entries {
+                let known = map.entry(mime.to_string()).or_default();
+                for id in list(ids) {
+                    if !known.iter().any(|known| known == id) {
+                        known.push(id.to_string());
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(not(all(unix, not(target_os = "macos"))))]
+mod desktop {
+    use super::Registry;
+
+    pub fn load(_: &mut Registry) {}
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn exec() {
+        let gimp = from_exec("GIMP", "gimp-2.10 %U");
+        assert_eq!(gimp.command, "gimp-2.10 {files}");
+        assert_eq!(from_exec("Vim", "vim -- %f").command, "vim -- {file}");
+        assert_eq!(
+            from_exec("Web", "browser --new %u").command,
+            "browser --new {file}"
+        );
+        assert_eq!(from_exec("Files", "files %F").command, "files {files}");
+        //Programs that don't say where the file goes get it at the end.
+        assert_eq!(
+            from_exec("Viewer", "viewer --fullscreen").command,
+            "viewer --fullscreen {file}"
+        );
+        //Icons and names are dropped, escaped percent signs are kept.
+        let app = from_exec("App", "app %i --name=%c --zoom=100%% %f");
+        assert_eq!(app.command, "app  --name= --zoom=100% {file}");
+        assert_eq!(
+            from_exec("Win", r"wine C:\\\\app.exe %f").command,
+            r"wine C:\\app.exe {file}"
+        );
+    }
+
+    fn registry() -> Registry {
+        let mut registry = Registry::default();
+        for id in [
+            "editor.desktop",
+            "ide.desktop",
+            "viewer.desktop",
+            "gimp.desktop",
+        ] {
+            let name = id.trim_end_matches(".desktop");
+            registry
+                .apps
+                .push((id.to_string(), External::new(name, name)));
+        }
+        for (mime, ids) in [
+            ("image/png", vec!["viewer.desktop", "gimp.desktop"]),
+            ("text/plain", vec!["editor.desktop"]),
+            ("text/x-rust", vec!["ide.desktop"]),
+        ] {
+            let ids = ids.into_iter().map(String::from).collect();
+            registry.handlers.insert(mime.to_string(), ids);
+        }
+        registry
+    }
+
+    fn names(registry: &Registry, mime: &str) -> Vec<String> {
+        registry
+            .apps(mime)
+            .into_iter()
+            .map(|app| app.name)
+            .collect()
+    }
+
+    #[test]
+    fn handlers() {
+        let registry = registry();
+        assert_eq!(names(&registry, "image/png"), ["viewer", "gimp"]);
+        //Text editors can open source code too.
+        assert_eq!(names(&registry, "text/x-rust"), ["ide", "editor"]);
+        assert!(names(&registry, "video/mp4").is_empty());
+    }
+
+    #[cfg(all(unix, not(target_os = "macos")))]
+    #[test]
+    fn mimeapps() {
+        let mut registry = registry();
+        //The user's mimeapps.list comes first.
+        desktop::add_mimeapps(
+            &mut registry,
+            "[Default Applications]\n\
+             image/png=gimp.desktop;\n\
+             [Removed Associations]\n\
+             text/x-rust=editor.desktop;\n",
+        );
+        //Then the system's.
+        desktop::add_mimeapps(
+            &mut registry,
+            "# System defaults\n\
+             [Default Applications]\n\
+             image/png=viewer.desktop;missing.desktop\n\
+             text/plain=ide.desktop;\n\
+             [Added Associations]\n\
+             video/mp4=viewer.desktop;\n",
+        );
+
+        //The user's default wins over the system's, unknown applications are skipped.
+        assert_eq!(names(&registry, "image/png"), ["gimp", "viewer"]);
+        assert_eq!(names(&registry, "text/plain"), ["ide", "editor"]);
+        assert_eq!(names(&registry, "text/x-rust"), ["ide"]);
+        assert_eq!(names(&registry, "video/mp4"), ["viewer"]);
+    }
+}
@@ -0,0 +1,256 @@
+use crate::{file_type, format_time, vfs::Vfs};
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+//Only the start of a file is read for text and hex previews.
+pub const TEXT_LIMIT: usize = 64 * 1024;
+pub const HEX_LIMIT: usize = 4 * 1024;
+//Larger images are scaled down before they're handed to the ui.
+pub const IMAGE_LIMIT: u32 = 1024;
+//Images have to be read whole, bigger ones on servers or in archives aren't worth the wait.
+pub const IMAGE_FILE_LIMIT: u64 = 32 * 1024 * 1024;
+
+pub const IMAGE_EXTENSIONS: [&str; 6] = ["png", "jpg", "jpeg", "gif", "webp", "bmp"];
+
+pub enum Content {
+    Directory,
+    Text {
+        text: String,
+        extension: String,
+        truncated: bool,
+    },
+    Image {
+        size: [usize; 2],
+        rgba: Vec<u8>,
+    },
+    Binary(String),
+    Error(String),
+}
+
+pub struct Properties {
+    pub path: PathBuf,
+    pub size: u64,
+    pub created: Option<String>,
+    pub modified: Option<String>,
+    pub accessed: Option<String>,
+    pub permissions: String,
+    pub mime: String,
+}
+
+pub struct Preview {
+    pub properties: Option<Properties>,
+    pub content: Content,
+}
+
+//Items that aren't on the local disk, like files inside archives, are read through `vfs`.
+pub fn load(vfs: &dyn Vfs, path: &Path) -> Preview {
+    let Some(local) = vfs.local_path(path) else {
+        return load_vfs(vfs, path);
+    };
+    let path = local.as_path();
+    let properties = properties(path).ok();
+
+    let content = if path.is_dir() {
+        Content::Directory
+    } else if is_image(path) {
+        match image::open(path) {
+            Ok(image) => scale(image),
+            Err(e) => Content::Error(e.to_string()),
+        }
+    } else {
+        match fs::File::open(path).and_then(|file| read_start(file, TEXT_LIMIT)) {
+            Ok((bytes, truncated)) => text_or_binary(path, bytes, truncated),
+            Err(e) => Content::Error(e.to_string()),
+        }
+    };
+
+    Preview {
+        properties,
+        content,
+    }
+}
+
+fn load_vfs(vfs: &dyn Vfs, path: &Path) -> Preview {
+    let metadata = match vfs.stat(path) {
+        Ok(metadata) => metadata,
+        Err(e) => {
+            return Preview {
+                properties: None,
+                content: Content::Error(e.to_string()),
+            }
+        }
+    };
+    let properties = Properties {
+        path: path.to_path_buf(),
+        size: metadata.len,
+        created: None,
+        modified: metadata.modified.map(format_time),
+        accessed: None,
+        permissions: if vfs.read_only(path) {
+            String::from("Read-only")
+        } else {
+            String::from("Read/Write")
+        },
+        mime: file_type::detect(path, metadata.is_dir(), None).mime,
+    };
+
+    let content = if metadata.is_dir() {
+        Content::Directory
+    } else if is_image(path) {
+        if metadata.len > IMAGE_FILE_LIMIT {
+            Content::Error(String::from("The image is too large to preview"))
+        } else {
+            match vfs.read(path) {
+                Ok(bytes) => match image::load_from_memory(&bytes) {
+                    Ok(image) => scale(image),
+                    Err(e) => Content::Error(e.to_string()),
+                },
+                Err(e) => Content::Error(e.to_string()),
+            }
+        }
+    } else {
+        //Only the start is downloaded or decompressed.
+        let start = vfs.open_read(path).and_then(|reader| {
+            read_start(reader, TEXT_LIMIT).map_err(|e| crate::Error::io(path, e))
+        });
+        match start {
+            Ok((bytes, truncated)) => text_or_binary(path, bytes, truncated),
+            Err(e) => Content::Error(e.to_string()),
+        }
+    };
+
+    Preview {
+        properties: Some(properties),
+        content,
+    }
+}
+
+pub fn properties(path: &Path) -> io::Result<Properties> {
+    let metadata = fs::metadata(path)?;
+
+    Ok(Properties {
+        path: fs::canonicalize(path).unwrap_or_else(|_| path.to_path_buf()),
+        size: metadata.len(),
+        created: metadata.created().ok().map(format_time),
+        modified: metadata.modified().ok().map(format_time),
+        accessed: metadata.accessed().ok().map(format_time),
+        permissions: permissions(&metadata),
+        mime: file_type::detect(path, metadata.is_dir(), Some(path)).mime,
+    })
+}
+
+pub fn is_image(path: &Path) -> bool {
+    match path.extension() {
+        Some(ex) => {
+            let ex = ex.to_string_lossy().to_ascii_lowercase();
+            IMAGE_EXTENSIONS.contains(&ex.as_str())
+        }
+        None => false,
+    }
+}
+
+pub fn hex_dump(bytes: &[u8]) -> String {
+    let mut dump = String::new();
+    for (i, chunk) in bytes.chunks(16).enumerate() {
+        dump.push_str(&format!("{:08x}  ", i * 16));
+        for j in 0..16 {
+            match chunk.get(j) {
+                Some(b) => dump.push_str(&format!("{b:02x} ")),
+                None => dump.push_str("   "),
+            }
+            if j == 7 {
+                dump.push(' ');
+            }
+        }
+        dump.push(' ');
+        for b in chunk {
+            if b.is_ascii_graphic() || *b == b' ' {
+                dump.push(*b as char);
+            } else {
+                dump.push('.');
+            }
+        }
+        dump.push('\n');
+    }
+    dump
+}
+
+fn scale(image: image::DynamicImage) -> Content {
+    let image = if image.width() > IMAGE_LIMIT || image.height() > IMAGE_LIMIT {
+        image.thumbnail(IMAGE_LIMIT, IMAGE_LIMIT)
+    } else {
+        image
+    };
+    let image = image.to_rgba8();
+
+    Content::Image {
+        size: [image.width() as usize, image.height() as usize],
+        rgba: image.into_raw(),
+    }
+}
+
+fn read_start(reader: impl Read, limit: usize) -> io::Result<(Vec<u8>, bool)> {
+    let mut bytes = Vec::new();
+    //Read one extra byte to find out if the file was cut short.
+    reader.take(limit as u64 + 1).read_to_end(&mut bytes)?;
+    let truncated = bytes.len() > limit;
+    bytes.truncate(limit);
+    Ok((bytes, truncated))
+}
+
+fn text_or_binary(path: &Path, bytes: Vec<u8>, truncated: bool) -> Content {
+    if !bytes.contains(&0) {
+        let text = match std::str::from_utf8(&bytes) {
+            Ok(text) => Some(text),
+            //The limit can land in the middle of a character.
+            Err(e) if truncated && bytes.len() - e.valid_up_to() < 4 => {
+                std::str::from_utf8(&bytes[..e.valid_up_to()]).ok()
+            }
+            Err(_) => None,
+        };
+
+        if let Some(text) = text {
+            let extension = path
+                .extension()
+                .unwrap_or_default()
+                .to_string_lossy()
+                .to_string();
+            return Content::Text {
+                text: text.to_string(),
+                extension,
+                truncated,
+            };
+        }
+    }
+
+    let len = bytes.len().min(HEX_LIMIT);
+    Content::Binary(hex_dump(&bytes[..len]))
+}
+
+#[cfg(unix)]
+fn permissions(metadata: &fs::Metadata) -> String {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mode = metadata.permissions().mode();
+    let mut s = String::with_capacity(10);
+    s.push(if metadata.is_dir() { 'd' } else { '-' });
+    for shift in [6, 3, 0] {
+        let bits = (mode >> shift) & 0o7;
+        s.push(if bits & 0o4 != 0 { 'r' } else { '-' });
+        s.push(if bits & 0o2 != 0 { 'w' } else { '-' });
+        s.push(if bits & 0o1 != 0 { 'x' } else { '-' });
+    }
+    format!("{s} ({:o})", mode & 0o7777)
+}
+
+#[cfg(not(unix))]
+fn permissions(metadata: &fs::Metadata) -> String {
+    if metadata.permissions().readonly() {
+        String::from("Read-only")
+    } else {
+        String::from("Read/Write")
+    }
+}
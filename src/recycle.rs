@@ -0,0 +1,92 @@
+//Browsing the system trash, only Windows and the freedesktop trash on Linux/BSD can do this.
+use std::{
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+pub use trash::Error;
+
+#[derive(Clone, Debug)]
+pub struct Item {
+    pub name: String,
+    pub original: PathBuf,
+    pub deleted: Option<SystemTime>,
+    item: trash::TrashItem,
+}
+
+#[cfg(any(windows, all(unix, not(target_os = "macos"))))]
+mod os {
+    pub use trash::os_limited::{list, purge_all, restore_all};
+}
+
+#[cfg(not(any(windows, all(unix, not(target_os = "macos")))))]
+mod os {
+    use trash::{Error, TrashItem};
+
+    fn unsupported() -> Error {
+        Error::Unknown {
+            description: String::from("The Trash can't be browsed on this platform"),
+        }
+    }
+
+    pub fn list() -> Result<Vec<TrashItem>, Error> {
+        Err(unsupported())
+    }
+
+    pub fn purge_all(_: impl IntoIterator<Item = TrashItem>) -> Result<(), Error> {
+        Err(unsupported())
+    }
+
+    pub fn restore_all(_: impl IntoIterator<Item = TrashItem>) -> Result<(), Error> {
+        Err(unsupported())
+    }
+}
+
+//Most recently deleted first.
+pub fn list() -> Result<Vec<Item>, Error> {
+    let mut items: Vec<Item> = os::list()?
+        .into_iter()
+        .map(|item| Item {
+            name: item.name.clone(),
+            original: item.original_path(),
+            //Items with a broken info file have a negative time.
+            deleted: u64::try_from(item.time_deleted)
+                .ok()
+                .map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+            item,
+        })
+        .collect();
+    items.sort_by_key(|item| std::cmp::Reverse(item.item.time_deleted));
+    Ok(items)
+}
+
+pub fn restore(items: &[Item]) -> Result<(), Error> {
+    os::restore_all(items.iter().map(|item| item.item.clone()))
+}
+
+//Restores into `dir` instead of where the items came from.
+pub fn restore_to(items: &[Item], dir: &Path) -> Result<(), Error> {
+    os::restore_all(items.iter().map(|item| {
+        let mut item = item.item.clone();
+        item.original_parent = dir.to_path_buf();
+        item
+    }))
+}
+
+pub fn purge(items: &[Item]) -> Result<(), Error> {
+    os::purge_all(items.iter().map(|item| item.item.clone()))
+}
+
+pub fn empty() -> Result<(), Error> {
+    os::purge_all(os::list()?)
+}
+
+//Puts the most recently trashed item that came from `path` back.
+pub fn restore_path(path: &Path) -> Result<(), Error> {
+    let item = list()?
+        .into_iter()
+        .find(|item| item.original == path)
+        .ok_or_else(|| Error::Unknown {
+            description: format!("{} is no longer in the Trash", path.display()),
+        })?;
+    restore(&[item])
+}
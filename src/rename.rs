@@ -0,0 +1,392 @@
+use crate::vfs::{self, Metadata};
+use chrono::{DateTime, Local};
+use regex::Regex;
+use std::{
+    collections::{HashMap, HashSet},
+    fmt,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Default)]
+pub enum Case {
+    #[default]
+    Unchanged,
+    Lower,
+    Upper,
+    Title,
+}
+
+//How each name is built:
+//1. `find` is replaced with `replace` in the name without its extension.
+//2. The case is changed.
+//3. The result is put into `template` as `{name}`, alongside `{n}` for a sequence
+//   number (`{n:3}` pads it to 3 digits), `{date}` for the modified date
+//   (`{date:%d-%m-%Y}` for a custom format) and `{ext}` for the original extension.
+//4. The extension is swapped for `extension` if there is one, an empty one removes it.
+#[derive(Clone, Debug)]
+pub struct Rule {
+    pub find: String,
+    pub replace: String,
+    pub regex: bool,
+    pub case: Case,
+    pub template: String,
+    pub start: u64,
+    pub extension: Option<String>,
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self {
+            find: String::new(),
+            replace: String::new(),
+            regex: false,
+            case: Case::Unchanged,
+            template: String::from("{name}"),
+            start: 1,
+            extension: None,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Conflict {
+    //Another file in the batch would get the same name.
+    Duplicate,
+    //A file that isn't being renamed already has this name.
+    Exists,
+    Invalid(&'static str),
+}
+
+impl fmt::Display for Conflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Conflict::Duplicate => write!(f, "Same name as another file"),
+            Conflict::Exists => write!(f, "Already exists"),
+            Conflict::Invalid(reason) => write!(f, "{reason}"),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct Rename {
+    pub from: PathBuf,
+    pub to: PathBuf,
+    pub conflict: Option<Conflict>,
+}
+
+impl Rename {
+    pub fn is_unchanged(&self) -> bool {
+        self.from == self.to
+    }
+}
+
+pub fn preview(files: &[PathBuf], rule: &Rule) -> Result<Vec<Rename>, regex::Error> {
+    let regex = if rule.regex && !rule.find.is_empty() {
+        Some(Regex::new(&rule.find)?)
+    } else {
+        None
+    };
+
+    //Files on servers are renamed too, so everything goes through the file system.
+    let disk = vfs::disk();
+    let mut renames: Vec<Rename> = files
+        .iter()
+        .enumerate()
+        .map(|(i, file)| {
+            let metadata = disk.stat(file).ok();
+            let number = rule.start + i as u64;
+            let name = new_name(file, metadata, rule, regex.as_ref(), number);
+            let conflict = validate(&name);
+            Rename {
+                from: file.clone(),
+                to: file.with_file_name(name),
+                conflict,
+            }
+        })
+        .collect();
+
+    let mut targets: HashMap<PathBuf, usize> = HashMap::new();
+    for rename in &renames {
+        *targets.entry(key(&rename.to)).or_default() += 1;
+    }
+    let sources: HashSet<PathBuf> = renames.iter().map(|r| key(&r.from)).collect();
+
+    for rename in renames.iter_mut().filter(|r| r.conflict.is_none()) {
+        let target = key(&rename.to);
+        if targets[&target] > 1 {
+            rename.conflict = Some(Conflict::Duplicate);
+        } else if !sources.contains(&target) && crate::vacant(&rename.to).is_err() {
+            //Files in the batch move out of the way so their old names are free.
+            rename.conflict = Some(Conflict::Exists);
+        }
+    }
+
+    Ok(renames)
+}
+
+//Windows doesn't care about case so "a.txt" and "A.txt" are the same file.
+fn key(path: &Path) -> PathBuf {
+    if cfg!(windows) {
+        PathBuf::from(path.to_string_lossy().to_lowercase())
+    } else {
+        path.to_path_buf()
+    }
+}
+
+fn validate(name: &str) -> Option<Conflict> {
+    let invalid: &[char] = if cfg!(windows) {
+        &['/', '\\', ':', '*', '?', '"', '<', '>', '|']
+    } else {
+        &['/']
+    };
+
+    if name.is_empty() || name == "." || name == ".." {
+        Some(Conflict::Invalid("Empty name"))
+    } else if name.contains(invalid) {
+        Some(Conflict::Invalid("Invalid character"))
+    } else {
+        None
+    }
+}
+
+fn new_name(
+    file: &Path,
+    metadata: Option<Metadata>,
+    rule: &Rule,
+    regex: Option<&Regex>,
+    number: u64,
+) -> String {
+    let dir = metadata.is_some_and(|m| m.is_dir());
+    let file_name = file.file_name().unwrap_or_default().to_string_lossy();
+    //Dot files like ".gitignore" are all name and no extension.
+    let (stem, ext) = match file_name.rfind('.') {
+        Some(i) if i > 0 && !dir => (&file_name[..i], Some(&file_name[i + 1..])),
+        _ => (&*file_name, None),
+    };
+
+    let stem = match regex {
+        Some(regex) => regex.replace_all(stem, rule.replace.as_str()).to_string(),
+        None if !rule.find.is_empty() => stem.replace(&rule.find, &rule.replace),
+        None => stem.to_string(),
+    };
+
+    let stem = match rule.case {
+        Case::Unchanged => stem,
+        Case::Lower => stem.to_lowercase(),
+        Case::Upper => stem.to_uppercase(),
+        Case::Title => title_case(&stem),
+    };
+
+    let modified = metadata.and_then(|m| m.modified);
+    let mut name = expand(
+        &rule.template,
+        modified,
+        &stem,
+        ext.unwrap_or_default(),
+        number,
+    );
+
+    match (&rule.extension, ext) {
+        (Some(new), _) if new.is_empty() => (),
+        (Some(new), _) => {
+            name.push('.');
+            name.push_str(new.trim_start_matches('.'));
+        }
+        (None, Some(ext)) => {
+            name.push('.');
+            name.push_str(ext);
+        }
+        (None, None) => (),
+    }
+
+    name
+}
+
+fn expand(
+    template: &str,
+    modified: Option<SystemTime>,
+    stem: &str,
+    ext: &str,
+    number: u64,
+) -> String {
+    let mut out = String::new();
+    let mut rest = template;
+
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let Some(end) = rest[start..].find('}') else {
+            break;
+        };
+        let token = &rest[start + 1..start + end];
+        let (name, arg) = match token.split_once(':') {
+            Some((name, arg)) => (name, Some(arg)),
+            None => (token, None),
+        };
+
+        match name {
+            "name" => out.push_str(stem),
+            "ext" => out.push_str(ext),
+            "n" => {
+                let width = arg.and_then(|w| w.parse().ok()).unwrap_or(0);
+                out.push_str(&format!("{number:0width$}"));
+            }
+            "date" => {
+                if let Some(modified) = modified {
+                    let date: DateTime<Local> = modified.into();
+                    let format = arg.unwrap_or("%Y-%m-%d");
+                    out.push_str(&date.format(format).to_string());
+                }
+            }
+            //Leave anything unknown alone.
+            _ => out.push_str(&rest[start..start + end + 1]),
+        }
+
+        rest = &rest[start + end + 1..];
+    }
+
+    out.push_str(rest);
+    out
+}
+
+fn title_case(s: &str) -> String {
+    let mut out = String::with_capacity(s.len());
+    let mut start = true;
+    for c in s.chars() {
+        if start {
+            out.extend(c.to_uppercase());
+        } else {
+            out.extend(c.to_lowercase());
+        }
+        start = !c.is_alphanumeric();
+    }
+    out
+}
+
+#[derive(Debug)]
+pub struct Error {
+    pub path: PathBuf,
+    pub error: Box<crate::Error>,
+    //False if some files couldn't be put back to their old names.
+    pub rolled_back: bool,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Failed to rename {}: {}",
+            self.path.display(),
+            self.error
+        )?;
+        if !self.rolled_back {
+            write!(f, " (some files could not be restored)")?;
+        }
+        Ok(())
+    }
+}
+
+//Renames everything or nothing. Files are first moved to temporary names so that
+//swaps like a -> b, b -> a work, then to their new names.
+pub fn apply(renames: &[Rename]) -> Result<(), Error> {
+    let renames: Vec<&Rename> = renames.iter().filter(|r| !r.is_unchanged()).collect();
+    let temps: Vec<PathBuf> = renames
+        .iter()
+        .enumerate()
+        .map(|(i, r)| {
+            r.from
+                .with_file_name(format!(".ex-rename-{}-{i}", std::process::id()))
+        })
+        .collect();
+
+    //Undo the first `n` moves of `from[i] -> to[i]`.
+    let disk = vfs::disk();
+    let undo = |from: &[&Path], to: &[&Path], n: usize| {
+        let mut ok = true;
+        for i in (0..n).rev() {
+            ok &= disk.rename(to[i], from[i]).is_ok();
+        }
+        ok
+    };
+
+    let originals: Vec<&Path> = renames.iter().map(|r| r.from.as_path()).collect();
+    let temps: Vec<&Path> = temps.iter().map(PathBuf::as_path).collect();
+    let targets: Vec<&Path> = renames.iter().map(|r| r.to.as_path()).collect();
+
+    for i in 0..renames.len() {
+        if let Err(error) = disk.rename(originals[i], temps[i]) {
+            let rolled_back = undo(&originals, &temps, i);
+            return Err(Error {
+                path: originals[i].to_path_buf(),
+                error: Box::new(error),
+                rolled_back,
+            });
+        }
+    }
+
+    for i in 0..renames.len() {
+        //Renames never overwrite, so anything that appeared since the preview stops it.
+        if let Err(error) = disk.rename(temps[i], targets[i]) {
+            //Both always run, files that can't go back shouldn't strand the rest.
+            let back = undo(&temps, &targets, i);
+            let restored = undo(&originals, &temps, renames.len());
+            let rolled_back = back && restored;
+            return Err(Error {
+                path: originals[i].to_path_buf(),
+                error: Box::new(error),
+                rolled_back,
+            });
+        }
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    fn rename(from: &Path, to: &str) -> Rename {
+        Rename {
+            from: from.to_path_buf(),
+            to: from.with_file_name(to),
+            conflict: None,
+        }
+    }
+
+    #[test]
+    fn failed_rename_restores_names() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+        fs::write(&a, "a").unwrap();
+        fs::write(&b, "b").unwrap();
+        //Appears after the preview, so only the second phase notices it.
+        fs::write(dir.path().join("y"), "y").unwrap();
+
+        let error = apply(&[rename(&a, "x"), rename(&b, "y")]).unwrap_err();
+        assert!(matches!(*error.error, crate::Error::AlreadyExists(_)));
+        assert!(error.rolled_back);
+
+        let mut names: Vec<String> = fs::read_dir(dir.path())
+            .unwrap()
+            .map(|entry| entry.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        assert_eq!(names, ["a", "b", "y"]);
+        assert_eq!(fs::read(&a).unwrap(), b"a");
+        assert_eq!(fs::read(&b).unwrap(), b"b");
+    }
+
+    #[test]
+    fn swap() {
+        let dir = tempfile::tempdir().unwrap();
+        let (a, b) = (dir.path().join("a"), dir.path().join("b"));
+        fs::write(&a, "a").unwrap();
+        fs::write(&b, "b").unwrap();
+
+        apply(&[rename(&a, "b"), rename(&b, "a")]).unwrap();
+        assert_eq!(fs::read(&a).unwrap(), b"b");
+        assert_eq!(fs::read(&b).unwrap(), b"a");
+    }
+}
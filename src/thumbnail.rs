@@ -0,0 +1,143 @@
+//Thumbnails are cached on disk following the freedesktop thumbnail spec.
+//https://specifications.freedesktop.org/thumbnail-spec/latest/
+use image::RgbaImage;
+use std::{
+    fs,
+    io::{self, BufWriter},
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+use url::Url;
+
+//The spec calls 128x128 thumbnails "normal".
+pub const SIZE: u32 = 128;
+
+pub fn cache_dir() -> Option<PathBuf> {
+    let cache = dirs::cache_dir()?;
+    //Windows has no shared thumbnail folder so keep ours apart from everything else.
+    #[cfg(windows)]
+    let cache = cache.join("ex");
+    Some(cache.join("thumbnails").join("normal"))
+}
+
+//Returns a cached thumbnail or generates and caches a new one.
+pub fn load(path: &Path) -> Result<RgbaImage, String> {
+    let key = Key::new(path).map_err(|e| e.to_string())?;
+
+    if let Some(image) = key.cached() {
+        return Ok(image);
+    }
+
+    let image = image::open(path)
+        .map_err(|e| e.to_string())?
+        .thumbnail(SIZE, SIZE)
+        .to_rgba8();
+
+    //Failing to write to the cache shouldn't stop the thumbnail from showing.
+    let _ = key.store(&image);
+
+    Ok(image)
+}
+
+struct Key {
+    uri: String,
+    mtime: u64,
+    size: u64,
+    cache: PathBuf,
+}
+
+impl Key {
+    fn new(path: &Path) -> io::Result<Self> {
+        let path = fs::canonicalize(path)?;
+        let metadata = fs::metadata(&path)?;
+        let mtime = metadata
+            .modified()?
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+        let uri = Url::from_file_path(&path)
+            .map_err(|_| io::Error::new(io::ErrorKind::InvalidInput, "Path is not absolute"))?
+            .to_string();
+        let cache = cache_dir()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "No cache directory"))?
+            .join(format!("{:x}.png", md5::compute(&uri)));
+
+        Ok(Self {
+            uri,
+            mtime,
+            size: metadata.len(),
+            cache,
+        })
+    }
+
+    fn cached(&self) -> Option<RgbaImage> {
+        let file = fs::File::open(&self.cache).ok()?;
+        let reader = png::Decoder::new(file).read_info().ok()?;
+
+        //A thumbnail is stale once the original has been modified.
+        let text = &reader.info().uncompressed_latin1_text;
+        let value = |keyword: &str| {
+            text.iter()
+                .find(|chunk| chunk.keyword == keyword)
+                .map(|chunk| chunk.text.as_str())
+        };
+        if value("Thumb::MTime")? != self.mtime.to_string() {
+            return None;
+        }
+        if let Some(size) = value("Thumb::Size") {
+            if size != self.size.to_string() {
+                return None;
+            }
+        }
+
+        let bytes = fs::read(&self.cache).ok()?;
+        let image = image::load_from_memory_with_format(&bytes, image::ImageFormat::Png).ok()?;
+        Some(image.to_rgba8())
+    }
+
+    fn store(&self, image: &RgbaImage) -> Result<(), png::EncodingError> {
+        let dir = self.cache.parent().unwrap_or(Path::new(""));
+        create_private_dir(dir)?;
+
+        //Write to a temporary file first so other readers never see half a thumbnail.
+        let temp = self
+            .cache
+            .with_extension(format!("ex-{}.png", std::process::id()));
+        let file = fs::File::create(&temp)?;
+
+        let mut encoder = png::Encoder::new(BufWriter::new(file), image.width(), image.height());
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.add_text_chunk(String::from("Thumb::URI"), self.uri.clone())?;
+        encoder.add_text_chunk(String::from("Thumb::MTime"), self.mtime.to_string())?;
+        encoder.add_text_chunk(String::from("Thumb::Size"), self.size.to_string())?;
+        encoder.add_text_chunk(String::from("Software"), String::from("ex"))?;
+
+        let result = encoder.write_header().and_then(|mut writer| {
+            writer.write_image_data(image.as_raw())?;
+            writer.finish()
+        });
+
+        match result {
+            Ok(_) => Ok(fs::rename(&temp, &self.cache)?),
+            Err(e) => {
+                let _ = fs::remove_file(&temp);
+                Err(e)
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    use std::os::unix::fs::DirBuilderExt;
+    fs::DirBuilder::new()
+        .recursive(true)
+        .mode(0o700)
+        .create(dir)
+}
+
+#[cfg(not(unix))]
+fn create_private_dir(dir: &Path) -> io::Result<()> {
+    fs::create_dir_all(dir)
+}
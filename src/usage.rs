@@ -0,0 +1,206 @@
+use crate::file_type::{self, Category};
+use jwalk::WalkDir;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicBool, Ordering},
+    time::{Duration, Instant},
+};
+
+pub struct Node {
+    pub path: PathBuf,
+    pub bytes: u64,
+    pub is_dir: bool,
+    //Largest first.
+    pub children: Vec<Node>,
+}
+
+impl Node {
+    pub fn name(&self) -> String {
+        self.path
+            .file_name()
+            .unwrap_or(self.path.as_os_str())
+            .to_string_lossy()
+            .to_string()
+    }
+
+    pub fn find(&self, path: &Path) -> Option<&Node> {
+        if self.path == path {
+            return Some(self);
+        }
+        self.children
+            .iter()
+            .find(|child| path.starts_with(&child.path))?
+            .find(path)
+    }
+
+    //Removes a node from the tree and returns how many bytes it held.
+    pub fn remove(&mut self, path: &Path) -> Option<u64> {
+        let i = self
+            .children
+            .iter()
+            .position(|child| path.starts_with(&child.path))?;
+
+        let bytes = if self.children[i].path == path {
+            self.children.remove(i).bytes
+        } else {
+            self.children[i].remove(path)?
+        };
+
+        self.bytes -= bytes;
+        Some(bytes)
+    }
+
+    fn sort(&mut self) {
+        self.children
+            .sort_unstable_by_key(|child| std::cmp::Reverse(child.bytes));
+        for child in &mut self.children {
+            child.sort();
+        }
+    }
+}
+
+pub struct TypeUsage {
+    pub name: String,
+    pub category: Category,
+    pub bytes: u64,
+    pub files: u64,
+}
+
+pub struct Usage {
+    pub root: Node,
+    //Largest first.
+    pub types: Vec<TypeUsage>,
+    pub files: u64,
+}
+
+impl Usage {
+    pub fn remove(&mut self, path: &Path) {
+        if let Some(node) = self.root.find(path) {
+            //Files that get removed no longer count towards their type.
+            let mut removed = Vec::new();
+            collect_files(node, &mut removed);
+            for (file, bytes) in removed {
+                let name = file_type::from_path(&file).name;
+                if let Some(usage) = self.types.iter_mut().find(|t| t.name == name) {
+                    usage.bytes = usage.bytes.saturating_sub(bytes);
+                    usage.files = usage.files.saturating_sub(1);
+                    self.files -= 1;
+                }
+            }
+            self.types.retain(|t| t.files > 0);
+        }
+        self.root.remove(path);
+    }
+}
+
+fn collect_files(node: &Node, files: &mut Vec<(PathBuf, u64)>) {
+    if node.is_dir {
+        for child in &node.children {
+            collect_files(child, files);
+        }
+    } else {
+        files.push((node.path.clone(), node.bytes));
+    }
+}
+
+//Scans the whole tree in parallel, `progress` is called with the files and bytes seen so far.
+//Returns `None` if the scan was cancelled.
+pub fn scan(path: &Path, cancel: &AtomicBool, mut progress: impl FnMut(u64, u64)) -> Option<Usage> {
+    let mut dirs: HashMap<PathBuf, Node> = HashMap::new();
+    let mut files: Vec<(PathBuf, u64)> = Vec::new();
+    let mut types: HashMap<String, TypeUsage> = HashMap::new();
+    let mut bytes = 0;
+    let mut last = Instant::now();
+
+    dirs.insert(path.to_path_buf(), dir(path));
+
+    for entry in WalkDir::new(path)
+        .skip_hidden(false)
+        .follow_links(false)
+        .into_iter()
+        .flatten()
+        .filter(|entry| entry.depth > 0)
+    {
+        if cancel.load(Ordering::Relaxed) {
+            return None;
+        }
+
+        let file = entry.path();
+        if entry.file_type().is_dir() {
+            dirs.insert(file.clone(), dir(&file));
+            continue;
+        }
+
+        let size = entry.metadata().map(|m| m.len()).unwrap_or(0);
+        let file_type = file_type::from_path(&file);
+        let usage = types
+            .entry(file_type.name.clone())
+            .or_insert_with(|| TypeUsage {
+                name: file_type.name,
+                category: file_type.category,
+                bytes: 0,
+                files: 0,
+            });
+        usage.bytes += size;
+        usage.files += 1;
+
+        bytes += size;
+        files.push((file, size));
+
+        if last.elapsed() > Duration::from_millis(100) {
+            progress(files.len() as u64, bytes);
+            last = Instant::now();
+        }
+    }
+
+    let file_count = files.len() as u64;
+    for (file, size) in files {
+        if let Some(parent) = file.parent().and_then(|parent| dirs.get_mut(parent)) {
+            parent.bytes += size;
+            parent.children.push(Node {
+                path: file,
+                bytes: size,
+                is_dir: false,
+                children: Vec::new(),
+            });
+        }
+    }
+
+    //Fold the deepest folders into their parents first so the sizes add up.
+    let mut paths: Vec<PathBuf> = dirs.keys().cloned().collect();
+    paths.sort_unstable_by_key(|path| std::cmp::Reverse(path.components().count()));
+    for dir in paths {
+        if dir == path {
+            continue;
+        }
+        let Some(node) = dirs.remove(&dir) else {
+            continue;
+        };
+        if let Some(parent) = dir.parent().and_then(|parent| dirs.get_mut(parent)) {
+            parent.bytes += node.bytes;
+            parent.children.push(node);
+        }
+    }
+
+    let mut root = dirs.remove(path)?;
+    root.sort();
+
+    let mut types: Vec<TypeUsage> = types.into_values().collect();
+    types.sort_unstable_by_key(|t| std::cmp::Reverse(t.bytes));
+
+    Some(Usage {
+        root,
+        types,
+        files: file_count,
+    })
+}
+
+fn dir(path: &Path) -> Node {
+    Node {
+        path: path.to_path_buf(),
+        bytes: 0,
+        is_dir: true,
+        children: Vec::new(),
+    }
+}
@@ -0,0 +1,220 @@
+//Everything `Ex` needs from a file system, so folders can live somewhere other than the local disk.
+use crate::{Error, Result};
+use std::{
+    io::{self, Read},
+    path::{Path, PathBuf},
+    sync::{Arc, OnceLock},
+    time::SystemTime,
+};
+
+pub(crate) mod archive;
+mod local;
+mod memory;
+mod remote;
+mod s3;
+mod sftp;
+mod webdav;
+
+pub use archive::{folder, is_archive, Archive, Archives};
+pub use local::Local;
+pub use memory::Memory;
+pub use remote::{is_remote, Connection, Location, Protocol, Remotes, Secret, SCHEMES};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Kind {
+    File,
+    Dir,
+    Symlink,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct Metadata {
+    pub kind: Kind,
+    //Folders are always 0.
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+}
+
+impl Metadata {
+    pub fn is_dir(&self) -> bool {
+        self.kind == Kind::Dir
+    }
+
+    pub fn is_file(&self) -> bool {
+        self.kind == Kind::File
+    }
+}
+
+//Paths are always absolute. Like the rest of the app, nothing that exists is ever replaced by
+//`rename`, `mkdir` or `copy`, only `write` overwrites.
+pub trait Vfs: Send + Sync {
+    //Every item in `dir` along with its metadata, since most file systems return both at once.
+    fn list(&self, dir: &Path) -> Result<Vec<(PathBuf, Metadata)>>;
+
+    //Links are followed.
+    fn stat(&self, path: &Path) -> Result<Metadata>;
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>>;
+
+    fn write(&self, path: &Path, data: &[u8]) -> Result<()>;
+
+    //Like `read`, without holding the whole file in memory.
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send>> {
+        Ok(Box::new(io::Cursor::new(self.read(path)?)))
+    }
+
+    //Like `write` with the `len` bytes from `reader`, without holding them all in memory.
+    fn write_from(&self, path: &Path, reader: &mut dyn Read, len: u64) -> Result<()> {
+        let mut data = Vec::with_capacity(len as usize);
+        reader
+            .read_to_end(&mut data)
+            .map_err(|e| Error::io(path, e))?;
+        self.write(path, &data)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()>;
+
+    //Folders are removed along with everything inside them.
+    fn remove(&self, path: &Path) -> Result<()>;
+
+    fn mkdir(&self, path: &Path) -> Result<()>;
+
+    //Folders are copied along with everything inside them.
+    fn copy(&self, from: &Path, to: &Path) -> Result<()>;
+
+    //Where the item is on the local disk, if it's there at all.
+    fn local_path(&self, _path: &Path) -> Option<PathBuf> {
+        None
+    }
+
+    fn read_only(&self, _path: &Path) -> bool {
+        false
+    }
+}
+
+//Lets tabs hold any file system as `Ex<Arc<dyn Vfs>>`.
+impl<V: Vfs + ?Sized> Vfs for Arc<V> {
+    fn list(&self, dir: &Path) -> Result<Vec<(PathBuf, Metadata)>> {
+        (**self).list(dir)
+    }
+
+    fn stat(&self, path: &Path) -> Result<Metadata> {
+        (**self).stat(path)
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        (**self).read(path)
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> Result<()> {
+        (**self).write(path, data)
+    }
+
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send>> {
+        (**self).open_read(path)
+    }
+
+    fn write_from(&self, path: &Path, reader: &mut dyn Read, len: u64) -> Result<()> {
+        (**self).write_from(path, reader, len)
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        (**self).rename(from, to)
+    }
+
+    fn remove(&self, path: &Path) -> Result<()> {
+        (**self).remove(path)
+    }
+
+    fn mkdir(&self, path: &Path) -> Result<()> {
+        (**self).mkdir(path)
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        (**self).copy(from, to)
+    }
+
+    fn local_path(&self, path: &Path) -> Option<PathBuf> {
+        (**self).local_path(path)
+    }
+
+    fn read_only(&self, path: &Path) -> bool {
+        (**self).read_only(path)
+    }
+}
+
+type Disk = Archives<Remotes<Local>>;
+
+fn shared() -> &'static Arc<Disk> {
+    static DISK: OnceLock<Arc<Disk>> = OnceLock::new();
+    DISK.get_or_init(|| Arc::new(Archives::new(Remotes::new(Local))))
+}
+
+//The local disk and any servers opened so far, with archives opened as folders. It's shared so
+//each archive is only read once and each server only connected to once.
+pub fn disk() -> Arc<dyn Vfs> {
+    shared().clone()
+}
+
+//Serves `scheme://authority` from `vfs` for the rest of the run, see `Remotes::mount`.
+pub fn mount(scheme: &str, authority: &str, vfs: Arc<dyn Vfs>) {
+    shared().inner().mount(scheme, authority, vfs);
+}
+
+//Lets tabs open the servers saved in the connection manager, see `Remotes::save`.
+pub fn save_connections(connections: &[Connection]) {
+    shared().inner().save(connections);
+}
+
+//Copies between two file systems, a file at a time so large ones never sit in memory.
+//Links are skipped since they can't be read.
+pub fn transfer(from_vfs: &dyn Vfs, from: &Path, to_vfs: &dyn Vfs, to: &Path) -> Result<()> {
+    if to_vfs.stat(to).is_ok() {
+        return Err(Error::AlreadyExists(to.to_path_buf()));
+    }
+
+    let metadata = from_vfs.stat(from)?;
+    match metadata.kind {
+        Kind::File => {
+            let mut reader = from_vfs.open_read(from)?;
+            to_vfs.write_from(to, &mut reader, metadata.len)
+        }
+        Kind::Symlink => Ok(()),
+        Kind::Dir => {
+            to_vfs.mkdir(to)?;
+            for (path, _) in from_vfs.list(from)? {
+                if let Some(name) = path.file_name() {
+                    transfer(from_vfs, &path, to_vfs, &to.join(name))?;
+                }
+            }
+            Ok(())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn mounted_server() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("file");
+        std::fs::write(&file, "data").unwrap();
+
+        mount("sftp", "mounted", Arc::new(Memory::new()));
+        let remote = crate::copy(&file, Path::new("sftp://mounted/")).unwrap();
+        assert_eq!(remote, Path::new("sftp://mounted/file"));
+        assert_eq!(disk().read(&remote).unwrap(), b"data");
+
+        //Moving it back leaves nothing on the server.
+        let back = dir.path().join("back");
+        std::fs::create_dir(&back).unwrap();
+        crate::cut(&remote, &back).unwrap();
+        assert_eq!(std::fs::read(back.join("file")).unwrap(), b"data");
+        assert!(disk()
+            .list(Path::new("sftp://mounted/"))
+            .unwrap()
+            .is_empty());
+    }
+}
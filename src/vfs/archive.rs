@@ -0,0 +1,575 @@
+use super::{transfer, Kind, Metadata, Vfs};
+use crate::{Error, Result};
+use chrono::{Local as Timezone, NaiveDate, TimeZone};
+use flate2::read::GzDecoder;
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs::File,
+    io::{self, BufReader, Cursor, Read, Seek, Write},
+    path::{Component, Path, PathBuf},
+    sync::{
+        mpsc::{sync_channel, Receiver, SyncSender},
+        Arc, Mutex,
+    },
+    thread,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use xz2::read::XzDecoder;
+use zip::ZipArchive;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub(crate) enum Format {
+    Zip,
+    Tar,
+    TarGz,
+    TarXz,
+    TarZst,
+}
+
+const SUFFIXES: [(&str, Format); 8] = [
+    (".zip", Format::Zip),
+    (".tar", Format::Tar),
+    (".tar.gz", Format::TarGz),
+    (".tgz", Format::TarGz),
+    (".tar.xz", Format::TarXz),
+    (".txz", Format::TarXz),
+    (".tar.zst", Format::TarZst),
+    (".tzst", Format::TarZst),
+];
+
+impl Format {
+    pub(crate) fn from_path(path: &Path) -> Option<Format> {
+        split(path).map(|(_, format)| format)
+    }
+
+    pub(crate) fn decoder(self, reader: Box<dyn ReadSeek>) -> io::Result<Box<dyn Read>> {
+        Ok(match self {
+            Format::Zip | Format::Tar => reader,
+            Format::TarGz => Box::new(GzDecoder::new(reader)),
+            Format::TarXz => Box::new(XzDecoder::new(reader)),
+            Format::TarZst => Box::new(zstd::Decoder::new(reader)?),
+        })
+    }
+}
+
+//The name without the archive's extensions, "photos.tar.gz" is "photos".
+fn split(path: &Path) -> Option<(String, Format)> {
+    let name = path.file_name()?.to_string_lossy();
+    let lower = name.to_ascii_lowercase();
+    SUFFIXES.iter().find_map(|(suffix, format)| {
+        let stem = lower.strip_suffix(suffix)?;
+        Some((name[..stem.len()].to_string(), *format))
+    })
+}
+
+//Whether `path` is named like an archive that can be browsed.
+pub fn is_archive(path: &Path) -> bool {
+    Format::from_path(path).is_some()
+}
+
+//The folder an archive is extracted into when it gets a folder of its own.
+pub fn folder(path: &Path) -> Option<PathBuf> {
+    let (stem, _) = split(path)?;
+    Some(path.with_file_name(stem))
+}
+
+pub(crate) trait ReadSeek: Read + Seek {}
+
+impl<T: Read + Seek> ReadSeek for T {}
+
+#[derive(Clone)]
+enum Source {
+    File(PathBuf),
+    //Archives inside other archives are kept in memory.
+    Bytes(Arc<[u8]>),
+}
+
+impl Source {
+    fn reader(&self) -> io::Result<Box<dyn ReadSeek>> {
+        Ok(match self {
+            Source::File(path) => Box::new(BufReader::new(File::open(path)?)),
+            Source::Bytes(bytes) => Box::new(Cursor::new(bytes.clone())),
+        })
+    }
+}
+
+struct Entry {
+    metadata: Metadata,
+    //Position inside the archive, folders that only exist implicitly don't have one.
+    index: Option<usize>,
+}
+
+//A zip or tar file as a read-only folder. The archive's own path is the root, so everything
+//inside shows up below it.
+pub struct Archive {
+    root: PathBuf,
+    format: Format,
+    source: Source,
+    entries: BTreeMap<PathBuf, Entry>,
+}
+
+//Zip files store the local time without a timezone.
+pub(crate) fn zip_time(time: zip::DateTime) -> Option<SystemTime> {
+    let date = NaiveDate::from_ymd_opt(time.year().into(), time.month().into(), time.day().into())?
+        .and_hms_opt(
+            time.hour().into(),
+            time.minute().into(),
+            time.second().into(),
+        )?;
+    Some(Timezone.from_local_datetime(&date).earliest()?.into())
+}
+
+//Tar paths can be absolute or climb out of the archive, those entries are skipped.
+pub(crate) fn clean(path: &Path) -> Option<PathBuf> {
+    let mut clean = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::Normal(name) => clean.push(name),
+            Component::CurDir => (),
+            _ => return None,
+        }
+    }
+    (!clean.as_os_str().is_empty()).then_some(clean)
+}
+
+fn read_only(path: &Path) -> Error {
+    Error::io(
+        path,
+        io::Error::new(io::ErrorKind::ReadOnlyFilesystem, "Archives are read-only"),
+    )
+}
+
+impl Archive {
+    pub fn open(vfs: &dyn Vfs, path: &Path) -> Result<Self> {
+        let format =
+            Format::from_path(path).ok_or_else(|| Error::InvalidName(path.to_path_buf()))?;
+        let metadata = vfs.stat(path)?;
+        let source = match vfs.local_path(path) {
+            Some(local) => Source::File(local),
+            None => Source::Bytes(vfs.read(path)?.into()),
+        };
+
+        let mut archive = Self {
+            root: path.to_path_buf(),
+            format,
+            source,
+            entries: BTreeMap::new(),
+        };
+        archive.entries.insert(
+            archive.root.clone(),
+            Entry {
+                metadata: Metadata {
+                    kind: Kind::Dir,
+                    len: 0,
+                    modified: metadata.modified,
+                },
+                index: None,
+            },
+        );
+        archive.index().map_err(|e| Error::io(path, e))?;
+        Ok(archive)
+    }
+
+    fn index(&mut self) -> io::Result<()> {
+        let reader = self.source.reader()?;
+        if self.format == Format::Zip {
+            let mut zip = ZipArchive::new(reader)?;
+            for i in 0..zip.len() {
+                let file = zip.by_index(i)?;
+                let Some(name) = file.enclosed_name().and_then(clean) else {
+                    continue;
+                };
+                let is_dir = file.is_dir();
+                let metadata = Metadata {
+                    kind: if is_dir { Kind::Dir } else { Kind::File },
+                    len: if is_dir { 0 } else { file.size() },
+                    modified: zip_time(file.last_modified()),
+                };
+                self.insert(&name, metadata, Some(i));
+            }
+            return Ok(());
+        }
+
+        let mut tar = tar::Archive::new(self.format.decoder(reader)?);
+        for (i, entry) in tar.entries()?.enumerate() {
+            let entry = entry?;
+            let Some(name) = clean(&entry.path()?) else {
+                continue;
+            };
+            let header = entry.header();
+            let kind = match header.entry_type() {
+                tar::EntryType::Directory => Kind::Dir,
+                tar::EntryType::Symlink => Kind::Symlink,
+                tar::EntryType::Regular | tar::EntryType::Continuous => Kind::File,
+                _ => continue,
+            };
+            let metadata = Metadata {
+                kind,
+                len: if kind == Kind::File { entry.size() } else { 0 },
+                modified: header
+                    .mtime()
+                    .ok()
+                    .map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+            };
+            self.insert(&name, metadata, Some(i));
+        }
+        Ok(())
+    }
+
+    //Archives don't always list the folders their files are in.
+    fn insert(&mut self, name: &Path, metadata: Metadata, index: Option<usize>) {
+        let path = self.root.join(name);
+        for parent in path.ancestors().skip(1) {
+            if parent == self.root {
+                break;
+            }
+            self.entries.entry(parent.to_path_buf()).or_insert(Entry {
+                metadata: Metadata {
+                    kind: Kind::Dir,
+                    len: 0,
+                    modified: None,
+                },
+                index: None,
+            });
+        }
+        self.entries.insert(path, Entry { metadata, index });
+    }
+
+    fn entry(&self, path: &Path) -> Result<&Entry> {
+        self.entries
+            .get(path)
+            .ok_or_else(|| Error::NotFound(path.to_path_buf()))
+    }
+}
+
+fn extract(format: Format, source: &Source, index: usize, out: &mut dyn Write) -> io::Result<()> {
+    let reader = source.reader()?;
+    if format == Format::Zip {
+        io::copy(&mut ZipArchive::new(reader)?.by_index(index)?, out)?;
+        return Ok(());
+    }
+
+    //Compressed tar files can't seek, so they're read from the start every time.
+    let mut tar = tar::Archive::new(format.decoder(reader)?);
+    let mut entry = tar
+        .entries()?
+        .nth(index)
+        .ok_or_else(|| io::Error::from(io::ErrorKind::UnexpectedEof))??;
+    io::copy(&mut entry, out)?;
+    Ok(())
+}
+
+//Entries borrow the archive they're in, so they're streamed from a thread of their own. The
+//thread stops once the reader is dropped.
+struct Pipe {
+    receiver: Receiver<io::Result<Vec<u8>>>,
+    chunk: Cursor<Vec<u8>>,
+}
+
+impl Read for Pipe {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            let n = self.chunk.read(buf)?;
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+            match self.receiver.recv() {
+                Ok(Ok(chunk)) => self.chunk = Cursor::new(chunk),
+                Ok(Err(e)) => return Err(e),
+                Err(_) => return Ok(0),
+            }
+        }
+    }
+}
+
+struct Chunks(SyncSender<io::Result<Vec<u8>>>);
+
+impl Write for Chunks {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.0
+            .send(Ok(buf.to_vec()))
+            .map_err(|_| io::Error::from(io::ErrorKind::BrokenPipe))?;
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+impl Vfs for Archive {
+    fn list(&self, dir: &Path) -> Result<Vec<(PathBuf, Metadata)>> {
+        if !self.entry(dir)?.metadata.is_dir() {
+            return Err(Error::NotADirectory(dir.to_path_buf()));
+        }
+        Ok(self
+            .entries
+            .range(dir.to_path_buf()..)
+            .take_while(|(path, _)| path.starts_with(dir))
+            .filter(|(path, _)| path.parent() == Some(dir))
+            .map(|(path, entry)| (path.clone(), entry.metadata))
+            .collect())
+    }
+
+    fn stat(&self, path: &Path) -> Result<Metadata> {
+        Ok(self.entry(path)?.metadata)
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        let entry = self.entry(path)?;
+        match entry.index {
+            Some(index) if !entry.metadata.is_dir() => {
+                let mut data = Vec::new();
+                extract(self.format, &self.source, index, &mut data)
+                    .map_err(|e| Error::io(path, e))?;
+                Ok(data)
+            }
+            _ => Err(Error::io(
+                path,
+                io::Error::new(io::ErrorKind::IsADirectory, "Is a folder"),
+            )),
+        }
+    }
+
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send>> {
+        let entry = self.entry(path)?;
+        let index = match entry.index {
+            Some(index) if !entry.metadata.is_dir() => index,
+            _ => {
+                return Err(Error::io(
+                    path,
+                    io::Error::new(io::ErrorKind::IsADirectory, "Is a folder"),
+                ))
+            }
+        };
+
+        let (sender, receiver) = sync_channel(4);
+        let (format, source) = (self.format, self.source.clone());
+        thread::spawn(move || {
+            let mut chunks = Chunks(sender.clone());
+            if let Err(e) = extract(format, &source, index, &mut chunks) {
+                let _ = sender.send(Err(e));
+            }
+        });
+        Ok(Box::new(Pipe {
+            receiver,
+            chunk: Cursor::new(Vec::new()),
+        }))
+    }
+
+    fn write(&self, path: &Path, _: &[u8]) -> Result<()> {
+        Err(read_only(path))
+    }
+
+    fn write_from(&self, path: &Path, _: &mut dyn Read, _: u64) -> Result<()> {
+        Err(read_only(path))
+    }
+
+    fn rename(&self, from: &Path, _: &Path) -> Result<()> {
+        Err(read_only(from))
+    }
+
+    fn remove(&self, path: &Path) -> Result<()> {
+        Err(read_only(path))
+    }
+
+    fn mkdir(&self, path: &Path) -> Result<()> {
+        Err(read_only(path))
+    }
+
+    fn copy(&self, _: &Path, to: &Path) -> Result<()> {
+        Err(read_only(to))
+    }
+
+    fn read_only(&self, _: &Path) -> bool {
+        true
+    }
+}
+
+//Opens archives on `V` as folders, anything outside of them goes straight to `V`.
+pub struct Archives<V> {
+    inner: V,
+    opened: Mutex<HashMap<PathBuf, Opened>>,
+}
+
+//Reopened whenever the archive's modified time changes.
+type Opened = (Option<SystemTime>, Arc<Archives<Archive>>);
+
+impl<V: Vfs> Archives<V> {
+    pub fn new(inner: V) -> Self {
+        Self {
+            inner,
+            opened: Mutex::new(HashMap::new()),
+        }
+    }
+
+    pub fn inner(&self) -> &V {
+        &self.inner
+    }
+
+    //The outermost archive holding `path`. With `itself` an archive also holds its own path,
+    //which is how it's browsed, otherwise it's still the file it is on `V`.
+    fn find(&self, path: &Path, itself: bool) -> Result<Option<Arc<Archives<Archive>>>> {
+        let mut ancestors: Vec<&Path> = path.ancestors().skip(usize::from(!itself)).collect();
+        ancestors.reverse();
+
+        for ancestor in ancestors {
+            if !is_archive(ancestor) {
+                continue;
+            }
+            let Ok(metadata) = self.inner.stat(ancestor) else {
+                return Ok(None);
+            };
+            if !metadata.is_file() {
+                continue;
+            }
+
+            if let Some((modified, archive)) = self.opened.lock().unwrap().get(ancestor) {
+                if *modified == metadata.modified {
+                    return Ok(Some(archive.clone()));
+                }
+            }
+            //Indexing can take a while, other archives shouldn't have to wait for it.
+            let archive = Arc::new(Archives::new(Archive::open(&self.inner, ancestor)?));
+            self.opened
+                .lock()
+                .unwrap()
+                .insert(ancestor.to_path_buf(), (metadata.modified, archive.clone()));
+            return Ok(Some(archive));
+        }
+        Ok(None)
+    }
+
+    //Copies out of whichever archive `from` is in, nested ones included.
+    fn extract(&self, from: &Path, vfs: &dyn Vfs, to: &Path) -> Result<()> {
+        match self.find(from, false)? {
+            Some(archive) => archive.extract(from, vfs, to),
+            None => transfer(&self.inner, from, vfs, to),
+        }
+    }
+}
+
+impl<V: Vfs> Vfs for Archives<V> {
+    fn list(&self, dir: &Path) -> Result<Vec<(PathBuf, Metadata)>> {
+        match self.find(dir, true)? {
+            Some(archive) => archive.list(dir),
+            None => self.inner.list(dir),
+        }
+    }
+
+    fn stat(&self, path: &Path) -> Result<Metadata> {
+        match self.find(path, true)? {
+            Some(archive) => archive.stat(path),
+            None => self.inner.stat(path),
+        }
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        match self.find(path, false)? {
+            Some(archive) => archive.read(path),
+            None => self.inner.read(path),
+        }
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> Result<()> {
+        match self.find(path, false)? {
+            Some(archive) => archive.write(path, data),
+            None => self.inner.write(path, data),
+        }
+    }
+
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send>> {
+        match self.find(path, false)? {
+            Some(archive) => archive.open_read(path),
+            None => self.inner.open_read(path),
+        }
+    }
+
+    fn write_from(&self, path: &Path, reader: &mut dyn Read, len: u64) -> Result<()> {
+        match self.find(path, false)? {
+            Some(archive) => archive.write_from(path, reader, len),
+            None => self.inner.write_from(path, reader, len),
+        }
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        match (self.find(from, false)?, self.find(to, false)?) {
+            (Some(archive), _) | (None, Some(archive)) => archive.rename(from, to),
+            (None, None) => self.inner.rename(from, to),
+        }
+    }
+
+    fn remove(&self, path: &Path) -> Result<()> {
+        match self.find(path, false)? {
+            Some(archive) => archive.remove(path),
+            None => self.inner.remove(path),
+        }
+    }
+
+    fn mkdir(&self, path: &Path) -> Result<()> {
+        match self.find(path, false)? {
+            Some(archive) => archive.mkdir(path),
+            None => self.inner.mkdir(path),
+        }
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        match (self.find(from, false)?, self.find(to, false)?) {
+            (_, Some(archive)) => archive.copy(from, to),
+            (Some(archive), None) => archive.extract(from, &self.inner, to),
+            (None, None) => self.inner.copy(from, to),
+        }
+    }
+
+    fn local_path(&self, path: &Path) -> Option<PathBuf> {
+        match self.find(path, false) {
+            Ok(None) => self.inner.local_path(path),
+            _ => None,
+        }
+    }
+
+    fn read_only(&self, path: &Path) -> bool {
+        match self.find(path, true) {
+            Ok(None) => self.inner.read_only(path),
+            _ => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::Local;
+    use flate2::{write::GzEncoder, Compression};
+
+    #[test]
+    fn entries_are_streamed() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("big.tar.gz");
+        let encoder = GzEncoder::new(File::create(&path).unwrap(), Compression::fast());
+        let mut tar = tar::Builder::new(encoder);
+        let data: Vec<u8> = (0..1_000_000u32).map(|i| i as u8).collect();
+        let mut header = tar::Header::new_gnu();
+        header.set_size(data.len() as u64);
+        header.set_mode(0o644);
+        tar.append_data(&mut header, "big", data.as_slice())
+            .unwrap();
+        tar.into_inner().unwrap().finish().unwrap();
+
+        let archives = Archives::new(Local);
+        let entry = path.join("big");
+        let mut start = Vec::new();
+        let reader = archives.open_read(&entry).unwrap();
+        reader.take(10).read_to_end(&mut start).unwrap();
+        assert_eq!(start, &data[..10]);
+
+        let mut all = Vec::new();
+        archives
+            .open_read(&entry)
+            .unwrap()
+            .read_to_end(&mut all)
+            .unwrap();
+        assert_eq!(all, data);
+        assert!(archives.open_read(&path.join("missing")).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+use super::{Kind, Metadata, Vfs};
+use crate::{Error, Result};
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+//The disk the app is running on.
+#[derive(Clone, Copy, Default, Debug)]
+pub struct Local;
+
+impl From<fs::Metadata> for Metadata {
+    fn from(metadata: fs::Metadata) -> Self {
+        let kind = if metadata.is_dir() {
+            Kind::Dir
+        } else if metadata.is_symlink() {
+            Kind::Symlink
+        } else {
+            Kind::File
+        };
+        Metadata {
+            kind,
+            len: if metadata.is_dir() { 0 } else { metadata.len() },
+            modified: metadata.modified().ok(),
+        }
+    }
+}
+
+fn vacant(path: &Path) -> Result<()> {
+    if path.symlink_metadata().is_ok() {
+        Err(Error::AlreadyExists(path.to_path_buf()))
+    } else {
+        Ok(())
+    }
+}
+
+#[cfg(unix)]
+fn symlink(target: &Path, link: &Path, _dir: bool) -> io::Result<()> {
+    std::os::unix::fs::symlink(target, link)
+}
+
+#[cfg(windows)]
+fn symlink(target: &Path, link: &Path, dir: bool) -> io::Result<()> {
+    if dir {
+        std::os::windows::fs::symlink_dir(target, link)
+    } else {
+        std::os::windows::fs::symlink_file(target, link)
+    }
+}
+
+impl Vfs for Local {
+    fn list(&self, dir: &Path) -> Result<Vec<(PathBuf, Metadata)>> {
+        let entries = fs::read_dir(dir).map_err(|e| Error::io(dir, e))?;
+        Ok(entries
+            .flatten()
+            .filter_map(|entry| {
+                let path = entry.path();
+                //Broken links are still listed, as links.
+                let metadata = path.metadata().or_else(|_| path.symlink_metadata()).ok()?;
+                Some((path, metadata.into()))
+            })
+            .collect())
+    }
+
+    fn stat(&self, path: &Path) -> Result<Metadata> {
+        let metadata = path.metadata().map_err(|e| Error::io(path, e))?;
+        Ok(metadata.into())
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        fs::read(path).map_err(|e| Error::io(path, e))
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> Result<()> {
+        fs::write(path, data).map_err(|e| Error::io(path, e))
+    }
+
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send>> {
+        let file = fs::File::open(path).map_err(|e| Error::io(path, e))?;
+        Ok(Box::new(file))
+    }
+
+    fn write_from(&self, path: &Path, reader: &mut dyn Read, _: u64) -> Result<()> {
+        let mut file = fs::File::create(path).map_err(|e| Error::io(path, e))?;
+        io::copy(reader, &mut file).map_err(|e| Error::io(path, e))?;
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        vacant(to)?;
+        fs::rename(from, to).map_err(|e| Error::io(from, e))
+    }
+
+    fn remove(&self, path: &Path) -> Result<()> {
+        //Links are removed, never followed.
+        let metadata = path.symlink_metadata().map_err(|e| Error::io(path, e))?;
+        let result = if metadata.is_dir() {
+            fs::remove_dir_all(path)
+        } else {
+            fs::remove_file(path)
+        };
+        result.map_err(|e| Error::io(path, e))
+    }
+
+    fn mkdir(&self, path: &Path) -> Result<()> {
+        fs::create_dir(path).map_err(|e| Error::io(path, e))
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        vacant(to)?;
+        if to.starts_with(from) {
+            return Err(Error::io(
+                to,
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Can't copy a folder into itself",
+                ),
+            ));
+        }
+        //Links are copied as links, never followed.
+        let metadata = from.symlink_metadata().map_err(|e| Error::io(from, e))?;
+        if metadata.is_symlink() {
+            let target = fs::read_link(from).map_err(|e| Error::io(from, e))?;
+            return symlink(&target, to, from.is_dir()).map_err(|e| Error::io(to, e));
+        }
+        if !metadata.is_dir() {
+            return fs::copy(from, to)
+                .map(|_| ())
+                .map_err(|e| Error::io(from, e));
+        }
+
+        self.mkdir(to)?;
+        for (path, _) in self.list(from)? {
+            if let Some(name) = path.file_name() {
+                self.copy(&path, &to.join(name))?;
+            }
+        }
+        Ok(())
+    }
+
+    fn local_path(&self, path: &Path) -> Option<PathBuf> {
+        Some(path.to_path_buf())
+    }
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::os::unix::fs::symlink;
+
+    #[test]
+    fn links() {
+        let dir = tempfile::tempdir().unwrap();
+        let folder = dir.path().join("folder");
+        fs::create_dir(&folder).unwrap();
+        fs::write(folder.join("file"), "data").unwrap();
+        symlink("file", folder.join("link")).unwrap();
+        symlink("missing", folder.join("broken")).unwrap();
+
+        let mut entries = Local.list(&folder).unwrap();
+        entries.sort_by(|a, b| a.0.cmp(&b.0));
+        let kinds: Vec<Kind> = entries.iter().map(|(_, m)| m.kind).collect();
+        assert_eq!(kinds, [Kind::Symlink, Kind::File, Kind::File]);
+
+        let copy = dir.path().join("copy");
+        Local.copy(&folder, &copy).unwrap();
+        assert_eq!(fs::read_link(copy.join("link")).unwrap(), Path::new("file"));
+        assert_eq!(
+            fs::read_link(copy.join("broken")).unwrap(),
+            Path::new("missing")
+        );
+        assert_eq!(fs::read(copy.join("link")).unwrap(), b"data");
+    }
+}
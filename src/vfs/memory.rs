@@ -0,0 +1,220 @@
+use super::{Kind, Metadata, Vfs};
+use crate::{Error, Result};
+use std::{
+    collections::BTreeMap,
+    io,
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::SystemTime,
+};
+
+enum Node {
+    Dir(SystemTime),
+    File(Vec<u8>, SystemTime),
+}
+
+impl Node {
+    fn metadata(&self) -> Metadata {
+        match self {
+            Node::Dir(modified) => Metadata {
+                kind: Kind::Dir,
+                len: 0,
+                modified: Some(*modified),
+            },
+            Node::File(data, modified) => Metadata {
+                kind: Kind::File,
+                len: data.len() as u64,
+                modified: Some(*modified),
+            },
+        }
+    }
+}
+
+//A file system that only exists while it's in use, starting with an empty root folder.
+pub struct Memory {
+    nodes: Mutex<BTreeMap<PathBuf, Node>>,
+}
+
+impl Default for Memory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Memory {
+    pub fn new() -> Self {
+        let mut nodes = BTreeMap::new();
+        nodes.insert(PathBuf::from("/"), Node::Dir(SystemTime::now()));
+        Self {
+            nodes: Mutex::new(nodes),
+        }
+    }
+}
+
+fn not_found(path: &Path) -> Error {
+    Error::NotFound(path.to_path_buf())
+}
+
+//The parent has to be a folder for anything to be created inside it.
+fn check_parent(nodes: &BTreeMap<PathBuf, Node>, path: &Path) -> Result<()> {
+    let parent = path
+        .parent()
+        .ok_or_else(|| Error::InvalidName(path.to_path_buf()))?;
+    match nodes.get(parent) {
+        Some(Node::Dir(_)) => Ok(()),
+        Some(Node::File(..)) => Err(Error::NotADirectory(parent.to_path_buf())),
+        None => Err(not_found(parent)),
+    }
+}
+
+fn check_vacant(nodes: &BTreeMap<PathBuf, Node>, path: &Path) -> Result<()> {
+    if nodes.contains_key(path) {
+        return Err(Error::AlreadyExists(path.to_path_buf()));
+    }
+    check_parent(nodes, path)
+}
+
+//`path` and everything below it.
+fn subtree(nodes: &BTreeMap<PathBuf, Node>, path: &Path) -> Vec<PathBuf> {
+    nodes
+        .range(path.to_path_buf()..)
+        .map(|(p, _)| p)
+        .take_while(|p| p.starts_with(path))
+        .cloned()
+        .collect()
+}
+
+//Where `path` ends up when `from` is moved to `to`, joining an empty path would add a slash.
+fn moved(path: &Path, from: &Path, to: &Path) -> PathBuf {
+    match path.strip_prefix(from) {
+        Ok(rest) if !rest.as_os_str().is_empty() => to.join(rest),
+        _ => to.to_path_buf(),
+    }
+}
+
+impl Vfs for Memory {
+    fn list(&self, dir: &Path) -> Result<Vec<(PathBuf, Metadata)>> {
+        let nodes = self.nodes.lock().unwrap();
+        match nodes.get(dir) {
+            Some(Node::Dir(_)) => (),
+            Some(Node::File(..)) => return Err(Error::NotADirectory(dir.to_path_buf())),
+            None => return Err(not_found(dir)),
+        }
+        Ok(nodes
+            .iter()
+            .filter(|(path, _)| path.parent() == Some(dir))
+            .map(|(path, node)| (path.clone(), node.metadata()))
+            .collect())
+    }
+
+    fn stat(&self, path: &Path) -> Result<Metadata> {
+        let nodes = self.nodes.lock().unwrap();
+        nodes
+            .get(path)
+            .map(Node::metadata)
+            .ok_or_else(|| not_found(path))
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        let nodes = self.nodes.lock().unwrap();
+        match nodes.get(path) {
+            Some(Node::File(data, _)) => Ok(data.clone()),
+            Some(Node::Dir(_)) => Err(Error::io(
+                path,
+                io::Error::new(io::ErrorKind::IsADirectory, "Is a folder"),
+            )),
+            None => Err(not_found(path)),
+        }
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> Result<()> {
+        let mut nodes = self.nodes.lock().unwrap();
+        check_parent(&nodes, path)?;
+        if let Some(Node::Dir(_)) = nodes.get(path) {
+            return Err(Error::io(
+                path,
+                io::Error::new(io::ErrorKind::IsADirectory, "Is a folder"),
+            ));
+        }
+        nodes.insert(
+            path.to_path_buf(),
+            Node::File(data.to_vec(), SystemTime::now()),
+        );
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        let mut nodes = self.nodes.lock().unwrap();
+        if !nodes.contains_key(from) {
+            return Err(not_found(from));
+        }
+        check_vacant(&nodes, to)?;
+        if to.starts_with(from) {
+            return Err(Error::io(
+                to,
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Can't move a folder into itself",
+                ),
+            ));
+        }
+
+        for path in subtree(&nodes, from) {
+            if let Some(node) = nodes.remove(&path) {
+                nodes.insert(moved(&path, from, to), node);
+            }
+        }
+        Ok(())
+    }
+
+    fn remove(&self, path: &Path) -> Result<()> {
+        let mut nodes = self.nodes.lock().unwrap();
+        if !nodes.contains_key(path) {
+            return Err(not_found(path));
+        }
+        if path.parent().is_none() {
+            return Err(Error::InvalidName(path.to_path_buf()));
+        }
+        for path in subtree(&nodes, path) {
+            nodes.remove(&path);
+        }
+        Ok(())
+    }
+
+    fn mkdir(&self, path: &Path) -> Result<()> {
+        let mut nodes = self.nodes.lock().unwrap();
+        check_vacant(&nodes, path)?;
+        nodes.insert(path.to_path_buf(), Node::Dir(SystemTime::now()));
+        Ok(())
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        let mut nodes = self.nodes.lock().unwrap();
+        if !nodes.contains_key(from) {
+            return Err(not_found(from));
+        }
+        check_vacant(&nodes, to)?;
+        if to.starts_with(from) {
+            return Err(Error::io(
+                to,
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Can't copy a folder into itself",
+                ),
+            ));
+        }
+
+        let now = SystemTime::now();
+        for path in subtree(&nodes, from) {
+            let Some(node) = nodes.get(&path) else {
+                continue;
+            };
+            let copy = match node {
+                Node::Dir(_) => Node::Dir(now),
+                Node::File(data, _) => Node::File(data.clone(), now),
+            };
+            nodes.insert(moved(&path, from, to), copy);
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,578 @@
+use super::{s3::S3, sftp::Sftp, transfer, webdav::WebDav, Metadata, Vfs};
+use crate::{Error, Result};
+use percent_encoding::{utf8_percent_encode, AsciiSet, NON_ALPHANUMERIC};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    env, fs,
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::Duration,
+};
+
+//Every kind of server a tab can be opened on. `dav` is WebDAV over http and `davs` over https.
+pub const SCHEMES: [&str; 4] = ["sftp", "dav", "davs", "s3"];
+
+//Characters that never need escaping in urls or S3 signatures.
+const UNRESERVED: &AsciiSet = &NON_ALPHANUMERIC
+    .remove(b'-')
+    .remove(b'_')
+    .remove(b'.')
+    .remove(b'~');
+
+pub(crate) fn encode(text: &str) -> String {
+    utf8_percent_encode(text, UNRESERVED).to_string()
+}
+
+//The HTTP client shared by WebDAV and S3.
+pub(crate) struct Http {
+    pub agent: ureq::Agent,
+}
+
+impl Http {
+    pub fn new() -> Self {
+        let agent = ureq::AgentBuilder::new()
+            .timeout_connect(Duration::from_secs(10))
+            .timeout_read(Duration::from_secs(30))
+            .build();
+        Self { agent }
+    }
+}
+
+//Status codes become the closest `io::ErrorKind` so they turn into the same errors as on disk.
+pub(crate) fn http_error(error: ureq::Error) -> io::Error {
+    match error {
+        ureq::Error::Status(status, response) => {
+            let kind = match status {
+                404 => io::ErrorKind::NotFound,
+                401 | 403 => io::ErrorKind::PermissionDenied,
+                409 | 412 => io::ErrorKind::AlreadyExists,
+                _ => io::ErrorKind::Other,
+            };
+            io::Error::new(kind, format!("{status} {}", response.status_text()))
+        }
+        //Tells `Remotes` to connect again.
+        ureq::Error::Transport(transport) => {
+            io::Error::new(io::ErrorKind::ConnectionAborted, transport.to_string())
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub enum Protocol {
+    Sftp,
+    WebDav,
+    S3,
+}
+
+impl Protocol {
+    pub const ALL: [Protocol; 3] = [Protocol::Sftp, Protocol::WebDav, Protocol::S3];
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Protocol::Sftp => "SFTP",
+            Protocol::WebDav => "WebDAV",
+            Protocol::S3 => "S3",
+        }
+    }
+
+    pub fn scheme(self) -> &'static str {
+        match self {
+            Protocol::Sftp => "sftp",
+            Protocol::WebDav => "dav",
+            Protocol::S3 => "s3",
+        }
+    }
+}
+
+//Where a password or secret key comes from when connecting, so it's never saved itself.
+#[derive(Clone, PartialEq, Eq, Debug, Default, Serialize, Deserialize)]
+pub enum Secret {
+    #[default]
+    None,
+    //The name of an environment variable.
+    Env(String),
+    //A file holding just the secret, like the ones secret managers mount.
+    File(PathBuf),
+}
+
+impl Secret {
+    pub fn read(&self) -> io::Result<Option<String>> {
+        match self {
+            Secret::None => Ok(None),
+            Secret::Env(name) => env::var(name)
+                .map(Some)
+                .map_err(|_| io::Error::other(format!("${name} isn't set"))),
+            Secret::File(path) => fs::read_to_string(path)
+                .map(|text| Some(text.trim_end().to_string()))
+                .map_err(|e| io::Error::other(format!("{}: {e}", path.display()))),
+        }
+    }
+}
+
+//A server saved in the connection manager. Tabs open it as `scheme://name/path`.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Connection {
+    pub name: String,
+    pub protocol: Protocol,
+    //`user@host:port` for SFTP, otherwise the url of the share or S3 endpoint.
+    pub endpoint: String,
+    //S3 only, most endpoints other than AWS ignore it.
+    pub region: String,
+    //The user for WebDAV and the access key for S3. SFTP has it in the endpoint.
+    pub user: String,
+    pub secret: Secret,
+}
+
+impl Default for Connection {
+    fn default() -> Self {
+        Self {
+            name: String::new(),
+            protocol: Protocol::Sftp,
+            endpoint: String::new(),
+            region: String::new(),
+            user: String::new(),
+            secret: Secret::None,
+        }
+    }
+}
+
+impl Connection {
+    pub fn root(&self) -> PathBuf {
+        PathBuf::from(format!("{}/", self.server()))
+    }
+
+    fn server(&self) -> String {
+        format!("{}://{}", self.protocol.scheme(), self.name)
+    }
+
+    fn connect(&self) -> io::Result<Arc<dyn Vfs>> {
+        let secret = self.secret.read()?;
+        Ok(match self.protocol {
+            Protocol::Sftp => Arc::new(Sftp::connect(&self.endpoint)?),
+            Protocol::WebDav => Arc::new(WebDav::connect(&self.endpoint, &self.user, secret)?),
+            Protocol::S3 => {
+                //Falls back to the variables every S3 tool reads.
+                let access_key = match self.user.trim() {
+                    "" => env::var("AWS_ACCESS_KEY_ID").unwrap_or_default(),
+                    user => user.to_string(),
+                };
+                let secret_key = secret
+                    .or_else(|| env::var("AWS_SECRET_ACCESS_KEY").ok())
+                    .unwrap_or_default();
+                Arc::new(S3::connect(
+                    &self.endpoint,
+                    &self.region,
+                    access_key,
+                    secret_key,
+                )?)
+            }
+        })
+    }
+}
+
+//A path on another machine. They're written as `sftp://user@host/path` so they can be passed
+//around like any other path.
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Location {
+    pub scheme: String,
+    //Everything between `//` and the path, usually `user@host:port`.
+    pub authority: String,
+    //Always absolute.
+    pub path: PathBuf,
+}
+
+impl Location {
+    pub fn parse(path: &Path) -> Option<Location> {
+        let mut components = path.components();
+        let Component::Normal(scheme) = components.next()? else {
+            return None;
+        };
+        let scheme = scheme.to_str()?.strip_suffix(':')?;
+        if !SCHEMES.contains(&scheme) {
+            return None;
+        }
+        let authority = components.next()?.as_os_str().to_str()?.to_string();
+        let parts: Vec<_> = components
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect();
+
+        Some(Location {
+            scheme: scheme.to_string(),
+            authority,
+            path: PathBuf::from(format!("/{}", parts.join("/"))),
+        })
+    }
+
+    //`path` on the same server, written out in full.
+    pub fn join(&self, path: &Path) -> PathBuf {
+        let parts: Vec<_> = path
+            .components()
+            .filter(|component| matches!(component, Component::Normal(_)))
+            .map(|component| component.as_os_str().to_string_lossy())
+            .collect();
+        PathBuf::from(format!(
+            "{}://{}/{}",
+            self.scheme,
+            self.authority,
+            parts.join("/")
+        ))
+    }
+
+    pub fn root(&self) -> PathBuf {
+        self.join(Path::new("/"))
+    }
+
+    fn server(&self) -> String {
+        format!("{}://{}", self.scheme, self.authority)
+    }
+}
+
+pub fn is_remote(path: &Path) -> bool {
+    Location::parse(path).is_some()
+}
+
+//Sends paths on other machines to a connection for that machine, anything else goes to `V`.
+pub struct Remotes<V> {
+    inner: V,
+    //Kept open until they drop.
+    connections: Mutex<HashMap<String, Arc<dyn Vfs>>>,
+    saved: Mutex<Vec<Connection>>,
+}
+
+impl<V: Vfs> Remotes<V> {
+    pub fn new(inner: V) -> Self {
+        Self {
+            inner,
+            connections: Mutex::new(HashMap::new()),
+            saved: Mutex::new(Vec::new()),
+        }
+    }
+
+    //Replaces the saved connections. Any that changed are reconnected next time they're used.
+    pub fn save(&self, connections: &[Connection]) {
+        let mut saved = self.saved.lock().unwrap();
+        let mut open = self.connections.lock().unwrap();
+        for connection in saved.iter().chain(connections) {
+            if !saved.contains(connection) || !connections.contains(connection) {
+                open.remove(&connection.server());
+            }
+        }
+        *saved = connections.to_vec();
+    }
+
+    //Serves `scheme://authority` from `vfs` instead of connecting to it, which lets a `Memory`
+    //file system stand in for a server.
+    pub fn mount(&self, scheme: &str, authority: &str, vfs: Arc<dyn Vfs>) {
+        let server = format!("{scheme}://{authority}");
+        self.connections.lock().unwrap().insert(server, vfs);
+    }
+
+    fn connection(&self, location: &Location) -> Result<Arc<dyn Vfs>> {
+        let server = location.server();
+        if let Some(vfs) = self.connections.lock().unwrap().get(&server) {
+            return Ok(vfs.clone());
+        }
+
+        let saved = self
+            .saved
+            .lock()
+            .unwrap()
+            .iter()
+            .find(|connection| connection.server() == server)
+            .cloned();
+
+        //Connecting can take a while, so nothing else is held up in the meantime.
+        let authority = &location.authority;
+        let vfs = match (saved, location.scheme.as_str()) {
+            (Some(connection), _) => connection.connect(),
+            (None, "sftp") => Sftp::connect(authority).map(|sftp| Arc::new(sftp) as Arc<dyn Vfs>),
+            (None, "dav") => WebDav::connect(&format!("http://{authority}"), "", None)
+                .map(|dav| Arc::new(dav) as Arc<dyn Vfs>),
+            (None, "davs") => WebDav::connect(&format!("https://{authority}"), "", None)
+                .map(|dav| Arc::new(dav) as Arc<dyn Vfs>),
+            (None, _) => Err(io::Error::other(format!(
+                "There's no saved connection named {authority}"
+            ))),
+        }
+        .map_err(|e| Error::io(&location.root(), e))?;
+        let mut connections = self.connections.lock().unwrap();
+        Ok(connections.entry(server).or_insert(vfs).clone())
+    }
+
+    //Runs `f` on the server's connection. One that has dropped is thrown away so the next call
+    //connects again, and with `retry` that happens right away. Only retry what changes nothing,
+    //since there's no telling how far it got.
+    fn on<T>(
+        &self,
+        location: &Location,
+        retry: bool,
+        mut f: impl FnMut(&dyn Vfs) -> Result<T>,
+    ) -> Result<T> {
+        let mut run = |vfs: &dyn Vfs| f(vfs).map_err(|e| e.map_path(|path| location.join(path)));
+        let vfs = self.connection(location)?;
+        match run(&*vfs) {
+            Err(e) if dropped(&e) => {
+                self.disconnect(location, &vfs);
+                if retry {
+                    run(&*self.connection(location)?)
+                } else {
+                    Err(e)
+                }
+            }
+            result => result,
+        }
+    }
+
+    fn disconnect(&self, location: &Location, vfs: &Arc<dyn Vfs>) {
+        let mut connections = self.connections.lock().unwrap();
+        //Another thread may have connected again already.
+        let server = location.server();
+        if connections
+            .get(&server)
+            .is_some_and(|open| Arc::ptr_eq(open, vfs))
+        {
+            connections.remove(&server);
+        }
+    }
+}
+
+//The connection itself failed, rather than what was asked of it.
+fn dropped(error: &Error) -> bool {
+    let Error::Io { error, .. } = error else {
+        return false;
+    };
+    matches!(
+        error.kind(),
+        io::ErrorKind::ConnectionAborted
+            | io::ErrorKind::ConnectionReset
+            | io::ErrorKind::BrokenPipe
+            | io::ErrorKind::NotConnected
+            | io::ErrorKind::TimedOut
+            | io::ErrorKind::UnexpectedEof
+    )
+}
+
+impl<V: Vfs> Vfs for Remotes<V> {
+    fn list(&self, dir: &Path) -> Result<Vec<(PathBuf, Metadata)>> {
+        match Location::parse(dir) {
+            Some(location) => {
+                let entries = self.on(&location, true, |vfs| vfs.list(&location.path))?;
+                Ok(entries
+                    .into_iter()
+                    .map(|(path, metadata)| (location.join(&path), metadata))
+                    .collect())
+            }
+            None => self.inner.list(dir),
+        }
+    }
+
+    fn stat(&self, path: &Path) -> Result<Metadata> {
+        match Location::parse(path) {
+            Some(location) => self.on(&location, true, |vfs| vfs.stat(&location.path)),
+            None => self.inner.stat(path),
+        }
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        match Location::parse(path) {
+            Some(location) => self.on(&location, true, |vfs| vfs.read(&location.path)),
+            None => self.inner.read(path),
+        }
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> Result<()> {
+        match Location::parse(path) {
+            Some(location) => self.on(&location, false, |vfs| vfs.write(&location.path, data)),
+            None => self.inner.write(path, data),
+        }
+    }
+
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send>> {
+        match Location::parse(path) {
+            Some(location) => self.on(&location, true, |vfs| vfs.open_read(&location.path)),
+            None => self.inner.open_read(path),
+        }
+    }
+
+    fn write_from(&self, path: &Path, reader: &mut dyn Read, len: u64) -> Result<()> {
+        match Location::parse(path) {
+            Some(location) => self.on(&location, false, |vfs| {
+                vfs.write_from(&location.path, reader, len)
+            }),
+            None => self.inner.write_from(path, reader, len),
+        }
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        match (Location::parse(from), Location::parse(to)) {
+            (None, None) => self.inner.rename(from, to),
+            (Some(location), Some(other)) if location.server() == other.server() => {
+                self.on(&location, false, |vfs| {
+                    vfs.rename(&location.path, &other.path)
+                })
+            }
+            _ => Err(Error::CrossDevice(from.to_path_buf())),
+        }
+    }
+
+    fn remove(&self, path: &Path) -> Result<()> {
+        match Location::parse(path) {
+            Some(location) => self.on(&location, false, |vfs| vfs.remove(&location.path)),
+            None => self.inner.remove(path),
+        }
+    }
+
+    fn mkdir(&self, path: &Path) -> Result<()> {
+        match Location::parse(path) {
+            Some(location) => self.on(&location, false, |vfs| vfs.mkdir(&location.path)),
+            None => self.inner.mkdir(path),
+        }
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        match (Location::parse(from), Location::parse(to)) {
+            (None, None) => self.inner.copy(from, to),
+            (Some(location), Some(other)) if location.server() == other.server() => {
+                self.on(&location, false, |vfs| {
+                    vfs.copy(&location.path, &other.path)
+                })
+            }
+            //Between machines everything goes through this one.
+            _ => transfer(self, from, self, to),
+        }
+    }
+
+    fn local_path(&self, path: &Path) -> Option<PathBuf> {
+        if is_remote(path) {
+            None
+        } else {
+            self.inner.local_path(path)
+        }
+    }
+
+    fn read_only(&self, path: &Path) -> bool {
+        //Asked every frame, so this never connects.
+        let Some(location) = Location::parse(path) else {
+            return self.inner.read_only(path);
+        };
+        match self.connections.lock().unwrap().get(&location.server()) {
+            Some(vfs) => vfs.read_only(&location.path),
+            None => true,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vfs::Memory;
+
+    //A server whose connection has gone away.
+    struct Dropped;
+
+    impl Vfs for Dropped {
+        fn list(&self, dir: &Path) -> Result<Vec<(PathBuf, Metadata)>> {
+            Err(gone(dir))
+        }
+
+        fn stat(&self, path: &Path) -> Result<Metadata> {
+            Err(gone(path))
+        }
+
+        fn read(&self, path: &Path) -> Result<Vec<u8>> {
+            Err(gone(path))
+        }
+
+        fn write(&self, path: &Path, _: &[u8]) -> Result<()> {
+            Err(gone(path))
+        }
+
+        fn rename(&self, from: &Path, _: &Path) -> Result<()> {
+            Err(gone(from))
+        }
+
+        fn remove(&self, path: &Path) -> Result<()> {
+            Err(gone(path))
+        }
+
+        fn mkdir(&self, path: &Path) -> Result<()> {
+            Err(gone(path))
+        }
+
+        fn copy(&self, from: &Path, _: &Path) -> Result<()> {
+            Err(gone(from))
+        }
+    }
+
+    fn gone(path: &Path) -> Error {
+        Error::io(path, io::Error::from(io::ErrorKind::ConnectionReset))
+    }
+
+    #[test]
+    fn locations() {
+        let location = Location::parse(Path::new("sftp://me@box:2222/home/me")).unwrap();
+        assert_eq!(location.scheme, "sftp");
+        assert_eq!(location.authority, "me@box:2222");
+        assert_eq!(location.path, Path::new("/home/me"));
+        assert_eq!(
+            location.join(Path::new("/etc/hosts")),
+            Path::new("sftp://me@box:2222/etc/hosts")
+        );
+        assert_eq!(location.root(), Path::new("sftp://me@box:2222/"));
+
+        let root = Location::parse(Path::new("s3://minio")).unwrap();
+        assert_eq!(root.path, Path::new("/"));
+        assert_eq!(Location::parse(&root.join(&root.path)), Some(root));
+
+        assert_eq!(Location::parse(Path::new("/home/me")), None);
+        assert_eq!(Location::parse(Path::new("http://box/file")), None);
+    }
+
+    #[test]
+    fn copy_between_machines() {
+        let dir = tempfile::tempdir().unwrap();
+        let remotes = Remotes::new(crate::vfs::Local);
+        remotes.mount("sftp", "box", Arc::new(Memory::new()));
+
+        let local = dir.path().join("folder");
+        std::fs::create_dir(&local).unwrap();
+        std::fs::write(local.join("file"), "data").unwrap();
+
+        let remote = Path::new("sftp://box/folder");
+        remotes.copy(&local, remote).unwrap();
+        let entries = remotes.list(remote).unwrap();
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries[0].0, Path::new("sftp://box/folder/file"));
+        assert_eq!(remotes.read(&entries[0].0).unwrap(), b"data");
+
+        //Renames can't cross machines, the caller copies instead.
+        let back = dir.path().join("back");
+        assert!(matches!(
+            remotes.rename(remote, &back),
+            Err(Error::CrossDevice(_))
+        ));
+        remotes.copy(remote, &back).unwrap();
+        assert_eq!(std::fs::read(back.join("file")).unwrap(), b"data");
+
+        //Nothing is ever overwritten.
+        assert!(matches!(
+            remotes.copy(&local, remote),
+            Err(Error::AlreadyExists(_))
+        ));
+    }
+
+    #[test]
+    fn dropped_connections_reconnect() {
+        let remotes = Remotes::new(Memory::new());
+        remotes.mount("s3", "box", Arc::new(Dropped));
+
+        //The dead connection is thrown away and a new one is tried, which fails since there's
+        //no saved connection to make it with.
+        let error = remotes.stat(Path::new("s3://box/file")).unwrap_err();
+        assert!(error.to_string().contains("no saved connection"), "{error}");
+        assert!(remotes.connections.lock().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,551 @@
+use super::{
+    remote::{encode, http_error, Http},
+    Kind, Metadata, Vfs,
+};
+use crate::{Error, Result};
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use sha2::{Digest, Sha256};
+use std::{
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+use url::Url;
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|byte| format!("{byte:02x}")).collect()
+}
+
+fn hmac(key: &[u8], data: &str) -> Vec<u8> {
+    let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any size");
+    mac.update(data.as_bytes());
+    mac.finalize().into_bytes().to_vec()
+}
+
+fn time(date: &str) -> Option<std::time::SystemTime> {
+    let date = DateTime::parse_from_rfc3339(date)
+        .or_else(|_| DateTime::parse_from_rfc2822(date))
+        .ok()?;
+    let secs = u64::try_from(date.timestamp()).ok()?;
+    Some(UNIX_EPOCH + Duration::from_secs(secs))
+}
+
+//`/bucket/some/key` split into the bucket and the key, the root has neither.
+fn split(path: &Path) -> (Option<String>, String) {
+    let mut parts = path.components().filter_map(|component| match component {
+        Component::Normal(name) => Some(name.to_string_lossy().to_string()),
+        _ => None,
+    });
+    let bucket = parts.next();
+    let key: Vec<String> = parts.collect();
+    (bucket, key.join("/"))
+}
+
+//Objects along with the prefixes that look like folders.
+type Listing = (Vec<(String, Metadata)>, Vec<String>);
+
+fn folder() -> Metadata {
+    Metadata {
+        kind: Kind::Dir,
+        len: 0,
+        modified: None,
+    }
+}
+
+fn not_found(path: &Path) -> Error {
+    Error::NotFound(path.to_path_buf())
+}
+
+//Object storage that speaks the S3 API, like AWS or MinIO. Buckets are folders at the root and
+//`/` in keys makes the rest look like folders too. Requests are path style so any endpoint works.
+pub struct S3 {
+    http: Http,
+    endpoint: Url,
+    region: String,
+    access_key: String,
+    secret_key: String,
+}
+
+impl S3 {
+    pub fn connect(
+        endpoint: &str,
+        region: &str,
+        access_key: String,
+        secret_key: String,
+    ) -> io::Result<Self> {
+        let endpoint = Url::parse(endpoint)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+        let region = match region.trim() {
+            "" => String::from("us-east-1"),
+            region => region.to_string(),
+        };
+        let s3 = Self {
+            http: Http::new(),
+            endpoint,
+            region,
+            access_key,
+            secret_key,
+        };
+        //Listing buckets checks the credentials.
+        s3.send("GET", None, "", &[], &[], &[])?;
+        Ok(s3)
+    }
+
+    //Signs with AWS Signature Version 4 and sends it.
+    fn send(
+        &self,
+        method: &str,
+        bucket: Option<&str>,
+        key: &str,
+        query: &[(&str, &str)],
+        headers: &[(&str, &str)],
+        body: &[u8],
+    ) -> io::Result<ureq::Response> {
+        let payload = hex(&Sha256::digest(body));
+        self.request(method, bucket, key, query, headers, &payload)
+            .send_bytes(body)
+            .map_err(http_error)
+    }
+
+    //A signed request for a body with the SHA-256 `payload`, or `UNSIGNED-PAYLOAD` when it's
+    //streamed and can't be hashed up front.
+    fn request(
+        &self,
+        method: &str,
+        bucket: Option<&str>,
+        key: &str,
+        query: &[(&str, &str)],
+        headers: &[(&str, &str)],
+        payload: &str,
+    ) -> ureq::Request {
+        let mut path = self.endpoint.path().trim_end_matches('/').to_string();
+        for part in bucket
+            .into_iter()
+            .chain(key.split('/').filter(|p| !p.is_empty()))
+        {
+            path.push('/');
+            path.push_str(&encode(part));
+        }
+        if path.is_empty() || key.ends_with('/') {
+            path.push('/');
+        }
+
+        let mut query: Vec<(String, String)> = query
+            .iter()
+            .map(|(name, value)| (encode(name), encode(value)))
+            .collect();
+        query.sort();
+        let query = query
+            .iter()
+            .map(|(name, value)| format!("{name}={value}"))
+            .collect::<Vec<_>>()
+            .join("&");
+
+        let host = match self.endpoint.port() {
+            Some(port) => format!("{}:{port}", self.endpoint.host_str().unwrap_or_default()),
+            None => self.endpoint.host_str().unwrap_or_default().to_string(),
+        };
+        let now = Utc::now();
+        let date = now.format("%Y%m%d").to_string();
+        let timestamp = now.format("%Y%m%dT%H%M%SZ").to_string();
+
+        let mut signed: Vec<(String, String)> = vec![
+            (String::from("host"), host),
+            (String::from("x-amz-content-sha256"), payload.to_string()),
+            (String::from("x-amz-date"), timestamp.clone()),
+        ];
+        signed.extend(
+            headers
+                .iter()
+                .map(|(name, value)| (name.to_ascii_lowercase(), value.trim().to_string())),
+        );
+        signed.sort();
+        let canonical_headers: String = signed
+            .iter()
+            .map(|(name, value)| format!("{name}:{value}\n"))
+            .collect();
+        let signed_headers = signed
+            .iter()
+            .map(|(name, _)| name.as_str())
+            .collect::<Vec<_>>()
+            .join(";");
+
+        let canonical =
+            format!("{method}\n{path}\n{query}\n{canonical_headers}\n{signed_headers}\n{payload}");
+        let scope = format!("{date}/{}/s3/aws4_request", self.region);
+        let to_sign = format!(
+            "AWS4-HMAC-SHA256\n{timestamp}\n{scope}\n{}",
+            hex(&Sha256::digest(canonical.as_bytes()))
+        );
+        let key = hmac(format!("AWS4{}", self.secret_key).as_bytes(), &date);
+        let key = hmac(&key, &self.region);
+        let key = hmac(&key, "s3");
+        let key = hmac(&key, "aws4_request");
+        let signature = hex(&hmac(&key, &to_sign));
+        let authorization = format!(
+            "AWS4-HMAC-SHA256 Credential={}/{scope}, SignedHeaders={signed_headers}, \
+             Signature={signature}",
+            self.access_key
+        );
+
+        let mut url = self.endpoint.clone();
+        url.set_path(&path);
+        url.set_query((!query.is_empty()).then_some(query.as_str()));
+
+        let mut request = self
+            .http
+            .agent
+            .request(method, url.as_str())
+            .set("Authorization", &authorization);
+        for (name, value) in signed.iter().filter(|(name, _)| name != "host") {
+            request = request.set(name, value);
+        }
+        request
+    }
+
+    //Everything under `prefix`, a page at a time. With `delimiter` folders are listed once
+    //instead of everything inside them.
+    fn objects(
+        &self,
+        bucket: &str,
+        prefix: &str,
+        delimiter: bool,
+        limit: Option<usize>,
+    ) -> io::Result<Listing> {
+        let (mut objects, mut prefixes) = (Vec::new(), Vec::new());
+        let mut token: Option<String> = None;
+        let max_keys = limit.map(|limit| limit.to_string());
+
+        loop {
+            let mut query = vec![("list-type", "2"), ("prefix", prefix)];
+            if delimiter {
+                query.push(("delimiter", "/"));
+            }
+            if let Some(token) = &token {
+                query.push(("continuation-token", token));
+            }
+            if let Some(max_keys) = &max_keys {
+                query.push(("max-keys", max_keys));
+            }
+            let text = self
+                .send("GET", Some(bucket), "", &query, &[], &[])?
+                .into_string()?;
+            let document = roxmltree::Document::parse(&text)
+                .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+            let child = |node: roxmltree::Node, name| {
+                node.children()
+                    .find(|node| node.tag_name().name() == name)
+                    .and_then(|node| node.text())
+                    .map(str::to_string)
+            };
+
+            let root = document.root_element();
+            for node in root.children() {
+                match node.tag_name().name() {
+                    "Contents" => {
+                        let Some(key) = child(node, "Key") else {
+                            continue;
+                        };
+                        let metadata = Metadata {
+                            kind: Kind::File,
+                            len: child(node, "Size")
+                                .and_then(|size| size.parse().ok())
+                                .unwrap_or(0),
+                            modified: child(node, "LastModified").and_then(|date| time(&date)),
+                        };
+                        objects.push((key, metadata));
+                    }
+                    "CommonPrefixes" => prefixes.extend(child(node, "Prefix")),
+                    _ => (),
+                }
+            }
+
+            token = child(root, "NextContinuationToken");
+            let truncated = child(root, "IsTruncated").is_some_and(|t| t == "true");
+            if !truncated || token.is_none() || limit.is_some() {
+                return Ok((objects, prefixes));
+            }
+        }
+    }
+
+    fn buckets(&self) -> io::Result<Vec<(String, Metadata)>> {
+        let text = self.send("GET", None, "", &[], &[], &[])?.into_string()?;
+        let document = roxmltree::Document::parse(&text)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+        Ok(document
+            .descendants()
+            .filter(|node| node.tag_name().name() == "Bucket")
+            .filter_map(|bucket| {
+                let text = |name| {
+                    bucket
+                        .children()
+                        .find(|node| node.tag_name().name() == name)?
+                        .text()
+                };
+                let metadata = Metadata {
+                    modified: text("CreationDate").and_then(time),
+                    ..folder()
+                };
+                Some((text("Name")?.to_string(), metadata))
+            })
+            .collect())
+    }
+
+    //Every object in a folder, including the empty one that marks it.
+    fn keys(&self, bucket: &str, key: &str) -> io::Result<Vec<String>> {
+        let prefix = format!("{key}/");
+        let (objects, _) = self.objects(bucket, &prefix, false, None)?;
+        Ok(objects.into_iter().map(|(key, _)| key).collect())
+    }
+
+    fn vacant(&self, path: &Path) -> Result<()> {
+        match self.stat(path) {
+            Ok(_) => Err(Error::AlreadyExists(path.to_path_buf())),
+            Err(Error::NotFound(_)) => Ok(()),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn copy_object(
+        &self,
+        bucket: &str,
+        key: &str,
+        to_bucket: &str,
+        to_key: &str,
+    ) -> io::Result<()> {
+        let source = format!("/{}/{}", encode(bucket), encode_key(key));
+        self.send(
+            "PUT",
+            Some(to_bucket),
+            to_key,
+            &[],
+            &[("x-amz-copy-source", &source)],
+            &[],
+        )?;
+        Ok(())
+    }
+}
+
+//Keys keep their slashes.
+fn encode_key(key: &str) -> String {
+    key.split('/').map(encode).collect::<Vec<_>>().join("/")
+}
+
+impl Vfs for S3 {
+    fn list(&self, dir: &Path) -> Result<Vec<(PathBuf, Metadata)>> {
+        let (bucket, key) = split(dir);
+        let Some(bucket) = bucket else {
+            let buckets = self.buckets().map_err(|e| Error::io(dir, e))?;
+            return Ok(buckets
+                .into_iter()
+                .map(|(name, metadata)| (dir.join(name), metadata))
+                .collect());
+        };
+
+        let prefix = if key.is_empty() {
+            key
+        } else {
+            format!("{key}/")
+        };
+        let (objects, prefixes) = self
+            .objects(&bucket, &prefix, true, None)
+            .map_err(|e| Error::io(dir, e))?;
+        if objects.is_empty() && prefixes.is_empty() && !prefix.is_empty() {
+            return Err(not_found(dir));
+        }
+
+        let name = |key: &str| key[prefix.len()..].trim_end_matches('/').to_string();
+        let folders = prefixes
+            .iter()
+            .map(|prefix| (dir.join(name(prefix)), folder()));
+        let files = objects
+            .iter()
+            //Skips the marker for the folder itself.
+            .filter(|(key, _)| key.len() > prefix.len())
+            .map(|(key, metadata)| (dir.join(name(key)), *metadata));
+        Ok(folders.chain(files).collect())
+    }
+
+    fn stat(&self, path: &Path) -> Result<Metadata> {
+        let (bucket, key) = split(path);
+        let Some(bucket) = bucket else {
+            return Ok(folder());
+        };
+        if key.is_empty() {
+            return match self.send("HEAD", Some(&bucket), "", &[], &[], &[]) {
+                Ok(_) => Ok(folder()),
+                Err(e) => Err(Error::io(path, e)),
+            };
+        }
+
+        match self.send("HEAD", Some(&bucket), &key, &[], &[], &[]) {
+            Ok(response) => Ok(Metadata {
+                kind: Kind::File,
+                len: response
+                    .header("Content-Length")
+                    .and_then(|len| len.parse().ok())
+                    .unwrap_or(0),
+                modified: response.header("Last-Modified").and_then(time),
+            }),
+            //There's no object, but there might be some inside it.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {
+                let prefix = format!("{key}/");
+                let (objects, prefixes) = self
+                    .objects(&bucket, &prefix, true, Some(1))
+                    .map_err(|e| Error::io(path, e))?;
+                if objects.is_empty() && prefixes.is_empty() {
+                    Err(not_found(path))
+                } else {
+                    Ok(folder())
+                }
+            }
+            Err(e) => Err(Error::io(path, e)),
+        }
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        let (Some(bucket), key) = split(path) else {
+            return Err(not_found(path));
+        };
+        let response = self
+            .send("GET", Some(&bucket), &key, &[], &[], &[])
+            .map_err(|e| Error::io(path, e))?;
+        let mut data = Vec::new();
+        response
+            .into_reader()
+            .read_to_end(&mut data)
+            .map_err(|e| Error::io(path, e))?;
+        Ok(data)
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> Result<()> {
+        let (Some(bucket), key) = split(path) else {
+            return Err(not_found(path));
+        };
+        if key.is_empty() {
+            return Err(Error::AlreadyExists(path.to_path_buf()));
+        }
+        self.send("PUT", Some(&bucket), &key, &[], &[], data)
+            .map_err(|e| Error::io(path, e))?;
+        Ok(())
+    }
+
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send>> {
+        let (Some(bucket), key) = split(path) else {
+            return Err(not_found(path));
+        };
+        let response = self
+            .send("GET", Some(&bucket), &key, &[], &[], &[])
+            .map_err(|e| Error::io(path, e))?;
+        Ok(response.into_reader())
+    }
+
+    fn write_from(&self, path: &Path, reader: &mut dyn Read, len: u64) -> Result<()> {
+        let (Some(bucket), key) = split(path) else {
+            return Err(not_found(path));
+        };
+        if key.is_empty() {
+            return Err(Error::AlreadyExists(path.to_path_buf()));
+        }
+        //S3 needs the length up front, it doesn't take chunked uploads.
+        self.request("PUT", Some(&bucket), &key, &[], &[], "UNSIGNED-PAYLOAD")
+            .set("Content-Length", &len.to_string())
+            .send(reader)
+            .map_err(|e| Error::io(path, http_error(e)))?;
+        Ok(())
+    }
+
+    //There's no rename in S3, everything is copied and then removed.
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        self.copy(from, to)?;
+        self.remove(from)
+    }
+
+    fn remove(&self, path: &Path) -> Result<()> {
+        let (Some(bucket), key) = split(path) else {
+            return Err(Error::PermissionDenied(path.to_path_buf()));
+        };
+        if self.stat(path)?.is_file() {
+            self.send("DELETE", Some(&bucket), &key, &[], &[], &[])
+                .map_err(|e| Error::io(path, e))?;
+            return Ok(());
+        }
+
+        let keys = if key.is_empty() {
+            let (objects, _) = self
+                .objects(&bucket, "", false, None)
+                .map_err(|e| Error::io(path, e))?;
+            objects.into_iter().map(|(key, _)| key).collect()
+        } else {
+            self.keys(&bucket, &key).map_err(|e| Error::io(path, e))?
+        };
+        for key in keys {
+            self.send("DELETE", Some(&bucket), &key, &[], &[], &[])
+                .map_err(|e| Error::io(&path.join(&key), e))?;
+        }
+        if key.is_empty() {
+            self.send("DELETE", Some(&bucket), "", &[], &[], &[])
+                .map_err(|e| Error::io(path, e))?;
+        }
+        Ok(())
+    }
+
+    //Folders only exist while something is in them, so an empty object marks new ones.
+    fn mkdir(&self, path: &Path) -> Result<()> {
+        let (Some(bucket), key) = split(path) else {
+            return Err(Error::AlreadyExists(path.to_path_buf()));
+        };
+        self.vacant(path)?;
+        let key = if key.is_empty() {
+            key
+        } else {
+            format!("{key}/")
+        };
+        self.send("PUT", Some(&bucket), &key, &[], &[], &[])
+            .map_err(|e| Error::io(path, e))?;
+        Ok(())
+    }
+
+    //Objects are copied on the server, nothing is downloaded.
+    fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        let (Some(bucket), key) = split(from) else {
+            return Err(Error::InvalidName(from.to_path_buf()));
+        };
+        let (Some(to_bucket), to_key) = split(to) else {
+            return Err(Error::AlreadyExists(to.to_path_buf()));
+        };
+        if to.starts_with(from) {
+            return Err(Error::io(
+                to,
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Can't copy a folder into itself",
+                ),
+            ));
+        }
+        self.vacant(to)?;
+
+        if self.stat(from)?.is_file() {
+            return self
+                .copy_object(&bucket, &key, &to_bucket, &to_key)
+                .map_err(|e| Error::io(from, e));
+        }
+        if key.is_empty() || to_key.is_empty() {
+            return Err(Error::io(
+                to,
+                io::Error::new(io::ErrorKind::Unsupported, "Buckets can't be copied"),
+            ));
+        }
+        self.mkdir(to)?;
+        for object in self.keys(&bucket, &key).map_err(|e| Error::io(from, e))? {
+            let rest = &object[key.len()..];
+            if rest == "/" {
+                continue;
+            }
+            self.copy_object(&bucket, &object, &to_bucket, &format!("{to_key}{rest}"))
+                .map_err(|e| Error::io(from, e))?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,373 @@
+use super::{transfer, Kind, Metadata, Vfs};
+use crate::{Error, Result};
+use ssh2::{CheckResult, FileStat, KnownHostFileKind, RenameFlags, Session};
+use std::{
+    env, fs,
+    io::{self, Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    path::{Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+use url::Url;
+
+const TIMEOUT: Duration = Duration::from_secs(10);
+
+//The parts of `~/.ssh/config` that matter for connecting.
+#[derive(Default, Debug)]
+struct Config {
+    host_name: Option<String>,
+    user: Option<String>,
+    port: Option<u16>,
+    identity_files: Vec<PathBuf>,
+}
+
+//`*` matches anything and `?` any one character, hosts are case insensitive.
+fn wildcard(pattern: &[u8], host: &[u8]) -> bool {
+    match (pattern.first(), host.first()) {
+        (None, None) => true,
+        (Some(b'*'), _) => {
+            wildcard(&pattern[1..], host) || (!host.is_empty() && wildcard(pattern, &host[1..]))
+        }
+        (Some(b'?'), Some(_)) => wildcard(&pattern[1..], &host[1..]),
+        (Some(p), Some(h)) if p.eq_ignore_ascii_case(h) => wildcard(&pattern[1..], &host[1..]),
+        _ => false,
+    }
+}
+
+//A `Host` line applies if any pattern matches and none of the negated ones do.
+fn matches(patterns: &str, host: &str) -> bool {
+    let mut matched = false;
+    for pattern in patterns.split_whitespace() {
+        let (negated, pattern) = match pattern.strip_prefix('!') {
+            Some(pattern) => (true, pattern),
+            None => (false, pattern),
+        };
+        if wildcard(pattern.as_bytes(), host.as_bytes()) {
+            if negated {
+                return false;
+            }
+            matched = true;
+        }
+    }
+    matched
+}
+
+impl Config {
+    fn load(host: &str) -> Config {
+        let Some(home) = dirs::home_dir() else {
+            return Config::default();
+        };
+        match fs::read_to_string(home.join(".ssh").join("config")) {
+            Ok(text) => Config::parse(&text, host, &home),
+            Err(_) => Config::default(),
+        }
+    }
+
+    //Like ssh, the first value found for each option wins. `Match` blocks are skipped.
+    fn parse(text: &str, host: &str, home: &Path) -> Config {
+        let mut config = Config::default();
+        let mut active = true;
+
+        for line in text.lines().map(str::trim) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+            let (key, value) = line
+                .split_once(|c: char| c.is_whitespace() || c == '=')
+                .unwrap_or((line, ""));
+            let value = value
+                .trim_start_matches(|c: char| c.is_whitespace() || c == '=')
+                .trim()
+                .trim_matches('"');
+
+            match key.to_ascii_lowercase().as_str() {
+                "host" => active = matches(value, host),
+                "match" => active = false,
+                _ if !active => (),
+                "hostname" => {
+                    config
+                        .host_name
+                        .get_or_insert_with(|| value.replace("%h", host));
+                }
+                "user" => {
+                    config.user.get_or_insert_with(|| value.to_string());
+                }
+                "port" if config.port.is_none() => config.port = value.parse().ok(),
+                "identityfile" => {
+                    let file = match value.strip_prefix("~/") {
+                        Some(rest) => home.join(rest),
+                        None => PathBuf::from(value),
+                    };
+                    config.identity_files.push(file);
+                }
+                _ => (),
+            }
+        }
+        config
+    }
+}
+
+impl From<FileStat> for Metadata {
+    fn from(stat: FileStat) -> Self {
+        let kind = if stat.is_dir() {
+            Kind::Dir
+        } else if stat.file_type().is_symlink() {
+            Kind::Symlink
+        } else {
+            Kind::File
+        };
+        Metadata {
+            kind,
+            len: if kind == Kind::Dir {
+                0
+            } else {
+                stat.size.unwrap_or(0)
+            },
+            modified: stat
+                .mtime
+                .map(|secs| UNIX_EPOCH + Duration::from_secs(secs)),
+        }
+    }
+}
+
+//Anything other than an SFTP status means the session itself failed, which tells `Remotes`
+//to connect again.
+fn error(path: &Path, error: ssh2::Error) -> Error {
+    match error.code() {
+        ssh2::ErrorCode::SFTP(_) => Error::io(path, error.into()),
+        ssh2::ErrorCode::Session(_) => Error::io(
+            path,
+            io::Error::new(io::ErrorKind::ConnectionAborted, error.to_string()),
+        ),
+    }
+}
+
+//A server reached over SSH. Only keys are used to log in, from the SSH agent or the files
+//`~/.ssh/config` points to, and the server has to be in `~/.ssh/known_hosts` already.
+pub struct Sftp {
+    //The session has to outlive the SFTP channel.
+    _session: Session,
+    sftp: ssh2::Sftp,
+}
+
+impl Sftp {
+    //`authority` is `user@host:port`, the user and port are optional.
+    pub fn connect(authority: &str) -> io::Result<Self> {
+        let invalid = || io::Error::new(io::ErrorKind::InvalidInput, "Invalid server address");
+        let url = Url::parse(&format!("sftp://{authority}")).map_err(|_| invalid())?;
+        let host = url.host_str().ok_or_else(invalid)?;
+        let config = Config::load(host);
+
+        let host_name = config.host_name.as_deref().unwrap_or(host);
+        let port = url.port().or(config.port).unwrap_or(22);
+        let user = match (url.username(), &config.user) {
+            ("", Some(user)) => user.clone(),
+            ("", None) => env::var("USER")
+                .or_else(|_| env::var("USERNAME"))
+                .map_err(|_| invalid())?,
+            (user, _) => user.to_string(),
+        };
+
+        let address = (host_name, port)
+            .to_socket_addrs()?
+            .next()
+            .ok_or_else(invalid)?;
+        let stream = TcpStream::connect_timeout(&address, TIMEOUT)?;
+        let mut session = Session::new()?;
+        session.set_tcp_stream(stream);
+        session.set_timeout(TIMEOUT.as_millis() as u32);
+        session.handshake()?;
+
+        Sftp::verify(&session, host_name, port)?;
+
+        //The agent goes first, then every key file that exists.
+        if session.userauth_agent(&user).is_err() {
+            let mut files = config.identity_files;
+            if let Some(ssh) = dirs::home_dir().map(|home| home.join(".ssh")) {
+                files.extend(["id_ed25519", "id_ecdsa", "id_rsa"].map(|name| ssh.join(name)));
+            }
+            for file in files.iter().filter(|file| file.exists()) {
+                if session
+                    .userauth_pubkey_file(&user, None, file, None)
+                    .is_ok()
+                {
+                    break;
+                }
+            }
+        }
+        if !session.authenticated() {
+            return Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("No key was accepted for {user}@{host_name}"),
+            ));
+        }
+
+        let sftp = session.sftp()?;
+        Ok(Self {
+            _session: session,
+            sftp,
+        })
+    }
+
+    fn verify(session: &Session, host: &str, port: u16) -> io::Result<()> {
+        let (key, _) = session
+            .host_key()
+            .ok_or_else(|| io::Error::other("The server didn't send a host key"))?;
+        let mut known_hosts = session.known_hosts()?;
+        if let Some(home) = dirs::home_dir() {
+            let _ = known_hosts.read_file(
+                &home.join(".ssh").join("known_hosts"),
+                KnownHostFileKind::OpenSSH,
+            );
+        }
+        match known_hosts.check_port(host, port, key) {
+            CheckResult::Match => Ok(()),
+            CheckResult::Mismatch => Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("The host key for {host} has changed"),
+            )),
+            _ => Err(io::Error::new(
+                io::ErrorKind::PermissionDenied,
+                format!("{host} isn't a known host, connect with ssh once to trust it"),
+            )),
+        }
+    }
+}
+
+impl Vfs for Sftp {
+    fn list(&self, dir: &Path) -> Result<Vec<(PathBuf, Metadata)>> {
+        let entries = self.sftp.readdir(dir).map_err(|e| error(dir, e))?;
+        Ok(entries
+            .into_iter()
+            .map(|(path, stat)| {
+                //Links are followed like they are on the local disk.
+                let stat = match stat.file_type().is_symlink() {
+                    true => self.sftp.stat(&path).unwrap_or(stat),
+                    false => stat,
+                };
+                (path, stat.into())
+            })
+            .collect())
+    }
+
+    fn stat(&self, path: &Path) -> Result<Metadata> {
+        let stat = self.sftp.stat(path).map_err(|e| error(path, e))?;
+        Ok(stat.into())
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        let mut file = self.sftp.open(path).map_err(|e| error(path, e))?;
+        let mut data = Vec::new();
+        file.read_to_end(&mut data)
+            .map_err(|e| Error::io(path, e))?;
+        Ok(data)
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> Result<()> {
+        let mut file = self.sftp.create(path).map_err(|e| error(path, e))?;
+        file.write_all(data).map_err(|e| Error::io(path, e))
+    }
+
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send>> {
+        let file = self.sftp.open(path).map_err(|e| error(path, e))?;
+        Ok(Box::new(file))
+    }
+
+    fn write_from(&self, path: &Path, reader: &mut dyn Read, _: u64) -> Result<()> {
+        let mut file = self.sftp.create(path).map_err(|e| error(path, e))?;
+        io::copy(reader, &mut file).map_err(|e| Error::io(path, e))?;
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        if self.sftp.lstat(to).is_ok() {
+            return Err(Error::AlreadyExists(to.to_path_buf()));
+        }
+        self.sftp
+            .rename(from, to, Some(RenameFlags::ATOMIC | RenameFlags::NATIVE))
+            .map_err(|e| error(from, e))
+    }
+
+    fn remove(&self, path: &Path) -> Result<()> {
+        //Links are removed, never followed.
+        let stat = self.sftp.lstat(path).map_err(|e| error(path, e))?;
+        if !stat.is_dir() {
+            return self.sftp.unlink(path).map_err(|e| error(path, e));
+        }
+        for (child, _) in self.sftp.readdir(path).map_err(|e| error(path, e))? {
+            self.remove(&child)?;
+        }
+        self.sftp.rmdir(path).map_err(|e| error(path, e))
+    }
+
+    fn mkdir(&self, path: &Path) -> Result<()> {
+        self.sftp.mkdir(path, 0o755).map_err(|e| error(path, e))
+    }
+
+    //SFTP can't copy on the server, so everything comes down and goes back up.
+    fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        if to.starts_with(from) {
+            return Err(Error::io(
+                to,
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Can't copy a folder into itself",
+                ),
+            ));
+        }
+        transfer(self, from, self, to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn wildcards() {
+        assert!(wildcard(b"*", b"box"));
+        assert!(wildcard(b"*.example.com", b"git.EXAMPLE.com"));
+        assert!(wildcard(b"bo?", b"box"));
+        assert!(!wildcard(b"bo?", b"bo"));
+        assert!(!wildcard(b"*.example.com", b"example.com"));
+
+        assert!(matches("dev-* !dev-secret", "dev-box"));
+        assert!(!matches("dev-* !dev-secret", "dev-secret"));
+        assert!(!matches("!dev-secret", "box"));
+    }
+
+    #[test]
+    fn config() {
+        let text = "
+            # Comment
+            Host box
+                HostName %h.example.com
+                Port=2222
+                IdentityFile ~/.ssh/box
+
+            Match user root
+                User nobody
+
+            Host *
+                User me
+                Port 22
+                IdentityFile \"/keys/default\"
+        ";
+        let home = Path::new("/home/me");
+
+        let config = Config::parse(text, "box", home);
+        assert_eq!(config.host_name.as_deref(), Some("box.example.com"));
+        assert_eq!(config.user.as_deref(), Some("me"));
+        //The first value wins.
+        assert_eq!(config.port, Some(2222));
+        assert_eq!(
+            config.identity_files,
+            [home.join(".ssh/box"), PathBuf::from("/keys/default")]
+        );
+
+        let config = Config::parse(text, "other", home);
+        assert_eq!(config.host_name, None);
+        assert_eq!(config.port, Some(22));
+        assert_eq!(config.identity_files, [PathBuf::from("/keys/default")]);
+    }
+}
@@ -0,0 +1,240 @@
+use super::{
+    remote::{encode, http_error, Http},
+    Kind, Metadata, Vfs,
+};
+use crate::{Error, Result};
+use chrono::DateTime;
+use percent_encoding::percent_decode_str;
+use std::{
+    io::{self, Read},
+    path::{Component, Path, PathBuf},
+    time::{Duration, UNIX_EPOCH},
+};
+use url::Url;
+
+const PROPFIND: &str = r#"<?xml version="1.0" encoding="utf-8"?>
+<d:propfind xmlns:d="DAV:">
+  <d:prop><d:resourcetype/><d:getcontentlength/><d:getlastmodified/></d:prop>
+</d:propfind>"#;
+
+//Responses come with namespaces, which are ignored since only `DAV:` is asked for.
+fn find<'a, 'input>(
+    node: roxmltree::Node<'a, 'input>,
+    name: &str,
+) -> Option<roxmltree::Node<'a, 'input>> {
+    node.descendants()
+        .find(|node| node.tag_name().name() == name)
+}
+
+//A WebDAV share. Paths are relative to `base`, which is usually a user's home on the server.
+pub struct WebDav {
+    http: Http,
+    base: Url,
+    //Sent as basic auth when there is one.
+    authorization: Option<String>,
+}
+
+impl WebDav {
+    pub fn connect(base: &str, user: &str, password: Option<String>) -> io::Result<Self> {
+        let mut base = Url::parse(base)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidInput, e.to_string()))?;
+        if !base.path().ends_with('/') {
+            base.set_path(&format!("{}/", base.path()));
+        }
+        let authorization = (!user.is_empty()).then(|| {
+            use base64::Engine;
+            let credentials = format!("{user}:{}", password.unwrap_or_default());
+            let encoded = base64::engine::general_purpose::STANDARD.encode(credentials);
+            format!("Basic {encoded}")
+        });
+
+        let dav = Self {
+            http: Http::new(),
+            base,
+            authorization,
+        };
+        //Fails early if the share isn't there or won't let us in.
+        dav.propfind(Path::new("/"), "0")?;
+        Ok(dav)
+    }
+
+    fn url(&self, path: &Path, dir: bool) -> String {
+        let mut url = self.base.as_str().trim_end_matches('/').to_string();
+        for component in path.components() {
+            if let Component::Normal(name) = component {
+                url.push('/');
+                url.push_str(&encode(&name.to_string_lossy()));
+            }
+        }
+        if dir || url.len() < self.base.as_str().len() {
+            url.push('/');
+        }
+        url
+    }
+
+    fn request(&self, method: &str, path: &Path, dir: bool) -> ureq::Request {
+        let request = self.http.agent.request(method, &self.url(path, dir));
+        match &self.authorization {
+            Some(authorization) => request.set("Authorization", authorization),
+            None => request,
+        }
+    }
+
+    //Where `href` from a response is on the share.
+    fn path(&self, href: &str) -> PathBuf {
+        //Some servers send full urls, others just the path.
+        let href = match Url::parse(href) {
+            Ok(url) => url.path().to_string(),
+            Err(_) => href.to_string(),
+        };
+        let href = href
+            .strip_prefix(self.base.path())
+            .or_else(|| href.strip_prefix(self.base.path().trim_end_matches('/')))
+            .unwrap_or(&href);
+        let href = percent_decode_str(href).decode_utf8_lossy();
+        Path::new("/").join(href.trim_matches('/'))
+    }
+
+    fn propfind(&self, path: &Path, depth: &str) -> io::Result<Vec<(PathBuf, Metadata)>> {
+        //Folders are asked for with a trailing slash, otherwise some servers redirect.
+        let response = self
+            .request("PROPFIND", path, depth != "0")
+            .set("Depth", depth)
+            .set("Content-Type", "application/xml")
+            .send_string(PROPFIND)
+            .map_err(http_error)?;
+        let text = response.into_string()?;
+        let document = roxmltree::Document::parse(&text)
+            .map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e.to_string()))?;
+
+        Ok(document
+            .descendants()
+            .filter(|node| node.tag_name().name() == "response")
+            .filter_map(|response| {
+                let href = find(response, "href")?.text()?;
+                let dir = find(response, "resourcetype")
+                    .and_then(|kind| find(kind, "collection"))
+                    .is_some();
+                let len = find(response, "getcontentlength")
+                    .and_then(|len| len.text()?.trim().parse().ok())
+                    .unwrap_or(0);
+                let modified = find(response, "getlastmodified")
+                    .and_then(|date| DateTime::parse_from_rfc2822(date.text()?.trim()).ok())
+                    .and_then(|date| u64::try_from(date.timestamp()).ok())
+                    .map(|secs| UNIX_EPOCH + Duration::from_secs(secs));
+                let metadata = Metadata {
+                    kind: if dir { Kind::Dir } else { Kind::File },
+                    len: if dir { 0 } else { len },
+                    modified,
+                };
+                Some((self.path(href), metadata))
+            })
+            .collect())
+    }
+
+    //MOVE and COPY never overwrite, which servers answer with 412.
+    fn transfer(&self, method: &str, from: &Path, to: &Path) -> Result<()> {
+        let dir = self.stat(from)?.is_dir();
+        self.request(method, from, dir)
+            .set("Destination", &self.url(to, dir))
+            .set("Overwrite", "F")
+            .set("Depth", "infinity")
+            .call()
+            .map_err(|e| match e {
+                ureq::Error::Status(412, _) => Error::AlreadyExists(to.to_path_buf()),
+                e => Error::io(from, http_error(e)),
+            })?;
+        Ok(())
+    }
+}
+
+impl Vfs for WebDav {
+    fn list(&self, dir: &Path) -> Result<Vec<(PathBuf, Metadata)>> {
+        let mut entries = self.propfind(dir, "1").map_err(|e| Error::io(dir, e))?;
+        //The folder itself is always part of the response.
+        entries.retain(|(path, _)| path != dir);
+        Ok(entries)
+    }
+
+    fn stat(&self, path: &Path) -> Result<Metadata> {
+        self.propfind(path, "0")
+            .map_err(|e| Error::io(path, e))?
+            .into_iter()
+            .next()
+            .map(|(_, metadata)| metadata)
+            .ok_or_else(|| Error::NotFound(path.to_path_buf()))
+    }
+
+    fn read(&self, path: &Path) -> Result<Vec<u8>> {
+        let response = self
+            .request("GET", path, false)
+            .call()
+            .map_err(|e| Error::io(path, http_error(e)))?;
+        let mut data = Vec::new();
+        response
+            .into_reader()
+            .read_to_end(&mut data)
+            .map_err(|e| Error::io(path, e))?;
+        Ok(data)
+    }
+
+    fn write(&self, path: &Path, data: &[u8]) -> Result<()> {
+        self.request("PUT", path, false)
+            .send_bytes(data)
+            .map_err(|e| Error::io(path, http_error(e)))?;
+        Ok(())
+    }
+
+    fn open_read(&self, path: &Path) -> Result<Box<dyn Read + Send>> {
+        let response = self
+            .request("GET", path, false)
+            .call()
+            .map_err(|e| Error::io(path, http_error(e)))?;
+        Ok(response.into_reader())
+    }
+
+    fn write_from(&self, path: &Path, reader: &mut dyn Read, len: u64) -> Result<()> {
+        //With a length the body is streamed as is instead of chunked, which not every server takes.
+        self.request("PUT", path, false)
+            .set("Content-Length", &len.to_string())
+            .send(reader)
+            .map_err(|e| Error::io(path, http_error(e)))?;
+        Ok(())
+    }
+
+    fn rename(&self, from: &Path, to: &Path) -> Result<()> {
+        self.transfer("MOVE", from, to)
+    }
+
+    fn remove(&self, path: &Path) -> Result<()> {
+        let dir = self.stat(path)?.is_dir();
+        self.request("DELETE", path, dir)
+            .call()
+            .map_err(|e| Error::io(path, http_error(e)))?;
+        Ok(())
+    }
+
+    fn mkdir(&self, path: &Path) -> Result<()> {
+        //405 is how servers say something is already there.
+        self.request("MKCOL", path, true)
+            .call()
+            .map_err(|e| match e {
+                ureq::Error::Status(405, _) => Error::AlreadyExists(path.to_path_buf()),
+                e => Error::io(path, http_error(e)),
+            })?;
+        Ok(())
+    }
+
+    fn copy(&self, from: &Path, to: &Path) -> Result<()> {
+        if to.starts_with(from) {
+            return Err(Error::io(
+                to,
+                io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "Can't copy a folder into itself",
+                ),
+            ));
+        }
+        self.transfer("COPY", from, to)
+    }
+}